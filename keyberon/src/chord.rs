@@ -1,15 +1,14 @@
 //! Module for chords v2 implementation.
 
 use std::cell::Cell;
+use std::collections::VecDeque;
 
-use arraydeque::ArrayDeque;
-use heapless::Vec as HVec;
 use rustc_hash::FxHashMap;
 
 use crate::{
     action::Action,
     key_code::KEY_MAX,
-    layout::{Event, Queue, Queued, QueuedAction},
+    layout::{Event, Queued, QueuedAction},
 };
 
 // Macro to help with this boilerplate.
@@ -45,6 +44,10 @@ pub struct ChordV2<'a, T> {
     pub disabled_layers: &'a [u16],
     /// When should the action for this chord be released.
     pub release_behaviour: ReleaseBehaviour,
+    /// The number of ticks during which no other key may have been pressed
+    /// before the first participant for this chord to be activatable.
+    /// A value of zero means there is no idle requirement.
+    pub prior_idle: u16,
 }
 
 #[derive(Debug, Clone)]
@@ -58,7 +61,8 @@ pub struct ChordsForKeys<'a, T> {
     pub mapping: FxHashMap<u16, ChordsForKey<'a, T>>,
 }
 
-const SMOL_Q_LEN: usize = 16;
+/// Default capacity of the queue of inputs that may still activate a chord.
+pub const DEFAULT_CHORDS_V2_QUEUE_LEN: usize = 32;
 
 struct ActiveChord<'a, T> {
     /// Chords uses a virtual coordinate in the keyberon state for an activated chord.
@@ -66,7 +70,7 @@ struct ActiveChord<'a, T> {
     coordinate: u16,
     /// Keys left to release.
     /// For OnFirstRelease, this should have length 0.
-    remaining_keys_to_release: HVec<u16, SMOL_Q_LEN>,
+    remaining_keys_to_release: Vec<u16>,
     /// Necessary to include here make sure that, for OnFirstRelease,
     /// random other releases that are not part of this chord,
     /// do not release this chord.
@@ -101,8 +105,8 @@ enum ActiveChordStatus {
 }
 use ActiveChordStatus::*;
 
-/// Like the layout Queue but smaller.
-pub(crate) type SmolQueue = ArrayDeque<Queued, SMOL_Q_LEN, arraydeque::behavior::Wrapping>;
+/// Inputs leaving chords processing, to be handled by the layout.
+pub(crate) type DrainQueue = VecDeque<Queued>;
 
 /// Global input chords configuration.
 pub struct ChordsV2<'a, T> {
@@ -112,11 +116,14 @@ pub struct ChordsV2<'a, T> {
     /// Queued inputs that can potentially activate a chord but have not yet.
     /// Inputs will leave if they are determined that they will not activate a chord,
     /// or if a chord activates.
-    queue: Queue,
+    queue: VecDeque<Queued>,
+    /// Maximum length of the queue above.
+    /// When full, the oldest input is forced out of chords processing.
+    queue_capacity: usize,
     /// Information about what chords are possible and what keys they are associated with.
     chords: ChordsForKeys<'a, T>,
     /// Chords that are active, i.e. ones that have not yet been released.
-    active_chords: HVec<ActiveChord<'a, T>, 10>,
+    active_chords: Vec<ActiveChord<'a, T>>,
     /// When a key leaves the combo queue without activating a chord,
     /// this activates a timer during which keys cannot activate chords
     /// and are always forwarded directly to the standard input queue.
@@ -136,9 +143,14 @@ pub struct ChordsV2<'a, T> {
     prev_active_layer: u16,
     /// Optimization: the below is part of skipping processing work - if this is has changed,
     /// then processing work cannot be skipped.
-    prev_queue_len: u8,
+    prev_queue_len: usize,
     /// Virtual coordinate for use in the layout state.
     next_coord: Cell<u16>,
+    /// Ticks elapsed since the most recent real key press.
+    ticks_since_last_press: u16,
+    /// For each key, the number of idle ticks that preceded its most recent press.
+    /// Used to check the `prior_idle` requirement of chords.
+    prior_idle_of_press: FxHashMap<u16, u16>,
 }
 
 impl<'a, T> std::fmt::Debug for ChordsV2<'a, T> {
@@ -148,18 +160,22 @@ impl<'a, T> std::fmt::Debug for ChordsV2<'a, T> {
 }
 
 impl<'a, T> ChordsV2<'a, T> {
-    pub fn new(chords: ChordsForKeys<'a, T>, ticks_ignore_chord: u16) -> Self {
+    pub fn new(chords: ChordsForKeys<'a, T>, ticks_ignore_chord: u16, queue_len: usize) -> Self {
         assert!(ticks_ignore_chord >= 5);
+        assert!(queue_len > 0);
         Self {
-            queue: Queue::new(),
+            queue: VecDeque::with_capacity(queue_len),
+            queue_capacity: queue_len,
             chords,
-            active_chords: HVec::new(),
+            active_chords: Vec::new(),
             ticks_to_ignore_chord: 0,
             configured_ticks_to_ignore_chord: ticks_ignore_chord,
             ticks_until_next_state_change: 0,
             prev_active_layer: u16::MAX,
-            prev_queue_len: u8::MAX,
+            prev_queue_len: usize::MAX,
             next_coord: Cell::new(KEY_MAX + 1),
+            ticks_since_last_press: u16::MAX,
+            prior_idle_of_press: FxHashMap::default(),
         }
     }
 
@@ -171,8 +187,21 @@ impl<'a, T> ChordsV2<'a, T> {
         self.ticks_to_ignore_chord == 0
     }
 
+    /// Adds an event to the chords queue.
+    /// If the queue is full, the oldest event is removed and returned.
     pub fn push_back_chv2(&mut self, item: Queued) -> Option<Queued> {
-        self.queue.push_back(item)
+        if let Event::Press(0, j) = item.event {
+            self.prior_idle_of_press
+                .insert(j, self.ticks_since_last_press);
+            self.ticks_since_last_press = 0;
+        }
+        let overflow = if self.queue.len() >= self.queue_capacity {
+            self.queue.pop_front()
+        } else {
+            None
+        };
+        self.queue.push_back(item);
+        overflow
     }
 
     pub fn chords(&self) -> &ChordsForKeys<'a, T> {
@@ -198,8 +227,8 @@ impl<'a, T> ChordsV2<'a, T> {
 
     /// Update the times in the queue without activating any chords yet.
     /// Returns queued events that are no longer usable in chords.
-    pub(crate) fn tick_chv2(&mut self, active_layer: u16) -> SmolQueue {
-        let mut q = SmolQueue::new();
+    pub(crate) fn tick_chv2(&mut self, active_layer: u16) -> DrainQueue {
+        let mut q = DrainQueue::new();
         self.queue.iter_mut().for_each(Queued::tick_qd);
         let prev_active_chord_len = self.active_chords.len();
         self.active_chords.iter_mut().for_each(tick_ach);
@@ -229,27 +258,34 @@ impl<'a, T> ChordsV2<'a, T> {
         }
         self.clear_released_chords(&mut q);
         self.ticks_to_ignore_chord = self.ticks_to_ignore_chord.saturating_sub(1);
+        self.ticks_since_last_press = self.ticks_since_last_press.saturating_add(1);
         q
     }
 
     fn next_coord(&self) -> u16 {
-        let ret = self.next_coord.get();
-        let mut new = ret + 1;
-        if new > KEY_MAX + 50 {
-            new = KEY_MAX + 1;
+        // Skip over coordinates still in use by active chords.
+        // There are always fewer active chords than available coordinates.
+        loop {
+            let ret = self.next_coord.get();
+            let new = match ret.checked_add(1) {
+                Some(new) => new,
+                None => KEY_MAX + 1,
+            };
+            self.next_coord.set(new);
+            if !self.active_chords.iter().any(|ach| ach.coordinate == ret) {
+                return ret;
+            }
         }
-        self.next_coord.set(new);
-        ret
     }
 
-    fn drain_inputs(&mut self, drainq: &mut SmolQueue, active_layer: u16) {
+    fn drain_inputs(&mut self, drainq: &mut DrainQueue, active_layer: u16) {
         if self.ticks_to_ignore_chord > 0 {
             drainq.extend(self.queue.drain(0..));
             return;
         }
         if self.ticks_until_next_state_change > 0
             && self.prev_active_layer == active_layer
-            && usize::from(self.prev_queue_len) == self.queue.len()
+            && self.prev_queue_len == self.queue.len()
         {
             self.ticks_until_next_state_change =
                 self.ticks_until_next_state_change.saturating_sub(1);
@@ -257,36 +293,33 @@ impl<'a, T> ChordsV2<'a, T> {
         }
         self.ticks_until_next_state_change = 0;
         self.prev_active_layer = active_layer;
-        debug_assert!(self.queue.capacity() < 255);
-        self.prev_queue_len = self.queue.len() as u8;
+        self.prev_queue_len = self.queue.len();
 
         self.drain_virtual_keys(drainq);
         self.drain_releases(drainq);
         self.process_presses(active_layer);
     }
 
-    fn drain_virtual_keys(&mut self, drainq: &mut SmolQueue) {
+    fn drain_virtual_keys(&mut self, drainq: &mut DrainQueue) {
         self.queue.retain(|qd| {
             match qd.event {
                 // Only row 0 is real inputs.
                 // Drain other rows (at the time of writing should only be index 1).
                 Event::Press(0, _) | Event::Release(0, _) => true,
                 _ => {
-                    let overflow = drainq.push_back(*qd);
-                    assert!(overflow.is_none(), "oops overflowed drain queue");
+                    drainq.push_back(*qd);
                     false
                 }
             }
         });
     }
 
-    fn drain_releases(&mut self, drainq: &mut SmolQueue) {
+    fn drain_releases(&mut self, drainq: &mut DrainQueue) {
         let achs = &mut self.active_chords;
-        let mut presses = HVec::<_, SMOL_Q_LEN>::new();
+        let mut presses = Vec::new();
         self.queue.retain(|qd| match qd.event {
             Event::Press(_, j) => {
-                let overflow = presses.push(j);
-                debug_assert!(overflow.is_ok());
+                presses.push(j);
                 true
             }
             Event::Release(_, j) => {
@@ -314,13 +347,12 @@ impl<'a, T> ChordsV2<'a, T> {
     }
 
     fn process_presses(&mut self, active_layer: u16) {
-        let mut presses = HVec::<u16, SMOL_Q_LEN>::new();
+        let mut presses = Vec::<u16>::with_capacity(self.queue.len());
         let mut relevant_release_found = false;
         for qd in self.queue.iter() {
             match qd.event {
                 Event::Press(_, j) => {
-                    let overflowed = presses.push(j);
-                    debug_assert!(overflowed.is_ok(), "too many presses in queue");
+                    presses.push(j);
                 }
                 Event::Release(_, j) => {
                     if presses.contains(&j) {
@@ -338,6 +370,11 @@ impl<'a, T> ChordsV2<'a, T> {
             no_chord_activations!(self);
             return;
         };
+        let prior_idle = self
+            .prior_idle_of_press
+            .get(starting_press)
+            .copied()
+            .unwrap_or(u16::MAX);
 
         // For subsequent keypresses,
         // all must fit into a single chord for chord state to remain pending
@@ -347,8 +384,8 @@ impl<'a, T> ChordsV2<'a, T> {
         // Prioritization of chord activation:
         // 1. Timed out chord
         // 2. Longer chord
-        let mut accumulated_presses = HVec::<u16, SMOL_Q_LEN>::new();
-        let mut chord_candidates = HVec::<&ChordV2<'a, T>, SMOL_Q_LEN>::new();
+        let mut accumulated_presses = Vec::<u16>::with_capacity(presses.len());
+        let mut chord_candidates = Vec::<&ChordV2<'a, T>>::new();
         let mut timed_out_chord = Option::<(&ChordV2<'a, T>, u8)>::default();
         let mut prev_count = usize::MAX;
         let mut min_timeout;
@@ -358,9 +395,7 @@ impl<'a, T> ChordsV2<'a, T> {
 
         for press in presses.iter().copied() {
             min_timeout = u16::MAX;
-            accumulated_presses
-                .push(press)
-                .expect("accpresses same len as presses");
+            accumulated_presses.push(press);
 
             let count_possible = if prev_count == chord_candidates.len() {
                 // optimization: no longer need to check the whole list.
@@ -389,7 +424,7 @@ impl<'a, T> ChordsV2<'a, T> {
                 possible_chords
                     .chords
                     .iter()
-                    .filter(|pch| !pch.disabled_layers.contains(&active_layer))
+                    .filter(|pch| chord_is_enabled(pch, active_layer, prior_idle))
                     .filter(|pch| {
                         if accumulated_presses
                             .iter()
@@ -404,9 +439,7 @@ impl<'a, T> ChordsV2<'a, T> {
                                 // this should only happen at most once per iteration due to needing an exact match.
                                 timed_out_chord = Some((pch, accumulated_presses.len() as u8));
                             }
-                            // If full, can't run the optimization above, but not fatal.
-                            // Can ignore the overflow.
-                            let _overflow = chord_candidates.push(pch);
+                            chord_candidates.push(pch);
                             min_timeout = std::cmp::min(min_timeout, pch.pending_duration);
                             true
                         } else {
@@ -428,8 +461,7 @@ impl<'a, T> ChordsV2<'a, T> {
                         .all(|pk| accumulated_presses.contains(pk))
                     {
                         let ach = get_active_chord(cch, since, coord, relevant_release_found);
-                        let overflow = self.active_chords.push(ach);
-                        assert!(overflow.is_ok(), "active chords has room");
+                        self.active_chords.push(ach);
                         break;
                     }
                 }
@@ -444,7 +476,7 @@ impl<'a, T> ChordsV2<'a, T> {
                     let completed_chord = possible_chords
                         .chords
                         .iter()
-                        .filter(|pch| !pch.disabled_layers.contains(&active_layer))
+                        .filter(|pch| chord_is_enabled(pch, active_layer, prior_idle))
                         .find(
                             // Ensure the two lists have the same set of keys
                             |pch| {
@@ -461,8 +493,7 @@ impl<'a, T> ChordsV2<'a, T> {
                         Some(cch) => {
                             let coord = self.next_coord();
                             let ach = get_active_chord(cch, since, coord, relevant_release_found);
-                            let overflow = self.active_chords.push(ach);
-                            assert!(overflow.is_ok(), "active chords has room");
+                            self.active_chords.push(ach);
                         }
                         None => no_chord_activations!(self),
                     }
@@ -476,46 +507,26 @@ impl<'a, T> ChordsV2<'a, T> {
         if self.ticks_until_next_state_change == 0 || relevant_release_found {
             // Find a chord that matches exactly and activate that,
             // otherwise clear the input queue.
-            let completed_chord = if chord_candidates.is_full() {
-                possible_chords
-                    .chords
-                    .iter()
-                    .filter(|pch| !pch.disabled_layers.contains(&active_layer))
-                    .find(
-                        // Ensure the two lists have the same set of keys
-                        |pch| {
-                            accumulated_presses
-                                .iter()
-                                .all(|acp| pch.participating_keys.contains(acp))
-                                && pch
-                                    .participating_keys
-                                    .iter()
-                                    .all(|pk| accumulated_presses.contains(pk))
-                        },
-                    )
-            } else {
-                chord_candidates
-                    .iter()
-                    .filter(|pch| !pch.disabled_layers.contains(&active_layer))
-                    .find(
-                        // Ensure the two lists have the same set of keys
-                        |pch| {
-                            accumulated_presses
+            let completed_chord = chord_candidates
+                .iter()
+                .filter(|pch| chord_is_enabled(pch, active_layer, prior_idle))
+                .find(
+                    // Ensure the two lists have the same set of keys
+                    |pch| {
+                        accumulated_presses
+                            .iter()
+                            .all(|acp| pch.participating_keys.contains(acp))
+                            && pch
+                                .participating_keys
                                 .iter()
-                                .all(|acp| pch.participating_keys.contains(acp))
-                                && pch
-                                    .participating_keys
-                                    .iter()
-                                    .all(|pk| accumulated_presses.contains(pk))
-                        },
-                    )
-            };
+                                .all(|pk| accumulated_presses.contains(pk))
+                    },
+                );
             match completed_chord {
                 Some(cch) => {
                     let ach =
                         get_active_chord(cch, since, self.next_coord(), relevant_release_found);
-                    let overflow = self.active_chords.push(ach);
-                    assert!(overflow.is_ok(), "active chords has room");
+                    self.active_chords.push(ach);
                 }
                 None => {
                     no_chord_activations!(self)
@@ -526,8 +537,7 @@ impl<'a, T> ChordsV2<'a, T> {
         if let Some((chord, consumed_presses)) = timed_out_chord {
             self.queue.drain(0..usize::from(consumed_presses));
             let ach = get_active_chord(chord, since, self.next_coord(), relevant_release_found);
-            let overflow = self.active_chords.push(ach);
-            assert!(overflow.is_ok(), "active chords has room");
+            self.active_chords.push(ach);
         }
 
         // Clear presses from the queue if they were consumed by a chord.
@@ -539,14 +549,13 @@ impl<'a, T> ChordsV2<'a, T> {
         }
     }
 
-    fn clear_released_chords(&mut self, drainq: &mut SmolQueue) {
+    fn clear_released_chords(&mut self, drainq: &mut DrainQueue) {
         self.active_chords.retain(|ach| {
             if ach.status == Released {
-                let overflow = drainq.push_back(Queued {
+                drainq.push_back(Queued {
                     event: Event::Release(0, ach.coordinate),
                     since: 0,
                 });
-                assert!(overflow.is_none(), "oops overflowed drain queue");
                 false
             } else {
                 true
@@ -555,13 +564,17 @@ impl<'a, T> ChordsV2<'a, T> {
     }
 }
 
+fn chord_is_enabled<T>(cch: &ChordV2<T>, active_layer: u16, prior_idle: u16) -> bool {
+    !cch.disabled_layers.contains(&active_layer) && cch.prior_idle <= prior_idle
+}
+
 fn get_active_chord<'a, T>(
     cch: &ChordV2<'a, T>,
     since: u16,
     coord: u16,
     release_found: bool,
 ) -> ActiveChord<'a, T> {
-    let mut remaining_keys_to_release = HVec::new();
+    let mut remaining_keys_to_release = vec![];
     if cch.release_behaviour == ReleaseBehaviour::OnLastRelease {
         remaining_keys_to_release.extend(cch.participating_keys.iter().copied());
     };
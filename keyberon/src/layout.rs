@@ -111,6 +111,14 @@ where
     pub historical_inputs: History<KCoord>,
     pub quick_tap_hold_timeout: bool,
     pub chords_v2: Option<ChordsV2<'a, T>>,
+    /// Coordinate of the most recently activated chordsv2 chord and the number of trigger presses
+    /// its activation added to the queue.
+    chord_activation_triggers: Option<(KCoord, u8)>,
+    /// Keys that output their shifted variant when held past a timeout.
+    pub autoshift: Option<AutoShift<'a>>,
+    autoshift_pending: Option<AutoShiftPending<'a, T>>,
+    /// Release of a tapped auto-shift key, which is processed on the tick after its press.
+    autoshift_release: Option<Queued>,
     rpt_multikey_key_buffer: MultiKeyBuffer<'a, T>,
     trans_resolution_behavior_v2: bool,
     delegate_to_first_layer: bool,
@@ -374,6 +382,23 @@ impl<'a, T: 'a> State<'a, T> {
     }
 }
 
+/// Configuration of auto-shift.
+#[derive(Copy, Clone, Debug)]
+pub struct AutoShift<'a> {
+    /// Number of ticks a key must be held to output the shifted variant.
+    pub timeout: u16,
+    pub keys: &'a [KeyCode],
+}
+
+/// A press of an auto-shift key whose output is not decided yet.
+#[derive(Copy, Clone, Debug)]
+struct AutoShiftPending<'a, T: 'a> {
+    coord: KCoord,
+    action: &'a Action<'a, T>,
+    /// Ticks since the key was pressed.
+    ticks: u16,
+}
+
 #[derive(Copy, Clone, Debug)]
 struct TapDanceState<'a, T: 'a> {
     actions: &'a [&'a Action<'a, T>],
@@ -428,6 +453,10 @@ pub struct WaitingState<'a, T: 'a + std::fmt::Debug> {
     config: WaitingConfig<'a, T>,
     layer_stack: LayerStack,
     prev_queue_len: QueueLen,
+    /// Number of chordsv2 trigger presses at the front of the queue that were caused by the
+    /// activation of the chord owning this waiting state. These must not count as other key
+    /// presses, otherwise a tap-hold chord action would resolve as soon as it activates.
+    own_chord_triggers: u8,
 }
 
 /// Actions that can be triggered for a key configured for HoldTap.
@@ -495,15 +524,25 @@ impl<'a, T: std::fmt::Debug> WaitingState<'a, T> {
         }
         self.prev_queue_len = queued.len() as u8;
         let mut skip_timeout = false;
+        let mut triggers_to_skip = self.own_chord_triggers;
+        let trigger_press = Event::Press(TRIGGER_TAPHOLD_COORD.0, TRIGGER_TAPHOLD_COORD.1);
+        let other_events = queued.iter().filter(move |q| {
+            if triggers_to_skip > 0 && q.event == trigger_press {
+                triggers_to_skip -= 1;
+                false
+            } else {
+                true
+            }
+        });
         match cfg {
             HoldTapConfig::Default => (),
             HoldTapConfig::HoldOnOtherKeyPress => {
-                if queued.iter().any(|s| s.event.is_press()) {
+                if other_events.clone().any(|s| s.event.is_press()) {
                     return Some(WaitingAction::Hold);
                 }
             }
             HoldTapConfig::PermissiveHold => {
-                let mut queued = queued.iter();
+                let mut queued = other_events;
                 while let Some(q) = queued.next() {
                     if q.event.is_press() {
                         let (i, j) = q.event.coord();
@@ -1085,6 +1124,10 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
             trans_resolution_behavior_v2: true,
             delegate_to_first_layer: false,
             chords_v2: None,
+            chord_activation_triggers: None,
+            autoshift: None,
+            autoshift_pending: None,
+            autoshift_release: None,
         }
     }
     pub fn new_with_trans_action_settings(
@@ -1258,13 +1301,24 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
     pub fn tick(&mut self) -> CustomEvent<'a, T> {
         let active_layer = self.current_layer() as u16;
         if let Some(chv2) = self.chords_v2.as_mut() {
-            self.queue.extend(chv2.tick_chv2(active_layer).drain(0..));
+            let drained = chv2.tick_chv2(active_layer);
             if let (qac @ Some(_), pause_input_processing) = chv2.get_action_chv2() {
+                if let Some((coord, _, _)) = qac {
+                    let trigger = Event::Press(TRIGGER_TAPHOLD_COORD.0, TRIGGER_TAPHOLD_COORD.1);
+                    let triggers = drained.iter().filter(|q| q.event == trigger).count();
+                    self.chord_activation_triggers =
+                        Some((coord, u8::try_from(triggers).unwrap_or(u8::MAX)));
+                }
                 self.action_queue.push_back(qac);
                 if pause_input_processing {
                     self.oneshot.pause_input_processing_ticks = self.oneshot.on_press_release_delay;
                 }
             }
+            for qd in drained {
+                if let Some(overflow) = self.queue.push_back(qd) {
+                    self.handle_queue_overflow(overflow);
+                }
+            }
         }
         if let Some(Some((coord, delay, action))) = self.action_queue.pop_front() {
             // If there's anything in the action queue, don't process anything else yet - execute
@@ -1300,6 +1354,13 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
             }
         }
 
+        if let Some(release) = self.autoshift_release.take() {
+            custom.update(self.dequeue(release));
+        }
+        if let Some(pending) = self.autoshift_pending.as_mut() {
+            pending.ticks = pending.ticks.saturating_add(1);
+        }
+
         custom.update(match &mut self.waiting {
             Some(w) => match w.tick_wt(&mut self.queue, &mut self.action_queue) {
                 Some((WaitingAction::Hold, _)) => self.waiting_into_hold(-1),
@@ -1331,6 +1392,15 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
                 }
             }
         });
+        if let Some(pending) = self.autoshift_pending {
+            // Queued events happened before now. The oldest one decides the output if it was
+            // before the timeout. This is checked after dequeuing so that a queued release of the
+            // key is not processed on the same tick as its press.
+            let since = self.queue.front().map_or(0, |q| q.since);
+            if pending.ticks.saturating_sub(since) >= self.autoshift.map_or(0, |a| a.timeout) {
+                self.resolve_autoshift(0);
+            }
+        }
         let custom = self.process_extra_waitings(custom);
         self.process_sequence_custom(custom)
     }
@@ -1381,12 +1451,10 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
                             self.oneshot.handle_release((0, 0));
                             self.states.retain(|s| s.seq_release(keycode).is_some());
                         }
-                        Some(SequenceEvent::Delay { duration }) => {
-                            // Setup a delay that will be decremented once per tick until 0
-                            if duration > 0 {
-                                // -1 to start since this tick counts
-                                seq.delay = duration - 1;
-                            }
+                        // Setup a delay that will be decremented once per tick until 0
+                        Some(SequenceEvent::Delay { duration }) if duration > 0 => {
+                            // -1 to start since this tick counts
+                            seq.delay = duration - 1;
                         }
                         Some(SequenceEvent::Custom(custom)) => {
                             let _ = self.states.push(State::SeqCustomPending(custom));
@@ -1478,6 +1546,15 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
         use Event::*;
         match queue.event {
             Release(i, j) => {
+                if self
+                    .autoshift_pending
+                    .is_some_and(|pending| pending.coord == (i, j))
+                {
+                    // The release is processed on the next tick so that the press is output.
+                    self.resolve_autoshift(queue.since);
+                    self.autoshift_release = Some(queue);
+                    return CustomEvent::NoEvent;
+                }
                 let mut custom = CustomEvent::NoEvent;
                 let (do_release, overflow_key) = self.oneshot.handle_release((i, j));
                 if do_release {
@@ -1492,6 +1569,8 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
             }
 
             Press(i, j) => {
+                // Pressing another key decides the output of a pending auto-shift key.
+                self.resolve_autoshift(queue.since);
                 let mut layer_stack = self.trans_resolution_layer_order().into_iter();
                 if let Some(tde) = self.tap_dance_eager {
                     if (i, j) == self.last_press_tracker.coord && !tde.is_expired() {
@@ -1512,29 +1591,101 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
                             // unwrap is here because tde cannot be ref mut
                             self.tap_dance_eager.as_mut().expect("some").set_expired();
                         }
-                        self.do_action(&Action::Trans, (i, j), queue.since, false, &mut layer_stack)
+                        self.press_coord((i, j), queue.since, &mut layer_stack)
                     }
                 } else {
-                    self.do_action(&Action::Trans, (i, j), queue.since, false, &mut layer_stack)
+                    self.press_coord((i, j), queue.since, &mut layer_stack)
+                }
+            }
+        }
+    }
+    /// Do the action of a pressed coordinate. If the action is the output of an auto-shift key,
+    /// the output is decided later, by how long the key is held.
+    fn press_coord(
+        &mut self,
+        coord: KCoord,
+        since: u16,
+        layer_stack: &mut (impl Iterator<Item = u16> + Clone),
+    ) -> CustomEvent<'a, T> {
+        if let Some(autoshift) = self.autoshift.filter(|_| coord.0 == REAL_KEY_ROW) {
+            let action = self.resolve_coord(coord, &mut layer_stack.clone());
+            if matches!(action, Action::KeyCode(kc) if autoshift.keys.contains(kc)) {
+                self.autoshift_pending = Some(AutoShiftPending {
+                    coord,
+                    action,
+                    ticks: since,
+                });
+                return CustomEvent::NoEvent;
+            }
+        }
+        self.do_action(&Action::Trans, coord, since, false, layer_stack)
+    }
+    /// Output the pending auto-shift key, shifted if it was held past the timeout `since` ticks
+    /// ago. Shift is cleared on the next action so that it only applies to this key.
+    fn resolve_autoshift(&mut self, since: u16) {
+        let Some(pending) = self.autoshift_pending.take() else {
+            return;
+        };
+        let shifted =
+            pending.ticks.saturating_sub(since) >= self.autoshift.map_or(0, |a| a.timeout);
+        if shifted {
+            // Pushed before the key so that shift is pressed first. The flag is set afterwards
+            // because the key action clears flagged keys.
+            let _ = self.states.push(NormalKey {
+                coord: pending.coord,
+                keycode: KeyCode::LShift,
+                flags: NormalKeyFlags(0),
+            });
+        }
+        // A key code action has no custom event.
+        let _ = self.do_action(
+            pending.action,
+            pending.coord,
+            pending.ticks,
+            false,
+            &mut core::iter::empty(),
+        );
+        if shifted {
+            for state in self.states.iter_mut() {
+                if let NormalKey {
+                    coord,
+                    keycode: KeyCode::LShift,
+                    flags,
+                } = state
+                {
+                    if *coord == pending.coord {
+                        *flags = NormalKeyFlags(NORMAL_KEY_FLAG_CLEAR_ON_NEXT_ACTION);
+                    }
                 }
             }
         }
     }
+    /// Returns true if an auto-shift key is pressed but its output is not decided yet.
+    pub fn is_autoshift_pending(&self) -> bool {
+        self.autoshift_pending.is_some() || self.autoshift_release.is_some()
+    }
     /// Register a key event.
     pub fn event(&mut self, event: Event) {
         if let Event::Press(x, y) = event {
             self.historical_inputs.push_front((x, y));
         }
-        if let Some(overflow) = if let Some(ch) = self.chords_v2.as_mut() {
-            ch.push_back_chv2(event.into())
-        } else {
-            self.queue.push_back(event.into())
-        } {
-            for i in -1..(EXTRA_WAITING_LEN as i8) {
-                self.waiting_into_hold(i);
-            }
-            self.dequeue(overflow);
+        let event = match self.chords_v2.as_mut() {
+            // An event that no longer fits in the chords queue can no longer be part of a chord.
+            // It continues to the layout queue behind the events that were drained before it.
+            Some(ch) => ch.push_back_chv2(event.into()),
+            None => Some(event.into()),
+        };
+        if let Some(overflow) = event.and_then(|event| self.queue.push_back(event)) {
+            self.handle_queue_overflow(overflow);
+        }
+    }
+    /// Handle an event that no longer fits in a queue
+    /// by resolving all waiting states and processing the event immediately.
+    fn handle_queue_overflow(&mut self, overflow: Queued) {
+        for i in -1..(EXTRA_WAITING_LEN as i8) {
+            self.waiting_into_hold(i);
         }
+        self.dequeue(overflow);
     }
     /// Resolve coordinate to first non-Trans actions.
     /// Trans on base layer, resolves to key from defsrc.
@@ -1662,6 +1813,13 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
                         config: WaitingConfig::HoldTap(*config),
                         layer_stack: layer_stack.collect(),
                         prev_queue_len: QueueLen::MAX,
+                        own_chord_triggers: match self.chord_activation_triggers {
+                            Some((chord_coord, triggers)) if chord_coord == coord => {
+                                self.chord_activation_triggers = None;
+                                triggers
+                            }
+                            _ => 0,
+                        },
                     };
                     if self.waiting.is_some() {
                         self.extra_waiting.push_back(waiting);
@@ -1717,6 +1875,7 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
                             }),
                             layer_stack: layer_stack.collect(),
                             prev_queue_len: QueueLen::MAX,
+                            own_chord_triggers: 0,
                         });
                     }
                     TapDanceConfig::Eager => {
@@ -1759,6 +1918,7 @@ impl<'a, const C: usize, const R: usize, T: 'a + Copy + std::fmt::Debug> Layout<
                     config: WaitingConfig::Chord(chords),
                     layer_stack: layer_stack.collect(),
                     prev_queue_len: QueueLen::MAX,
+                    own_chord_triggers: 0,
                 });
             }
             &KeyCode(keycode) => {
@@ -0,0 +1,410 @@
+//! Parses a configuration and keeps what the language server needs from it: the locations of
+//! definitions and references, code that is inactive, the error if any and the aliases' actions.
+
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+
+use kanata_parser::cfg::sexpr::{self, SExpr, Span};
+use kanata_parser::cfg::{parse_cfg_raw_string, FileContentProvider, ParserState};
+use kanata_parser::keys::{oscode_to_str, str_to_oscode, OsCode};
+use kanata_parser::lsp_hints::ReferencesMap;
+use lsp_types::{Position, Range};
+
+#[cfg(target_os = "windows")]
+const DEF_LOCAL_KEYS: &str = "deflocalkeys-win";
+#[cfg(target_os = "macos")]
+const DEF_LOCAL_KEYS: &str = "deflocalkeys-macos";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const DEF_LOCAL_KEYS: &str = "deflocalkeys-linux";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Alias,
+    Variable,
+    VirtualKey,
+    Layer,
+    Template,
+}
+
+pub type Symbol = (SymbolKind, String);
+
+/// A range in a file, in the line and UTF-16 column units of the language server protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loc {
+    pub file: PathBuf,
+    pub range: Range,
+}
+
+impl Loc {
+    fn contains(&self, file: &Path, pos: Position) -> bool {
+        self.file == file && self.range.start <= pos && pos <= self.range.end
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CfgError {
+    /// Location of the error, if the parser knows it.
+    pub loc: Option<Loc>,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub definitions: HashMap<Symbol, Loc>,
+    pub references: HashMap<Symbol, Vec<Loc>>,
+    /// Code that is skipped by the parser, with the reason.
+    pub inactive: Vec<(Loc, String)>,
+    pub error: Option<CfgError>,
+    /// The source expression of every alias, or the kind of its action if the expression is not
+    /// found.
+    pub alias_actions: HashMap<String, String>,
+    /// The text of the files that were parsed, by path.
+    files: HashMap<PathBuf, String>,
+}
+
+impl Analysis {
+    /// Parses the configuration in `text`. Included files are loaded with `read_file`.
+    pub fn new(
+        text: &str,
+        path: &Path,
+        read_file: &mut impl FnMut(&Path) -> Result<String, String>,
+    ) -> Self {
+        let mut analysis = Analysis::default();
+        analysis.files.insert(path.to_path_buf(), text.to_string());
+        let mut s = ParserState::default();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            parse_cfg_raw_string(
+                text,
+                &mut s,
+                path,
+                &mut FileContentProvider::new(&mut |file: &Path| {
+                    let file = resolve_path(path, file);
+                    let content = read_file(&file)?;
+                    analysis.files.insert(file, content.clone());
+                    Ok(content)
+                }),
+                DEF_LOCAL_KEYS,
+                Ok(std::env::vars().collect()),
+            )
+            .map(|_| ())
+        }));
+        analysis.error = match result {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(CfgError {
+                loc: e.span.as_ref().map(|span| span_loc(path, span)),
+                message: e.msg,
+            }),
+            Err(_) => Some(CfgError {
+                loc: None,
+                message: "The parser panicked on this configuration".to_string(),
+            }),
+        };
+
+        let hints = s.lsp_hints.borrow();
+        let defs = &hints.definition_locations;
+        for (kind, locations) in [
+            (SymbolKind::Alias, &defs.alias),
+            (SymbolKind::Variable, &defs.variable),
+            (SymbolKind::VirtualKey, &defs.virtual_key),
+            (SymbolKind::Layer, &defs.layer),
+            (SymbolKind::Template, &defs.template),
+        ] {
+            for (name, span) in locations {
+                analysis
+                    .definitions
+                    .insert((kind, name.clone()), span_loc(path, span));
+            }
+        }
+        let refs = &hints.reference_locations;
+        for (kind, references) in [
+            (SymbolKind::Alias, &refs.alias),
+            (SymbolKind::Variable, &refs.variable),
+            (SymbolKind::VirtualKey, &refs.virtual_key),
+            (SymbolKind::Layer, &refs.layer),
+            (SymbolKind::Template, &refs.template),
+        ] {
+            analysis.add_references(path, kind, references);
+        }
+        analysis.inactive = hints
+            .inactive_code
+            .iter()
+            .map(|code| (span_loc(path, &code.span), code.reason.clone()))
+            .collect();
+        let mut parsed_files = HashMap::new();
+        analysis.alias_actions = s
+            .aliases()
+            .map(|(name, action)| {
+                let source = defs
+                    .alias
+                    .get(name)
+                    .and_then(|span| alias_source(span, &mut parsed_files));
+                let summary = || {
+                    let debug = format!("{action:?}");
+                    let kind: String = debug.chars().take_while(|c| c.is_alphanumeric()).collect();
+                    format!("{kind} action")
+                };
+                (name.to_string(), source.unwrap_or_else(summary))
+            })
+            .collect();
+        analysis
+    }
+
+    fn add_references(&mut self, path: &Path, kind: SymbolKind, references: &ReferencesMap) {
+        for (name, spans) in &references.0 {
+            self.references
+                .entry((kind, name.clone()))
+                .or_default()
+                .extend(spans.iter().map(|span| span_loc(path, span)));
+        }
+    }
+
+    /// Keeps the symbols of `previous` that are missing in this analysis, because parsing
+    /// stopped at an error before reaching them. Locations where the text no longer has the
+    /// symbol's name are dropped. Files that were not parsed this time are read with
+    /// `read_file`.
+    pub fn keep_symbols_from(
+        &mut self,
+        previous: &Analysis,
+        read_file: &mut impl FnMut(&Path) -> Result<String, String>,
+    ) {
+        let mut files = std::mem::take(&mut self.files);
+        let mut is_valid = |(_, name): &Symbol, loc: &Loc| {
+            if !files.contains_key(&loc.file) {
+                let Ok(text) = read_file(&loc.file) else {
+                    return false;
+                };
+                files.insert(loc.file.clone(), text);
+            }
+            range_text(&files[&loc.file], &loc.range)
+                .is_some_and(|text| text.trim_start_matches(['@', '$']) == name)
+        };
+        for (symbol, loc) in &previous.definitions {
+            if !self.definitions.contains_key(symbol) && is_valid(symbol, loc) {
+                self.definitions.insert(symbol.clone(), loc.clone());
+            }
+        }
+        for (symbol, locs) in &previous.references {
+            if self.references.contains_key(symbol) {
+                continue;
+            }
+            let locs: Vec<Loc> = locs
+                .iter()
+                .filter(|loc| is_valid(symbol, loc))
+                .cloned()
+                .collect();
+            if !locs.is_empty() {
+                self.references.insert(symbol.clone(), locs);
+            }
+        }
+        self.files = files;
+        for (alias, action) in &previous.alias_actions {
+            if self
+                .definitions
+                .contains_key(&(SymbolKind::Alias, alias.clone()))
+            {
+                self.alias_actions
+                    .entry(alias.clone())
+                    .or_insert_with(|| action.clone());
+            }
+        }
+    }
+
+    /// Returns the symbol that is defined or referenced at the position.
+    pub fn symbol_at(&self, file: &Path, pos: Position) -> Option<&Symbol> {
+        self.definitions
+            .iter()
+            .find(|(_, loc)| loc.contains(file, pos))
+            .map(|(symbol, _)| symbol)
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .find(|(_, locs)| locs.iter().any(|loc| loc.contains(file, pos)))
+                    .map(|(symbol, _)| symbol)
+            })
+    }
+}
+
+/// Returns the names of all keys, one per key.
+pub fn key_names() -> Vec<&'static str> {
+    (0..=u16::from(OsCode::KEY_MAX))
+        .filter_map(OsCode::from_u16)
+        .filter_map(|osc| oscode_to_str(osc).filter(|name| str_to_oscode(name) == Some(osc)))
+        .collect()
+}
+
+/// Returns the source text of the expression that follows the alias name at `name` in its
+/// `defalias`. The files are parsed once and kept in `parsed_files`.
+fn alias_source(name: &Span, parsed_files: &mut HashMap<String, Vec<SExpr>>) -> Option<String> {
+    let exprs = parsed_files.entry(name.file_name()).or_insert_with(|| {
+        sexpr::parse(&name.file_content, &name.file_name())
+            .map(|top_levels| top_levels.into_iter().map(SExpr::List).collect())
+            .unwrap_or_default()
+    });
+    fn find<'a>(exprs: &'a [SExpr], name: &Span) -> Option<&'a SExpr> {
+        exprs.iter().enumerate().find_map(|(i, expr)| match expr {
+            SExpr::Atom(atom) if atom.span.start() == name.start() => exprs.get(i + 1),
+            SExpr::Atom(_) => None,
+            SExpr::List(list) => find(&list.t, name),
+        })
+    }
+    let expr = find(exprs, name)?;
+    Some(name.file_content[expr.span()].to_string())
+}
+
+/// Returns the text of the range, or None if the range is not in the text.
+fn range_text<'a>(text: &'a str, range: &Range) -> Option<&'a str> {
+    let offset = |pos: Position| {
+        let line_start = if pos.line == 0 {
+            0
+        } else {
+            text.match_indices('\n').nth(pos.line as usize - 1)?.0 + 1
+        };
+        let mut character = 0;
+        for (i, c) in text[line_start..].char_indices() {
+            if character == pos.character {
+                return Some(line_start + i);
+            }
+            if c == '\n' {
+                return None;
+            }
+            character += c.len_utf16() as u32;
+        }
+        (character == pos.character).then_some(text.len())
+    };
+    text.get(offset(range.start)?..offset(range.end)?)
+}
+
+/// Included files are relative to the directory of the main configuration file.
+fn resolve_path(main_cfg: &Path, file: &Path) -> PathBuf {
+    if file == main_cfg {
+        return file.to_path_buf();
+    }
+    let path = match main_cfg.parent() {
+        Some(dir) => dir.join(file),
+        None => file.to_path_buf(),
+    };
+    // Drop the `.` components so that `./file` and `file` are the same path.
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+fn span_loc(main_cfg: &Path, span: &Span) -> Loc {
+    let position = |pos: kanata_parser::cfg::sexpr::Position| Position {
+        line: pos.line as u32,
+        character: span.file_content[pos.line_beginning..pos.absolute]
+            .encode_utf16()
+            .count() as u32,
+    };
+    Loc {
+        file: resolve_path(main_cfg, Path::new(&*span.file_name)),
+        range: Range::new(position(span.start), position(span.end)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(text: &str, included: &[(&str, &str)]) -> Analysis {
+        Analysis::new(text, Path::new("/cfg/main.kbd"), &mut |path| {
+            included
+                .iter()
+                .find(|(name, _)| Path::new("/cfg").join(name) == path)
+                .map(|(_, content)| content.to_string())
+                .ok_or_else(|| "File is not known".to_string())
+        })
+    }
+
+    fn pos(line: u32, character: u32) -> Position {
+        Position::new(line, character)
+    }
+
+    fn loc(file: &str, line: u32, start: u32, end: u32) -> Loc {
+        Loc {
+            file: PathBuf::from(file),
+            range: Range::new(pos(line, start), pos(line, end)),
+        }
+    }
+
+    #[test]
+    fn finds_definitions_and_references() {
+        let analysis = analyze(
+            "(defsrc a b)
+(defalias 🙂x (tap-hold 200 200 a (layer-while-held nav)))
+(deflayer base @🙂x b)
+(deflayer nav a @🙂x)",
+            &[],
+        );
+        assert!(analysis.error.is_none(), "{:?}", analysis.error);
+        let alias = analysis
+            .symbol_at(Path::new("/cfg/main.kbd"), pos(2, 16))
+            .unwrap();
+        assert_eq!(alias, &(SymbolKind::Alias, "🙂x".to_string()));
+        assert_eq!(analysis.definitions[alias], loc("/cfg/main.kbd", 1, 10, 13));
+        assert_eq!(analysis.references[alias].len(), 2);
+        let layer = analysis
+            .symbol_at(Path::new("/cfg/main.kbd"), pos(1, 53))
+            .unwrap();
+        assert_eq!(layer, &(SymbolKind::Layer, "nav".to_string()));
+        assert_eq!(analysis.definitions[layer], loc("/cfg/main.kbd", 3, 10, 13));
+        assert_eq!(
+            analysis.alias_actions["🙂x"],
+            "(tap-hold 200 200 a (layer-while-held nav))"
+        );
+    }
+
+    #[test]
+    fn resolves_included_files() {
+        let analysis = analyze(
+            "(defsrc a) (include ./vars.kbd) (deflayer base $key)",
+            &[("vars.kbd", "(defvar key b)")],
+        );
+        assert!(analysis.error.is_none(), "{:?}", analysis.error);
+        let symbol = (SymbolKind::Variable, "key".to_string());
+        assert_eq!(
+            analysis.definitions[&symbol],
+            loc("/cfg/vars.kbd", 0, 8, 11)
+        );
+        assert_eq!(
+            analysis.symbol_at(Path::new("/cfg/vars.kbd"), pos(0, 9)),
+            Some(&symbol)
+        );
+    }
+
+    #[test]
+    fn reports_error_and_keeps_previous_symbols() {
+        let good = analyze("(defsrc a) (defalias x b) (deflayer base @x)", &[]);
+        let mut bad = analyze("(defsrc a) (defalias x b) (deflayer base @x unknown)", &[]);
+        let error = bad.error.clone().unwrap();
+        assert_eq!(error.loc, Some(loc("/cfg/main.kbd", 0, 26, 52)));
+        assert!(bad.definitions.is_empty());
+        bad.keep_symbols_from(&good, &mut |_| Err("File is not known".to_string()));
+        assert_eq!(
+            bad.definitions[&(SymbolKind::Alias, "x".to_string())],
+            loc("/cfg/main.kbd", 0, 21, 22)
+        );
+        assert_eq!(bad.alias_actions["x"], "b");
+    }
+
+    #[test]
+    fn drops_previous_symbols_that_moved() {
+        let good = analyze("(defsrc a) (defalias x b) (deflayer base @x)", &[]);
+        let mut bad = analyze("(defsrc a b) (defalias y b) (deflayer base @y b c)", &[]);
+        bad.keep_symbols_from(&good, &mut |_| Err("File is not known".to_string()));
+        let symbol = (SymbolKind::Alias, "x".to_string());
+        assert!(!bad.definitions.contains_key(&symbol));
+        assert!(!bad.references.contains_key(&symbol));
+        assert!(!bad.alias_actions.contains_key("x"));
+    }
+
+    #[test]
+    fn key_names_parse_to_distinct_keys() {
+        let names = key_names();
+        assert!(names.contains(&"a") && names.contains(&"lshift") && names.contains(&"f24"));
+        let keys: std::collections::HashSet<_> =
+            names.iter().map(|name| str_to_oscode(name)).collect();
+        assert_eq!(keys.len(), names.len());
+    }
+}
@@ -0,0 +1,301 @@
+//! A language server for kanata configuration files, which communicates over stdio.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as LspNotification, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, GotoDefinition, HoverRequest, References, Request as LspRequest,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, DiagnosticTag, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams, Range, ReferenceParams,
+    ServerCapabilities, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+
+mod analysis;
+use analysis::*;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server::new();
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    break;
+                }
+                connection
+                    .sender
+                    .send(Message::Response(server.handle_request(req)))?;
+            }
+            Message::Notification(notification) => {
+                for notification in server.handle_notification(notification) {
+                    connection
+                        .sender
+                        .send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+struct Server {
+    docs: HashMap<Url, Document>,
+    key_names: Vec<&'static str>,
+}
+
+impl Server {
+    fn new() -> Self {
+        Self {
+            docs: HashMap::new(),
+            key_names: key_names(),
+        }
+    }
+
+    fn handle_request(&self, req: Request) -> Response {
+        match req.method.as_str() {
+            GotoDefinition::METHOD => self.dispatch::<GotoDefinition>(req, Self::definition),
+            References::METHOD => self.dispatch::<References>(req, Self::references),
+            HoverRequest::METHOD => self.dispatch::<HoverRequest>(req, Self::hover),
+            Completion::METHOD => self.dispatch::<Completion>(req, Self::completion),
+            _ => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request: {}", req.method),
+            ),
+        }
+    }
+
+    fn dispatch<R: LspRequest>(
+        &self,
+        req: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(req.params) {
+            Ok(params) => Response::new_ok(req.id, handler(self, params)),
+            Err(e) => Response::new_err(req.id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    /// Returns the diagnostics to publish after the change.
+    fn handle_notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let mut closed = None;
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Ok(params) =
+                    serde_json::from_value::<DidOpenTextDocumentParams>(notification.params)
+                else {
+                    return vec![];
+                };
+                let doc = params.text_document;
+                self.docs.insert(
+                    doc.uri,
+                    Document {
+                        text: doc.text,
+                        analysis: Analysis::default(),
+                    },
+                );
+            }
+            DidChangeTextDocument::METHOD => {
+                let Ok(params) =
+                    serde_json::from_value::<DidChangeTextDocumentParams>(notification.params)
+                else {
+                    return vec![];
+                };
+                let (Some(doc), Some(change)) = (
+                    self.docs.get_mut(&params.text_document.uri),
+                    params.content_changes.into_iter().last(),
+                ) else {
+                    return vec![];
+                };
+                doc.text = change.text;
+            }
+            DidCloseTextDocument::METHOD => {
+                let Ok(params) =
+                    serde_json::from_value::<DidCloseTextDocumentParams>(notification.params)
+                else {
+                    return vec![];
+                };
+                self.docs.remove(&params.text_document.uri);
+                closed = Some(params.text_document.uri);
+            }
+            _ => return vec![],
+        }
+        // A document can be included by other documents, so all of them are parsed again.
+        self.analyze_all();
+        let mut diagnostics: Vec<_> = self
+            .docs
+            .iter()
+            .map(|(uri, doc)| (uri.clone(), diagnostics(&doc_path(uri), &doc.analysis)))
+            .collect();
+        diagnostics.extend(closed.map(|uri| (uri, vec![])));
+        diagnostics
+            .into_iter()
+            .map(|(uri, diagnostics)| {
+                Notification::new(
+                    PublishDiagnostics::METHOD.to_string(),
+                    PublishDiagnosticsParams::new(uri, diagnostics, None),
+                )
+            })
+            .collect()
+    }
+
+    fn analyze_all(&mut self) {
+        let texts: HashMap<PathBuf, String> = self
+            .docs
+            .iter()
+            .map(|(uri, doc)| (doc_path(uri), doc.text.clone()))
+            .collect();
+        let mut read_file = |path: &Path| match texts.get(path) {
+            Some(text) => Ok(text.clone()),
+            None => {
+                std::fs::read_to_string(path).map_err(|e| format!("Failed to include file: {e}"))
+            }
+        };
+        for (uri, doc) in self.docs.iter_mut() {
+            let mut analysis = Analysis::new(&doc.text, &doc_path(uri), &mut read_file);
+            if analysis.error.is_some() {
+                analysis.keep_symbols_from(&doc.analysis, &mut read_file);
+            }
+            doc.analysis = analysis;
+        }
+    }
+
+    /// Returns the symbol at the position and the analysis that knows it. The document may be
+    /// a file included by another open document, so the analyses of all documents are searched.
+    fn symbol_at(&self, params: &TextDocumentPositionParams) -> Option<(&Analysis, &Symbol)> {
+        let path = doc_path(&params.text_document.uri);
+        let own = self.docs.get(&params.text_document.uri);
+        own.into_iter().chain(self.docs.values()).find_map(|doc| {
+            doc.analysis
+                .symbol_at(&path, params.position)
+                .map(|symbol| (&doc.analysis, symbol))
+        })
+    }
+
+    fn location(&self, loc: &Loc) -> Option<Location> {
+        let uri = self
+            .docs
+            .keys()
+            .find(|uri| doc_path(uri) == loc.file)
+            .cloned()
+            .or_else(|| Url::from_file_path(&loc.file).ok())?;
+        Some(Location::new(uri, loc.range))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let (analysis, symbol) = self.symbol_at(&params.text_document_position_params)?;
+        let loc = analysis.definitions.get(symbol)?;
+        self.location(loc).map(GotoDefinitionResponse::Scalar)
+    }
+
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let (analysis, symbol) = self.symbol_at(&params.text_document_position)?;
+        let definition = analysis
+            .definitions
+            .get(symbol)
+            .filter(|_| params.context.include_declaration);
+        let locations = definition
+            .into_iter()
+            .chain(analysis.references.get(symbol).into_iter().flatten())
+            .filter_map(|loc| self.location(loc))
+            .collect();
+        Some(locations)
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (analysis, (kind, name)) = self.symbol_at(&params.text_document_position_params)?;
+        if *kind != SymbolKind::Alias {
+            return None;
+        }
+        let action = analysis.alias_actions.get(name)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("alias `{name}`\n```\n{action}\n```"),
+            }),
+            range: None,
+        })
+    }
+
+    fn completion(&self, _params: CompletionParams) -> Option<CompletionResponse> {
+        let items = self
+            .key_names
+            .iter()
+            .map(|name| CompletionItem {
+                label: name.to_string(),
+                kind: Some(CompletionItemKind::CONSTANT),
+                detail: Some("key".to_string()),
+                ..Default::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+fn doc_path(uri: &Url) -> PathBuf {
+    uri.to_file_path()
+        .unwrap_or_else(|_| PathBuf::from(uri.path()))
+}
+
+fn diagnostics(path: &Path, analysis: &Analysis) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<_> = analysis
+        .inactive
+        .iter()
+        .filter(|(loc, _)| loc.file == path)
+        .map(|(loc, reason)| Diagnostic {
+            range: loc.range,
+            severity: Some(DiagnosticSeverity::HINT),
+            tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+            message: reason.clone(),
+            ..Default::default()
+        })
+        .collect();
+    if let Some(error) = &analysis.error {
+        // Errors in other files are shown at the start of the document.
+        let (range, message) = match &error.loc {
+            Some(loc) if loc.file == path => (loc.range, error.message.clone()),
+            Some(loc) => (
+                Range::default(),
+                format!("{}: {}", loc.file.display(), error.message),
+            ),
+            None => (Range::default(), error.message.clone()),
+        };
+        diagnostics.push(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("kanata".to_string()),
+            message,
+            ..Default::default()
+        });
+    }
+    diagnostics
+}
@@ -0,0 +1,35 @@
+//! Auto-shift outputs the shifted variant of a key when it is held past a timeout.
+//!
+//! The parser only collects the timeout and keys; the keyberon layout decides the output of each
+//! press of these keys.
+
+use super::*;
+
+use crate::{anyhow_expr, bail, bail_expr};
+
+pub(crate) fn parse_autoshift(exprs: &[SExpr], s: &ParserState) -> Result<AutoShift<'static>> {
+    const ERR_MSG: &str = "defautoshift expects a timeout followed by one or more keys";
+    let Some(timeout_expr) = exprs.get(1) else {
+        bail!("{ERR_MSG}");
+    };
+    let timeout = parse_non_zero_u16(timeout_expr, s, "defautoshift timeout")?;
+    if exprs.len() < 3 {
+        bail_expr!(timeout_expr, "{ERR_MSG}");
+    }
+    let mut keys = vec![];
+    for key_expr in &exprs[2..] {
+        let key = key_expr
+            .atom(s.vars())
+            .and_then(str_to_oscode)
+            .ok_or_else(|| anyhow_expr!(key_expr, "Expected a known key name"))?;
+        let key = KeyCode::from(key);
+        if keys.contains(&key) {
+            bail_expr!(key_expr, "Duplicate key, not allowed");
+        }
+        keys.push(key);
+    }
+    Ok(AutoShift {
+        timeout,
+        keys: s.a.sref_vec(keys),
+    })
+}
@@ -11,6 +11,7 @@ use super::*;
 pub(crate) fn parse_defchordv2(
     exprs: &[SExpr],
     s: &ParserState,
+    default_prior_idle: u16,
 ) -> Result<ChordsForKeys<'static, KanataCustom>> {
     if exprs[0].atom(None).expect("should be atom") == "defchordsv2-experimental" {
         log::warn!(
@@ -19,7 +20,25 @@ pub(crate) fn parse_defchordv2(
         );
     }
 
-    let mut chunks = exprs[1..].chunks_exact(5);
+    // Each chord entry is 5 items,
+    // optionally followed by per-chord options such as (require-prior-idle 100).
+    let mut chunks = vec![];
+    let mut rem = &exprs[1..];
+    while rem.len() >= 5 {
+        let (chunk, rest) = rem.split_at(5);
+        let option_count = rest.iter().take_while(|e| is_chord_option(e)).count();
+        let (options, rest) = rest.split_at(option_count);
+        if let Some(name) = rest.first().and_then(unknown_chord_option) {
+            bail_expr!(
+                name,
+                "Unknown chord option.\nValid options: {}",
+                CHORD_OPTIONS.join(", ")
+            );
+        }
+        chunks.push((chunk, options));
+        rem = rest;
+    }
+
     let mut chords_container = ChordsForKeys::<'static, KanataCustom> {
         mapping: FxHashMap::default(),
     };
@@ -27,8 +46,8 @@ pub(crate) fn parse_defchordv2(
     let mut all_participating_key_sets = FxHashSet::default();
 
     let all_chords = chunks
-        .by_ref()
-        .flat_map(|chunk| match chunk[0] {
+        .into_iter()
+        .flat_map(|(chunk, options)| match chunk[0] {
             // Match a line like
             // (include filename.txt) () 100 all-released (layer1 layer2)
             SExpr::List(Spanned {
@@ -46,12 +65,20 @@ pub(crate) fn parse_defchordv2(
                 let chord_definitions = parse_chord_file(file_name).unwrap();
                 let processed = chord_definitions.iter().map(|chord_def| {
                     let chunk = chord_translation.translate_chord(chord_def);
-                    parse_single_chord(&chunk, s, &mut all_participating_key_sets)
+                    parse_single_chord(
+                        &chunk,
+                        options,
+                        default_prior_idle,
+                        s,
+                        &mut all_participating_key_sets,
+                    )
                 });
                 Ok::<_, ParseError>(processed.collect_vec())
             }
             _ => Ok(vec![parse_single_chord(
                 chunk,
+                options,
+                default_prior_idle,
                 s,
                 &mut all_participating_key_sets,
             )]),
@@ -78,7 +105,6 @@ pub(crate) fn parse_defchordv2(
                 .push(s.a.sref(chord.clone()));
         }
     }
-    let rem = chunks.remainder();
     if !rem.is_empty() {
         bail_expr!(
             rem.last().unwrap(),
@@ -91,6 +117,8 @@ pub(crate) fn parse_defchordv2(
 
 fn parse_single_chord(
     chunk: &[SExpr],
+    options: &[SExpr],
+    default_prior_idle: u16,
     s: &ParserState,
     all_participating_key_sets: &mut FxHashSet<Vec<u16>>,
 ) -> Result<ChordV2<'static, KanataCustom>> {
@@ -105,16 +133,62 @@ fn parse_single_chord(
     let timeout = parse_timeout(&chunk[2], s)?;
     let release_behaviour = parse_release_behaviour(&chunk[3], s)?;
     let disabled_layers = parse_disabled_layers(&chunk[4], s)?;
+    let mut prior_idle = default_prior_idle;
+    for option in options {
+        let option_list = option.list(s.vars()).expect("checked by is_chord_option");
+        match option_list[0].atom(s.vars()) {
+            Some(REQUIRE_PRIOR_IDLE) => {
+                if option_list.len() != 2 {
+                    bail_expr!(
+                        option,
+                        "{REQUIRE_PRIOR_IDLE} expects one parameter: idle-time"
+                    );
+                }
+                prior_idle = parse_u16(&option_list[1], s, REQUIRE_PRIOR_IDLE)?;
+            }
+            _ => unreachable!("checked by is_chord_option"),
+        }
+    }
     let chord: ChordV2<'static, KanataCustom> = ChordV2 {
         action,
         participating_keys: s.a.sref_vec(participants.clone()),
         pending_duration: timeout,
         disabled_layers: s.a.sref_vec(disabled_layers),
         release_behaviour,
+        prior_idle,
     };
     Ok(s.a.sref(chord).clone())
 }
 
+const REQUIRE_PRIOR_IDLE: &str = "require-prior-idle";
+
+const CHORD_OPTIONS: &[&str] = &[REQUIRE_PRIOR_IDLE];
+
+/// Options are lists that follow a chord entry and start with one of the [`CHORD_OPTIONS`].
+fn is_chord_option(expr: &SExpr) -> bool {
+    matches!(
+        expr,
+        SExpr::List(Spanned { t: exprs, .. })
+            if matches!(exprs.first(), Some(SExpr::Atom(a)) if CHORD_OPTIONS.contains(&a.t.as_str()))
+    )
+}
+
+/// A list following a chord entry that is neither an option nor the start of the next chord
+/// entry, i.e. it does not start with a key name, a variable or an include. Returns its name.
+fn unknown_chord_option(expr: &SExpr) -> Option<&SExpr> {
+    match expr {
+        SExpr::List(Spanned { t: exprs, .. }) => match exprs.first() {
+            Some(name @ SExpr::Atom(a))
+                if a.t != "include" && !a.t.starts_with('$') && str_to_oscode(&a.t).is_none() =>
+            {
+                Some(name)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn parse_participating_keys(keys: &SExpr, s: &ParserState) -> Result<Vec<u16>> {
     let mut participants = keys
         .list(s.vars())
@@ -27,13 +27,32 @@ pub struct CfgLinuxOptions {
     pub linux_dev: Vec<String>,
     pub linux_dev_names_include: Option<Vec<String>>,
     pub linux_dev_names_exclude: Option<Vec<String>>,
+    pub linux_dev_include_match: Vec<super::DeviceMatcher>,
+    pub linux_dev_exclude_match: Vec<super::DeviceMatcher>,
     pub linux_continue_if_no_devs_found: bool,
     pub linux_unicode_u_code: crate::keys::OsCode,
     pub linux_unicode_termination: UnicodeTermination,
+    pub linux_unicode_output: UnicodeOutput,
+    /// Key to remap for unicode output with [`UnicodeOutput::Keymap`]. If None, an unused
+    /// keycode is found in the keymap.
+    pub linux_unicode_keymap_key: Option<OsCode>,
+    pub linux_unicode_compose_key: OsCode,
+    /// Key presses that output each character with [`UnicodeOutput::Compose`], following the
+    /// compose key. Each step is a key with its modifiers.
+    pub linux_unicode_compose_sequences: rustc_hash::FxHashMap<char, Vec<Vec<OsCode>>>,
     pub linux_x11_repeat_delay_rate: Option<KeyRepeatSettings>,
+    pub linux_key_repeat_delay_rate: Option<KeyRepeatSettings>,
+    pub linux_key_repeat_output: KeyRepeatOutput,
     pub linux_use_trackpoint_property: bool,
+    pub linux_passthru_non_key_events: bool,
     pub linux_output_bus_type: LinuxCfgOutputBusType,
+    pub linux_output_device_name: String,
+    /// If set, pointer events are output on a separate device with this name.
+    pub linux_output_pointer_device_name: Option<String>,
+    /// Bus type of the pointer device. If None, the bus type of the main output device is used.
+    pub linux_output_pointer_bus_type: Option<LinuxCfgOutputBusType>,
     pub linux_device_detect_mode: Option<DeviceDetectMode>,
+    pub linux_setmouse_screen_extents: Option<ScreenExtents>,
 }
 #[cfg(any(target_os = "linux", target_os = "unknown"))]
 impl Default for CfgLinuxOptions {
@@ -42,15 +61,28 @@ impl Default for CfgLinuxOptions {
             linux_dev: vec![],
             linux_dev_names_include: None,
             linux_dev_names_exclude: None,
+            linux_dev_include_match: vec![],
+            linux_dev_exclude_match: vec![],
             linux_continue_if_no_devs_found: false,
             // historically was the only option, so make KEY_U the default
             linux_unicode_u_code: crate::keys::OsCode::KEY_U,
             // historically was the only option, so make Enter the default
             linux_unicode_termination: UnicodeTermination::Enter,
+            linux_unicode_output: UnicodeOutput::CtrlShiftU,
+            linux_unicode_keymap_key: None,
+            linux_unicode_compose_key: OsCode::KEY_COMPOSE,
+            linux_unicode_compose_sequences: Default::default(),
             linux_x11_repeat_delay_rate: None,
+            linux_key_repeat_delay_rate: None,
+            linux_key_repeat_output: KeyRepeatOutput::Press,
             linux_use_trackpoint_property: false,
+            linux_passthru_non_key_events: false,
             linux_output_bus_type: LinuxCfgOutputBusType::BusI8042,
+            linux_output_device_name: "kanata".into(),
+            linux_output_pointer_device_name: None,
+            linux_output_pointer_bus_type: None,
             linux_device_detect_mode: None,
+            linux_setmouse_screen_extents: None,
         }
     }
 }
@@ -127,6 +159,7 @@ pub struct CfgOptions {
     pub sequence_input_mode: SequenceInputMode,
     pub sequence_backtrack_modcancel: bool,
     pub sequence_always_on: bool,
+    pub sequence_progress_show_names: bool,
     pub log_layer_changes: bool,
     pub delegate_to_first_layer: bool,
     pub movemouse_inherit_accel_state: bool,
@@ -138,6 +171,8 @@ pub struct CfgOptions {
     pub rapid_event_delay: u16,
     pub trans_resolution_behavior_v2: bool,
     pub chords_v2_min_idle: u16,
+    pub chords_v2_require_prior_idle: u16,
+    pub chords_v2_queue_size: u16,
     #[cfg(any(target_os = "linux", target_os = "unknown"))]
     pub linux_opts: CfgLinuxOptions,
     #[cfg(any(target_os = "windows", target_os = "unknown"))]
@@ -166,6 +201,7 @@ impl Default for CfgOptions {
             sequence_input_mode: SequenceInputMode::HiddenSuppressed,
             sequence_backtrack_modcancel: true,
             sequence_always_on: false,
+            sequence_progress_show_names: false,
             log_layer_changes: true,
             delegate_to_first_layer: false,
             movemouse_inherit_accel_state: false,
@@ -177,6 +213,8 @@ impl Default for CfgOptions {
             rapid_event_delay: 5,
             trans_resolution_behavior_v2: true,
             chords_v2_min_idle: 5,
+            chords_v2_require_prior_idle: 0,
+            chords_v2_queue_size: kanata_keyberon::chord::DEFAULT_CHORDS_V2_QUEUE_LEN as u16,
             #[cfg(any(target_os = "linux", target_os = "unknown"))]
             linux_opts: Default::default(),
             #[cfg(any(target_os = "windows", target_os = "unknown"))]
@@ -208,6 +246,12 @@ pub fn parse_defcfg(expr: &[SExpr]) -> Result<CfgOptions> {
                 if !is_process_unmapped_keys_defined {
                     log::warn!("The item process-unmapped-keys is not defined in defcfg. Consider whether process-unmapped-keys should be yes vs. no.");
                 }
+                #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                if cfg.linux_opts.linux_output_pointer_device_name.as_ref()
+                    == Some(&cfg.linux_opts.linux_output_device_name)
+                {
+                    bail!("linux-output-pointer-device-name must be different from linux-output-device-name");
+                }
                 return Ok(cfg);
             }
         };
@@ -233,6 +277,9 @@ pub fn parse_defcfg(expr: &[SExpr]) -> Result<CfgOptions> {
                     "sequence-always-on" => {
                         cfg.sequence_always_on = parse_defcfg_val_bool(val, label)?
                     }
+                    "sequence-progress-show-names" => {
+                        cfg.sequence_progress_show_names = parse_defcfg_val_bool(val, label)?
+                    }
                     "dynamic-macro-max-presses" => {
                         cfg.dynamic_macro_max_presses = parse_cfg_val_u16(val, label, false)?;
                     }
@@ -279,6 +326,27 @@ pub fn parse_defcfg(expr: &[SExpr]) -> Result<CfgOptions> {
                             cfg.linux_opts.linux_dev_names_exclude = Some(parse_dev(val)?);
                         }
                     }
+                    "linux-dev-include-match" => {
+                        let matchers = super::parse_device_matchers(val, label)?;
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            if matchers.is_empty() {
+                                log::warn!("linux-dev-include-match is empty");
+                            }
+                            cfg.linux_opts.linux_dev_include_match = matchers;
+                        }
+                        #[cfg(not(any(target_os = "linux", target_os = "unknown")))]
+                        let _ = matchers;
+                    }
+                    "linux-dev-exclude-match" => {
+                        let matchers = super::parse_device_matchers(val, label)?;
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            cfg.linux_opts.linux_dev_exclude_match = matchers;
+                        }
+                        #[cfg(not(any(target_os = "linux", target_os = "unknown")))]
+                        let _ = matchers;
+                    }
                     "linux-unicode-u-code" => {
                         #[cfg(any(target_os = "linux", target_os = "unknown"))]
                         {
@@ -306,25 +374,81 @@ pub fn parse_defcfg(expr: &[SExpr]) -> Result<CfgOptions> {
                             }
                         }
                     }
+                    "linux-unicode-output" => {
+                        let v = sexpr_to_str_or_err(val, label)?;
+                        let output = match v {
+                            "ctrl-shift-u" => UnicodeOutput::CtrlShiftU,
+                            "keymap" => UnicodeOutput::Keymap,
+                            "compose" => UnicodeOutput::Compose,
+                            _ => bail_expr!(
+                                val,
+                                "{label} got {}. It accepts: ctrl-shift-u|keymap|compose",
+                                v
+                            ),
+                        };
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            cfg.linux_opts.linux_unicode_output = output;
+                        }
+                        #[cfg(not(any(target_os = "linux", target_os = "unknown")))]
+                        let _ = output;
+                    }
+                    "linux-unicode-keymap-key" | "linux-unicode-compose-key" => {
+                        let v = sexpr_to_str_or_err(val, label)?;
+                        let key = str_to_oscode(v)
+                            .ok_or_else(|| anyhow_expr!(val, "unknown code for {label}: {}", v))?;
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        match label {
+                            "linux-unicode-keymap-key" => {
+                                // X11 keycodes are the evdev codes offset by 8 and at most 255.
+                                if u16::from(key) > 247 {
+                                    bail_expr!(
+                                        val,
+                                        "{label} must be a key that exists in X11 keymaps"
+                                    );
+                                }
+                                cfg.linux_opts.linux_unicode_keymap_key = Some(key);
+                            }
+                            _ => cfg.linux_opts.linux_unicode_compose_key = key,
+                        }
+                        #[cfg(not(any(target_os = "linux", target_os = "unknown")))]
+                        let _ = key;
+                    }
+                    "linux-unicode-compose-sequences" => {
+                        let sequences = parse_compose_sequences(val, label)?;
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            cfg.linux_opts.linux_unicode_compose_sequences = sequences;
+                        }
+                        #[cfg(not(any(target_os = "linux", target_os = "unknown")))]
+                        let _ = sequences;
+                    }
                     "linux-x11-repeat-delay-rate" => {
                         #[cfg(any(target_os = "linux", target_os = "unknown"))]
                         {
-                            let v = sexpr_to_str_or_err(val, label)?;
-                            let delay_rate = v.split(',').collect::<Vec<_>>();
-                            const ERRMSG: &str = "Invalid value for linux-x11-repeat-delay-rate.\nExpected two numbers 0-65535 separated by a comma, e.g. 200,25";
-                            if delay_rate.len() != 2 {
-                                bail_expr!(val, "{}", ERRMSG)
+                            cfg.linux_opts.linux_x11_repeat_delay_rate =
+                                Some(parse_key_repeat_settings(val, label)?);
+                        }
+                    }
+                    "linux-key-repeat-delay-rate" => {
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            let settings = parse_key_repeat_settings(val, label)?;
+                            if !(1..=1000).contains(&settings.rate) {
+                                bail_expr!(val, "The repeat rate for {label} must be 1-1000");
                             }
-                            cfg.linux_opts.linux_x11_repeat_delay_rate = Some(KeyRepeatSettings {
-                                delay: match str::parse::<u16>(delay_rate[0]) {
-                                    Ok(delay) => delay,
-                                    Err(_) => bail_expr!(val, "{}", ERRMSG),
-                                },
-                                rate: match str::parse::<u16>(delay_rate[1]) {
-                                    Ok(rate) => rate,
-                                    Err(_) => bail_expr!(val, "{}", ERRMSG),
-                                },
-                            });
+                            cfg.linux_opts.linux_key_repeat_delay_rate = Some(settings);
+                        }
+                    }
+                    "linux-key-repeat-output" => {
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            cfg.linux_opts.linux_key_repeat_output =
+                                match sexpr_to_str_or_err(val, label)? {
+                                    "repeat" => KeyRepeatOutput::Repeat,
+                                    "press" => KeyRepeatOutput::Press,
+                                    _ => bail_expr!(val, "{label} must be one of: repeat | press"),
+                                };
                         }
                     }
                     "linux-use-trackpoint-property" => {
@@ -334,6 +458,35 @@ pub fn parse_defcfg(expr: &[SExpr]) -> Result<CfgOptions> {
                                 parse_defcfg_val_bool(val, label)?
                         }
                     }
+                    "linux-passthru-non-key-events" => {
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            cfg.linux_opts.linux_passthru_non_key_events =
+                                parse_defcfg_val_bool(val, label)?
+                        }
+                    }
+                    "linux-setmouse-screen-extents" => {
+                        let v = sexpr_to_str_or_err(val, label)?;
+                        const ERRMSG: &str = "Invalid value for linux-setmouse-screen-extents.\nExpected two numbers 1-65535 separated by a comma, e.g. 1920,1080";
+                        let extents = match v.split(',').collect::<Vec<_>>()[..] {
+                            [width, height] => {
+                                match (str::parse::<u16>(width), str::parse::<u16>(height)) {
+                                    (Ok(width @ 1..), Ok(height @ 1..)) => (width, height),
+                                    _ => bail_expr!(val, "{}", ERRMSG),
+                                }
+                            }
+                            _ => bail_expr!(val, "{}", ERRMSG),
+                        };
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            cfg.linux_opts.linux_setmouse_screen_extents = Some(ScreenExtents {
+                                width: extents.0,
+                                height: extents.1,
+                            });
+                        }
+                        #[cfg(not(any(target_os = "linux", target_os = "unknown")))]
+                        let _ = extents;
+                    }
                     "linux-output-device-bus-type" => {
                         let bus_type = sexpr_to_str_or_err(val, label)?;
                         match bus_type {
@@ -350,6 +503,40 @@ pub fn parse_defcfg(expr: &[SExpr]) -> Result<CfgOptions> {
                             cfg.linux_opts.linux_output_bus_type = bus_type;
                         }
                     }
+                    "linux-output-pointer-device-bus-type" => {
+                        let bus_type = sexpr_to_str_or_err(val, label)?;
+                        match bus_type {
+                            "USB" | "I8042" => {}
+                            _ => bail_expr!(
+                                val,
+                                "Invalid value for {label}.\nExpected one of: USB or I8042"
+                            ),
+                        };
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        {
+                            cfg.linux_opts.linux_output_pointer_bus_type = Some(match bus_type {
+                                "USB" => LinuxCfgOutputBusType::BusUsb,
+                                "I8042" => LinuxCfgOutputBusType::BusI8042,
+                                _ => unreachable!("validated earlier"),
+                            });
+                        }
+                    }
+                    "linux-output-device-name" | "linux-output-pointer-device-name" => {
+                        let name = sexpr_to_str_or_err(val, label)?;
+                        if name.is_empty() {
+                            bail_expr!(val, "{label} must not be empty");
+                        }
+                        #[cfg(any(target_os = "linux", target_os = "unknown"))]
+                        match label {
+                            "linux-output-device-name" => {
+                                cfg.linux_opts.linux_output_device_name = name.to_owned();
+                            }
+                            _ => {
+                                cfg.linux_opts.linux_output_pointer_device_name =
+                                    Some(name.to_owned());
+                            }
+                        }
+                    }
                     "linux-device-detect-mode" => {
                         let detect_mode = sexpr_to_str_or_err(val, label)?;
                         match detect_mode {
@@ -764,6 +951,12 @@ pub fn parse_defcfg(expr: &[SExpr]) -> Result<CfgOptions> {
                         }
                         cfg.chords_v2_min_idle = min_idle;
                     }
+                    "chords-v2-require-prior-idle" => {
+                        cfg.chords_v2_require_prior_idle = parse_cfg_val_u16(val, label, false)?;
+                    }
+                    "chords-v2-queue-size" => {
+                        cfg.chords_v2_queue_size = parse_cfg_val_u16(val, label, true)?;
+                    }
                     _ => bail_expr!(key, "Unknown defcfg option {}", label),
                 };
             }
@@ -887,6 +1080,56 @@ pub fn parse_dev(val: &SExpr) -> Result<Vec<String>> {
     })
 }
 
+/// Parse pairs of a character and the list of keys to press after the compose key, e.g.
+/// `(é (' e) ñ (S-grv n))`. The keys can have modifier prefixes.
+fn parse_compose_sequences(
+    val: &SExpr,
+    label: &str,
+) -> Result<rustc_hash::FxHashMap<char, Vec<Vec<OsCode>>>> {
+    const ERR_MSG: &str = "expects a list of pairs of a character and a list of keys";
+    let Some(list) = val.list(None) else {
+        bail_expr!(val, "{label} {ERR_MSG}, e.g. (é (' e))");
+    };
+    if list.len() % 2 != 0 {
+        bail_expr!(val, "{label} {ERR_MSG}; the last character has no keys");
+    }
+    let mut sequences = rustc_hash::FxHashMap::default();
+    for pair in list.chunks_exact(2) {
+        let mut chars = match pair[0].atom(None) {
+            Some(a) => a.trim_atom_quotes().chars(),
+            None => bail_expr!(&pair[0], "Expected a single character"),
+        };
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            bail_expr!(&pair[0], "Expected a single character");
+        };
+        if sequences.contains_key(&c) {
+            bail_expr!(&pair[0], "Duplicate character, not allowed");
+        }
+        let Some(keys) = pair[1].list(None).filter(|keys| !keys.is_empty()) else {
+            bail_expr!(&pair[1], "Expected a non-empty list of keys");
+        };
+        let steps = keys
+            .iter()
+            .map(|key_expr| {
+                let Some(key) = key_expr.atom(None) else {
+                    bail_expr!(key_expr, "Expected a key, not a list");
+                };
+                let (mods, key) = super::parse_mod_prefix(key)
+                    .map_err(|e| anyhow_expr!(key_expr, "{}", e.msg))?;
+                let key = str_to_oscode(key)
+                    .ok_or_else(|| anyhow_expr!(key_expr, "Unknown key name: {key}"))?;
+                Ok(mods
+                    .into_iter()
+                    .map(OsCode::from)
+                    .chain(std::iter::once(key))
+                    .collect::<Vec<_>>())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        sequences.insert(c, steps);
+    }
+    Ok(sequences)
+}
+
 fn sexpr_to_str_or_err<'a>(expr: &'a SExpr, label: &str) -> Result<&'a str> {
     match expr {
         SExpr::Atom(a) => Ok(a.t.trim_atom_quotes()),
@@ -949,6 +1192,46 @@ pub struct KeyRepeatSettings {
     pub rate: u16,
 }
 
+#[cfg(any(target_os = "linux", target_os = "unknown"))]
+fn parse_key_repeat_settings(val: &SExpr, label: &str) -> Result<KeyRepeatSettings> {
+    let v = sexpr_to_str_or_err(val, label)?;
+    let delay_rate = v.split(',').collect::<Vec<_>>();
+    let errmsg = format!("Invalid value for {label}.\nExpected two numbers 0-65535 separated by a comma, e.g. 200,25");
+    if delay_rate.len() != 2 {
+        bail_expr!(val, "{}", errmsg)
+    }
+    Ok(KeyRepeatSettings {
+        delay: match str::parse::<u16>(delay_rate[0]) {
+            Ok(delay) => delay,
+            Err(_) => bail_expr!(val, "{}", errmsg),
+        },
+        rate: match str::parse::<u16>(delay_rate[1]) {
+            Ok(rate) => rate,
+            Err(_) => bail_expr!(val, "{}", errmsg),
+        },
+    })
+}
+
+/// How key repeats generated by kanata are written to the output device.
+#[cfg(any(target_os = "linux", target_os = "unknown"))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyRepeatOutput {
+    /// Write key repeat events, the same as a keyboard does. Only the Linux console and other
+    /// programs that read the events without libinput use these.
+    Repeat,
+    /// Write a release followed by a press. Libinput, used by Wayland compositors and most X11
+    /// setups, ignores repeat events.
+    Press,
+}
+
+/// Screen size in pixels used for absolute mouse positioning.
+#[cfg(any(target_os = "linux", target_os = "unknown"))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScreenExtents {
+    pub width: u16,
+    pub height: u16,
+}
+
 #[cfg(any(target_os = "linux", target_os = "unknown"))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum UnicodeTermination {
@@ -958,6 +1241,17 @@ pub enum UnicodeTermination {
     EnterSpace,
 }
 
+/// How unicode characters are typed on Linux.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnicodeOutput {
+    /// Ctrl+Shift+U, the hex code and a terminating key. Works in GTK and IBus applications.
+    CtrlShiftU,
+    /// Temporarily map a spare keycode to the character in the X11 keymap and press it. X11 only.
+    Keymap,
+    /// Press the compose key followed by the configured sequence for the character.
+    Compose,
+}
+
 #[cfg(any(target_os = "windows", target_os = "unknown"))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AltGrBehaviour {
@@ -0,0 +1,447 @@
+//! Per-device configuration sections.
+//!
+//! A `defdevice` section matches input devices by their properties and can change how the keys of
+//! those devices are processed:
+//!
+//! - `layer` selects the base layer to use while typing on the device.
+//! - `defsrc` maps the keys of the device to the keys of the main `defsrc`, by position.
+//! - `on-connect` and `on-disconnect` are actions to run when kanata grabs the device or the
+//!   device is removed.
+//!
+//! The device matching is done at runtime by the Linux input code; the section index is carried on
+//! key events into the processing loop.
+
+use super::*;
+
+use crate::{anyhow_expr, bail, bail_expr};
+
+/// Properties of an input device that `defdevice` sections and the `linux-dev-*-match` options
+/// are matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo<'a> {
+    pub name: &'a str,
+    pub phys: &'a str,
+    pub uniq: &'a str,
+    pub bus: u16,
+    pub vendor: u16,
+    pub product: u16,
+}
+
+/// Pattern for a text property of a device.
+#[derive(Debug, Clone)]
+pub enum DevicePattern {
+    /// Glob pattern where `*` matches any sequence of characters and `?` matches any single
+    /// character. The whole text must match.
+    Glob(String),
+    /// Regular expression. Like usual for regular expressions, a match anywhere in the text is
+    /// accepted unless the pattern is anchored with `^` and `$`.
+    Regex(regex::Regex),
+}
+
+impl DevicePattern {
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            DevicePattern::Glob(glob) => glob_matches(glob, text),
+            DevicePattern::Regex(re) => re.is_match(text),
+        }
+    }
+}
+
+impl PartialEq for DevicePattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DevicePattern::Glob(a), DevicePattern::Glob(b)) => a == b,
+            (DevicePattern::Regex(a), DevicePattern::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DevicePattern {}
+
+impl std::fmt::Display for DevicePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DevicePattern::Glob(glob) => write!(f, "\"{glob}\""),
+            DevicePattern::Regex(re) => write!(f, "(regex \"{}\")", re.as_str()),
+        }
+    }
+}
+
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut g, mut t) = (0, 0);
+    // Position of the last `*` in the glob and the text position it is currently matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_g, star_t)) => {
+                    backtrack = Some((star_g, star_t + 1));
+                    g = star_g + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+/// Conditions on device properties. Every condition that is specified must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceMatcher {
+    pub name: Option<DevicePattern>,
+    pub phys: Option<DevicePattern>,
+    pub uniq: Option<DevicePattern>,
+    pub bus: Option<u16>,
+    pub vendor: Option<u16>,
+    pub product: Option<u16>,
+}
+
+impl DeviceMatcher {
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        self.name.as_ref().is_none_or(|p| p.matches(info.name))
+            && self.phys.as_ref().is_none_or(|p| p.matches(info.phys))
+            && self.uniq.as_ref().is_none_or(|p| p.matches(info.uniq))
+            && self.bus.is_none_or(|bus| bus == info.bus)
+            && self.vendor.is_none_or(|vendor| vendor == info.vendor)
+            && self.product.is_none_or(|product| product == info.product)
+    }
+}
+
+impl std::fmt::Display for DeviceMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut conditions = vec![];
+        for (label, pattern) in [(NAME, &self.name), (PHYS, &self.phys), (UNIQ, &self.uniq)] {
+            if let Some(pattern) = pattern {
+                conditions.push(format!("{label} {pattern}"));
+            }
+        }
+        for (label, value) in [
+            (BUS, self.bus),
+            (VENDOR, self.vendor),
+            (PRODUCT, self.product),
+        ] {
+            if let Some(value) = value {
+                conditions.push(format!("{label} {value:04x}"));
+            }
+        }
+        write!(f, "({})", conditions.join(" "))
+    }
+}
+
+const NAME: &str = "name";
+const PHYS: &str = "phys";
+const UNIQ: &str = "uniq";
+const BUS: &str = "bus";
+const VENDOR: &str = "vendor";
+const PRODUCT: &str = "product";
+const ID: &str = "id";
+const MATCHER_NAMES: &str = "name, phys, uniq, bus, vendor, product, id";
+
+#[derive(Debug, Clone, Default)]
+pub struct DeviceCfg {
+    /// Name of the `defdevice` section, used for logging.
+    pub name: String,
+    pub matcher: DeviceMatcher,
+    /// Index of the layer to use as the base layer while typing on the device.
+    pub layer: Option<usize>,
+    /// Mapping of device keys to main `defsrc` keys. If empty, the device keys are used as-is.
+    /// Otherwise, device keys that are not in the mapping are not processed by kanata.
+    pub key_mapping: HashMap<OsCode, OsCode>,
+    /// Action to run when kanata grabs a matching device.
+    pub on_connect: Option<&'static KanataAction>,
+    /// Action to run when a matching device that kanata grabbed is removed.
+    pub on_disconnect: Option<&'static KanataAction>,
+}
+
+impl DeviceCfg {
+    /// Returns the key to process for a key from this device.
+    /// Returns None if the key should not be processed.
+    pub fn map_key(&self, osc: OsCode) -> Option<OsCode> {
+        if self.key_mapping.is_empty() {
+            Some(osc)
+        } else {
+            self.key_mapping.get(&osc).copied()
+        }
+    }
+}
+
+/// Returns the index of the first section that matches the device.
+pub fn find_device_cfg(devices: &[DeviceCfg], info: &DeviceInfo) -> Option<usize> {
+    devices.iter().position(|d| d.matcher.matches(info))
+}
+
+pub(crate) fn parse_defdevices(
+    exprs: &[&Vec<SExpr>],
+    s: &ParserState,
+    mapped_keys: &mut MappedKeys,
+) -> Result<Vec<DeviceCfg>> {
+    let mut devices: Vec<DeviceCfg> = vec![];
+    for expr in exprs {
+        let device = parse_defdevice(expr, s, mapped_keys)?;
+        if devices.iter().any(|d| d.name == device.name) {
+            bail_expr!(&expr[1], "Duplicate defdevice name, not allowed");
+        }
+        devices.push(device);
+    }
+    Ok(devices)
+}
+
+fn parse_defdevice(
+    exprs: &[SExpr],
+    s: &ParserState,
+    mapped_keys: &mut MappedKeys,
+) -> Result<DeviceCfg> {
+    const LAYER: &str = "layer";
+    const DEFSRC: &str = "defsrc";
+    const ON_CONNECT: &str = "on-connect";
+    const ON_DISCONNECT: &str = "on-disconnect";
+
+    let Some(name_expr) = exprs.get(1) else {
+        bail!("defdevice expects a section name followed by configuration pairs");
+    };
+    let mut device = DeviceCfg {
+        name: name_expr
+            .atom(s.vars())
+            .ok_or_else(|| anyhow_expr!(name_expr, "defdevice section name must not be a list"))?
+            .to_owned(),
+        ..Default::default()
+    };
+
+    let mut seen: Vec<&str> = vec![];
+    let mut pairs = exprs[2..].chunks_exact(2);
+    for pair in pairs.by_ref() {
+        let config_name = &pair[0];
+        let config_value = &pair[1];
+        let Some(cfg_name) = config_name.atom(s.vars()) else {
+            bail_expr!(config_name, "Expected a defdevice configuration name");
+        };
+        if seen.contains(&cfg_name) {
+            bail_expr!(
+                config_name,
+                "This is the 2nd instance; it can only be defined once"
+            );
+        }
+        match cfg_name {
+            LAYER => {
+                device.layer = Some(
+                    config_value
+                        .atom(s.vars())
+                        .and_then(|l| s.layer_idxs.get(l))
+                        .copied()
+                        .ok_or_else(|| anyhow_expr!(config_value, "Not a known layer name."))?,
+                );
+            }
+            DEFSRC => {
+                let keys = config_value.list(s.vars()).ok_or_else(|| {
+                    anyhow_expr!(config_value, "{DEFSRC} must be followed by a list of keys")
+                })?;
+                if keys.len() != s.mapping_order.len() {
+                    bail_expr!(
+                        config_value,
+                        "The device defsrc must have the same number of keys as the main defsrc.\n\
+                        Found {} keys but expected {}.",
+                        keys.len(),
+                        s.mapping_order.len()
+                    );
+                }
+                for (key, src_key) in keys.iter().zip(s.mapping_order.iter().copied()) {
+                    let osc = key
+                        .atom(s.vars())
+                        .and_then(str_to_oscode)
+                        .ok_or_else(|| anyhow_expr!(key, "Expected a known key name"))?;
+                    let src_key = OsCode::from_u16(src_key as u16).expect("valid defsrc key");
+                    if device.key_mapping.insert(osc, src_key).is_some() {
+                        bail_expr!(key, "Duplicate key, not allowed");
+                    }
+                    mapped_keys.insert(osc);
+                }
+            }
+            ON_CONNECT => device.on_connect = Some(parse_action(config_value, s)?),
+            ON_DISCONNECT => device.on_disconnect = Some(parse_action(config_value, s)?),
+            _ => {
+                if !parse_matcher_pair(cfg_name, config_value, s.vars(), &mut device.matcher)? {
+                    bail_expr!(config_name, "Unknown defdevice configuration name");
+                }
+            }
+        }
+        seen.push(cfg_name);
+    }
+    let rem = pairs.remainder();
+    if !rem.is_empty() {
+        bail_expr!(&rem[0], "defdevice config name is missing its value");
+    }
+    if device.matcher == DeviceMatcher::default() {
+        bail_expr!(
+            name_expr,
+            "defdevice must have at least one of: {MATCHER_NAMES}"
+        );
+    }
+    Ok(device)
+}
+
+/// Parses a list of device matchers, e.g. `((name "Logi*" bus usb) (id 046d:c52b))`.
+pub(crate) fn parse_device_matchers(expr: &SExpr, label: &str) -> Result<Vec<DeviceMatcher>> {
+    let Some(lists) = expr.list(None) else {
+        bail_expr!(
+            expr,
+            "{label} expects a list of device matchers, e.g. ((name \"My Keyboard*\" bus usb))"
+        );
+    };
+    let mut matchers = vec![];
+    for list_expr in lists {
+        let Some(list) = list_expr.list(None) else {
+            bail_expr!(
+                list_expr,
+                "Expected a device matcher list of name/value pairs, e.g. (name \"My Keyboard*\")"
+            );
+        };
+        let mut matcher = DeviceMatcher::default();
+        let mut seen: Vec<&str> = vec![];
+        let mut pairs = list.chunks_exact(2);
+        for pair in pairs.by_ref() {
+            let Some(name) = pair[0].atom(None) else {
+                bail_expr!(&pair[0], "Expected one of: {MATCHER_NAMES}");
+            };
+            if seen.contains(&name) {
+                bail_expr!(
+                    &pair[0],
+                    "This is the 2nd instance; it can only be defined once"
+                );
+            }
+            if !parse_matcher_pair(name, &pair[1], None, &mut matcher)? {
+                bail_expr!(&pair[0], "Expected one of: {MATCHER_NAMES}");
+            }
+            seen.push(name);
+        }
+        let rem = pairs.remainder();
+        if !rem.is_empty() {
+            bail_expr!(&rem[0], "Device matcher name is missing its value");
+        }
+        if matcher == DeviceMatcher::default() {
+            bail_expr!(
+                list_expr,
+                "Device matcher must have at least one of: {MATCHER_NAMES}"
+            );
+        }
+        matchers.push(matcher);
+    }
+    Ok(matchers)
+}
+
+/// Parses a device property condition into the matcher.
+/// Returns false if the name is not a device property.
+fn parse_matcher_pair(
+    name: &str,
+    value: &SExpr,
+    vars: Option<&HashMap<String, SExpr>>,
+    matcher: &mut DeviceMatcher,
+) -> Result<bool> {
+    match name {
+        NAME => matcher.name = Some(parse_pattern(value, vars, NAME)?),
+        PHYS => matcher.phys = Some(parse_pattern(value, vars, PHYS)?),
+        UNIQ => matcher.uniq = Some(parse_pattern(value, vars, UNIQ)?),
+        BUS => {
+            let bus = atom_value(value, vars, BUS)?;
+            matcher.bus = Some(match bus.to_ascii_lowercase().as_str() {
+                "usb" => 0x03,
+                "bluetooth" => 0x05,
+                "virtual" => 0x06,
+                "i8042" => 0x11,
+                "i2c" => 0x18,
+                "host" => 0x19,
+                "spi" => 0x1c,
+                other => parse_hex_u16(other.trim_start_matches("0x")).ok_or_else(|| {
+                    anyhow_expr!(
+                        value,
+                        "bus expects one of: usb, bluetooth, virtual, i8042, i2c, host, spi; \
+                        or a hexadecimal bus number, e.g. 0x03"
+                    )
+                })?,
+            });
+        }
+        VENDOR | PRODUCT => {
+            let id = parse_hex_u16(atom_value(value, vars, name)?)
+                .ok_or_else(|| anyhow_expr!(value, "{name} expects a hexadecimal id, e.g. 046d"))?;
+            if matcher.vendor.is_some() && matcher.product.is_some() {
+                bail_expr!(value, "{name} can not be used together with {ID}");
+            }
+            match name {
+                VENDOR => matcher.vendor = Some(id),
+                _ => matcher.product = Some(id),
+            }
+        }
+        ID => {
+            const ERRMSG: &str =
+                "id expects a hexadecimal vendor and product id separated by a colon, e.g. 046d:c52b";
+            if matcher.vendor.is_some() || matcher.product.is_some() {
+                bail_expr!(
+                    value,
+                    "{ID} can not be used together with {VENDOR} or {PRODUCT}"
+                );
+            }
+            let id = atom_value(value, vars, ID)?;
+            let Some((vendor, product)) = id.split_once(':') else {
+                bail_expr!(value, "{ERRMSG}");
+            };
+            match (parse_hex_u16(vendor), parse_hex_u16(product)) {
+                (Some(vendor), Some(product)) => {
+                    matcher.vendor = Some(vendor);
+                    matcher.product = Some(product);
+                }
+                _ => bail_expr!(value, "{ERRMSG}"),
+            }
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn parse_pattern(
+    expr: &SExpr,
+    vars: Option<&HashMap<String, SExpr>>,
+    label: &str,
+) -> Result<DevicePattern> {
+    const ERRMSG: &str = "expects a glob pattern string or (regex \"<pattern>\")";
+    if let Some(glob) = expr.atom(vars) {
+        return Ok(DevicePattern::Glob(glob.trim_atom_quotes().to_owned()));
+    }
+    let list = expr.list(vars).unwrap_or_default();
+    match list {
+        [kind, pattern] if kind.atom(vars) == Some("regex") => {
+            let pattern = pattern
+                .atom(vars)
+                .map(|p| p.trim_atom_quotes())
+                .ok_or_else(|| anyhow_expr!(pattern, "{label} {ERRMSG}"))?;
+            regex::Regex::new(pattern)
+                .map(DevicePattern::Regex)
+                .map_err(|e| anyhow_expr!(expr, "Invalid regex for {label}: {e}"))
+        }
+        _ => bail_expr!(expr, "{label} {ERRMSG}"),
+    }
+}
+
+fn parse_hex_u16(s: &str) -> Option<u16> {
+    u16::from_str_radix(s, 16).ok()
+}
+
+fn atom_value<'a>(
+    expr: &'a SExpr,
+    vars: Option<&'a HashMap<String, SExpr>>,
+    label: &str,
+) -> Result<&'a str> {
+    expr.atom(vars)
+        .map(|a| a.trim_atom_quotes())
+        .ok_or_else(|| anyhow_expr!(expr, "The value for {label} can't be a list"))
+}
@@ -77,12 +77,102 @@ struct CfgError {
     file_content: Option<String>,
 }
 
-pub(super) fn help(err_msg: impl AsRef<str>) -> String {
-    format!(
-        r"{}
+const HELP_SUFFIX: &str = r"
 
 For more info, see the configuration guide:
-https://github.com/jtroo/kanata/blob/main/docs/config.adoc",
-        err_msg.as_ref(),
-    )
+https://github.com/jtroo/kanata/blob/main/docs/config.adoc";
+
+pub(super) fn help(err_msg: impl AsRef<str>) -> String {
+    format!("{}{HELP_SUFFIX}", err_msg.as_ref())
+}
+
+/// A configuration error with its location, for tools such as editors that show errors inline.
+#[derive(Debug, Clone)]
+pub struct CfgDiagnostic {
+    /// The description of the error.
+    pub message: String,
+    /// Further help shown after the message, such as where to find more info.
+    pub help: Option<String>,
+    /// Name of the file that contains the error.
+    pub file_name: Option<String>,
+    pub span: Option<DiagnosticSpan>,
+    /// The error formatted for display in a terminal.
+    formatted: String,
+}
+
+/// Location of an error. Offsets are in bytes, while lines and columns start at 1 and columns
+/// count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticSpan {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl CfgDiagnostic {
+    pub fn from_report(report: &miette::Report) -> Self {
+        // The help of a configuration error is the message followed by HELP_SUFFIX.
+        let (message, help) = match report.help().map(|help| help.to_string()) {
+            Some(help) => match help.strip_suffix(HELP_SUFFIX) {
+                Some(message) => (message.to_string(), Some(HELP_SUFFIX.trim().to_string())),
+                None => (help, None),
+            },
+            None => (report.to_string(), None),
+        };
+        let label = report.labels().and_then(|mut labels| labels.next());
+        let source = report.source_code();
+        let mut file_name = None;
+        let span = match (label, source) {
+            (Some(label), Some(source)) => {
+                let (start, end) = (label.offset(), label.offset() + label.len());
+                file_name = source
+                    .read_span(label.inner(), 0, 0)
+                    .ok()
+                    .and_then(|contents| contents.name().map(str::to_string));
+                let (start_line, start_column) = line_column(source, start);
+                let (end_line, end_column) = line_column(source, end);
+                Some(DiagnosticSpan {
+                    start,
+                    end,
+                    start_line,
+                    start_column,
+                    end_line,
+                    end_column,
+                })
+            }
+            _ => None,
+        };
+        Self {
+            message,
+            help,
+            file_name,
+            span,
+            formatted: format!("{report:?}"),
+        }
+    }
+}
+
+impl std::fmt::Display for CfgDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.formatted)
+    }
+}
+
+impl std::error::Error for CfgDiagnostic {}
+
+/// Returns the 1-based line and column of a byte offset.
+fn line_column(source: &dyn miette::SourceCode, offset: usize) -> (usize, usize) {
+    if offset == 0 {
+        return (1, 1);
+    }
+    let Ok(contents) = source.read_span(&SourceSpan::new(0.into(), offset.into()), 0, 0) else {
+        return (1, 1);
+    };
+    let before = String::from_utf8_lossy(contents.data());
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
 }
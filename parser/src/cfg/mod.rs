@@ -96,6 +96,15 @@ use permutations::*;
 mod zippychord;
 pub use zippychord::*;
 
+mod steno;
+pub use steno::*;
+
+mod autoshift;
+use autoshift::*;
+
+mod defdevice;
+pub use defdevice::*;
+
 use crate::lsp_hints::{self, LspHints};
 
 mod str_ext;
@@ -240,13 +249,13 @@ impl KanataLayout {
     }
 
     /// bm stands for borrow mut.
-    pub fn bm(&mut self) -> &mut BorrowedKLayout {
+    pub fn bm(&mut self) -> &mut BorrowedKLayout<'_> {
         // shrink the lifetime
         unsafe { std::mem::transmute(&mut self.layout) }
     }
 
     /// b stands for borrow.
-    pub fn b(&self) -> &BorrowedKLayout {
+    pub fn b(&self) -> &BorrowedKLayout<'_> {
         // shrink the lifetime
         unsafe { std::mem::transmute(&self.layout) }
     }
@@ -262,6 +271,8 @@ pub struct Cfg {
     pub key_outputs: KeyOutputs,
     /// Layer info used for printing to the logs.
     pub layer_info: Vec<LayerInfo>,
+    /// The keys of `defsrc` in the order they are written.
+    pub defsrc: Vec<OsCode>,
     /// Configuration items in `defcfg`.
     pub options: CfgOptions,
     /// The keyberon layout state machine struct.
@@ -276,6 +287,10 @@ pub struct Cfg {
     pub switch_max_key_timing: u16,
     /// Zipchord-like configuration.
     pub zippy: Option<(ZchPossibleChords, ZchConfig)>,
+    /// Steno configuration and dictionary.
+    pub steno: Option<StenoConfig>,
+    /// Per-device configuration sections defined in `defdevice`.
+    pub devices: Vec<DeviceCfg>,
 }
 
 /// Parse a new configuration from a file.
@@ -313,6 +328,7 @@ pub fn new_from_str(cfg_text: &str, file_content: HashMap<String, String>) -> MR
         allocations,
     );
     layout.bm().chords_v2 = icfg.chords_v2;
+    layout.bm().autoshift = icfg.autoshift;
     layout.bm().quick_tap_hold_timeout = icfg.options.concurrent_tap_hold;
     layout.bm().oneshot.on_press_release_delay = icfg.options.rapid_event_delay;
     let mut fake_keys: HashMap<String, usize> = s
@@ -326,6 +342,7 @@ pub fn new_from_str(cfg_text: &str, file_content: HashMap<String, String>) -> MR
         options: icfg.options,
         mapped_keys: icfg.mapped_keys,
         layer_info: icfg.layer_info,
+        defsrc: defsrc_keys(&s),
         key_outputs,
         layout,
         sequences: icfg.sequences,
@@ -333,11 +350,20 @@ pub fn new_from_str(cfg_text: &str, file_content: HashMap<String, String>) -> MR
         fake_keys,
         switch_max_key_timing,
         zippy: icfg.zippy,
+        steno: icfg.steno,
+        devices: icfg.devices,
     })
 }
 
 pub type MappedKeys = HashSet<OsCode>;
 
+fn defsrc_keys(s: &ParserState) -> Vec<OsCode> {
+    s.mapping_order
+        .iter()
+        .filter_map(|&code| OsCode::try_from(code).ok())
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct LayerInfo {
     pub name: String,
@@ -362,6 +388,7 @@ fn parse_cfg(p: &Path) -> MResult<Cfg> {
         allocations,
     );
     layout.bm().chords_v2 = icfg.chords_v2;
+    layout.bm().autoshift = icfg.autoshift;
     layout.bm().quick_tap_hold_timeout = icfg.options.concurrent_tap_hold;
     layout.bm().oneshot.on_press_release_delay = icfg.options.rapid_event_delay;
     if let Some(s) = icfg.start_action {
@@ -378,6 +405,7 @@ fn parse_cfg(p: &Path) -> MResult<Cfg> {
         options: icfg.options,
         mapped_keys: icfg.mapped_keys,
         layer_info: icfg.layer_info,
+        defsrc: defsrc_keys(&s),
         key_outputs,
         layout,
         sequences: icfg.sequences,
@@ -385,6 +413,8 @@ fn parse_cfg(p: &Path) -> MResult<Cfg> {
         fake_keys,
         switch_max_key_timing,
         zippy: icfg.zippy,
+        steno: icfg.steno,
+        devices: icfg.devices,
     })
 }
 
@@ -420,8 +450,11 @@ pub struct IntermediateCfg {
     pub sequences: KeySeqsToFKeys,
     pub overrides: Overrides,
     pub chords_v2: Option<ChordsV2<'static, KanataCustom>>,
+    pub autoshift: Option<AutoShift<'static>>,
     pub start_action: Option<&'static KanataAction>,
     pub zippy: Option<(ZchPossibleChords, ZchConfig)>,
+    pub steno: Option<StenoConfig>,
+    pub devices: Vec<DeviceCfg>,
 }
 
 // A snapshot of enviroment variables, or an error message with an explanation
@@ -558,6 +591,9 @@ pub fn parse_cfg_raw_string(
     env_vars: EnvVars,
 ) -> Result<IntermediateCfg> {
     let mut lsp_hints: LspHints = Default::default();
+    // Discard references left over by a previous parse that failed.
+    #[cfg(feature = "lsp")]
+    LSP_VARIABLE_REFERENCES.with_borrow_mut(|refs| refs.0.clear());
 
     let spanned_root_exprs = sexpr::parse(text, &cfg_path.to_string_lossy())
         .and_then(|xs| expand_includes(xs, file_content_provider, &mut lsp_hints))
@@ -687,7 +723,6 @@ pub fn parse_cfg_raw_string(
     let layer_exprs = spanned_root_exprs
         .iter()
         .filter(deflayer_spanned_filter)
-        .cloned()
         .map(|e| match e.t[0].atom(None).unwrap() {
             DEFLAYER => SpannedLayerExprs::DefsrcMapping(e.clone()),
             DEFLAYER_MAPPED => SpannedLayerExprs::CustomMapping(e.clone()),
@@ -744,7 +779,6 @@ pub fn parse_cfg_raw_string(
     let layer_exprs = root_exprs
         .iter()
         .filter(deflayer_filter)
-        .cloned()
         .map(|e| match e[0].atom(None).unwrap() {
             DEFLAYER => LayerExprs::DefsrcMapping(e.clone()),
             DEFLAYER_MAPPED => LayerExprs::CustomMapping(e.clone()),
@@ -824,6 +858,27 @@ pub fn parse_cfg_raw_string(
     let mut klayers = parse_layers(s, &mut mapped_keys, &cfg)?;
 
     resolve_chord_groups(&mut klayers, s)?;
+
+    let autoshift_exprs = root_exprs
+        .iter()
+        .filter(gen_first_atom_filter("defautoshift"))
+        .collect::<Vec<_>>();
+    let autoshift = match autoshift_exprs.len() {
+        0 => None,
+        1 => Some(parse_autoshift(autoshift_exprs[0], s)?),
+        _ => {
+            let spanned = spanned_root_exprs
+                .iter()
+                .filter(gen_first_atom_filter_spanned("defautoshift"))
+                .nth(1)
+                .expect("> 2 defautoshift");
+            bail_span!(
+                spanned,
+                "Only one defautoshift allowed, found more.\nDelete the extras."
+            )
+        }
+    };
+
     let layers = s.a.bref_slice(klayers);
     s.layers = layers;
     let override_exprs = root_exprs
@@ -867,8 +922,12 @@ pub fn parse_cfg_raw_string(
     let chords_v2 = match chords_v2_exprs.len() {
         0 => None,
         1 => {
-            let cfks = parse_defchordv2(chords_v2_exprs[0], s)?;
-            Some(ChordsV2::new(cfks, cfg.chords_v2_min_idle))
+            let cfks = parse_defchordv2(chords_v2_exprs[0], s, cfg.chords_v2_require_prior_idle)?;
+            Some(ChordsV2::new(
+                cfks,
+                cfg.chords_v2_min_idle,
+                cfg.chords_v2_queue_size.into(),
+            ))
         }
         _ => {
             let spanned = spanned_root_exprs
@@ -914,6 +973,32 @@ pub fn parse_cfg_raw_string(
         }
     };
 
+    let steno_exprs = root_exprs
+        .iter()
+        .filter(gen_first_atom_filter("defsteno"))
+        .collect::<Vec<_>>();
+    let steno = match steno_exprs.len() {
+        0 => None,
+        1 => Some(parse_steno(steno_exprs[0], s, file_content_provider)?),
+        _ => {
+            let spanned = spanned_root_exprs
+                .iter()
+                .filter(gen_first_atom_filter_spanned("defsteno"))
+                .nth(1)
+                .expect("> 2 overrides");
+            bail_span!(
+                spanned,
+                "Only one defsteno allowed, found more.\nDelete the extras."
+            )
+        }
+    };
+
+    let device_exprs = root_exprs
+        .iter()
+        .filter(gen_first_atom_filter("defdevice"))
+        .collect::<Vec<_>>();
+    let devices = parse_defdevices(&device_exprs, s, &mut mapped_keys)?;
+
     #[cfg(feature = "lsp")]
     LSP_VARIABLE_REFERENCES.with_borrow_mut(|refs| {
         s.lsp_hints
@@ -933,8 +1018,11 @@ pub fn parse_cfg_raw_string(
         sequences,
         overrides,
         chords_v2,
+        autoshift,
         start_action,
         zippy,
+        steno,
+        devices,
     })
 }
 
@@ -970,6 +1058,9 @@ fn error_on_unknown_top_level_atoms(exprs: &[Spanned<Vec<SExpr>>]) -> Result<()>
                 | "defchordsv2"
                 | "defchordsv2-experimental"
                 | "defzippy-experimental"
+                | "defsteno"
+                | "defautoshift"
+                | "defdevice"
                 | "defseq" => Ok(()),
                 _ => err_span!(expr, "Found unknown configuration item"),
             })
@@ -1123,6 +1214,9 @@ fn parse_defsrc(
                 | OsCode::BTN_MIDDLE
                 | OsCode::BTN_SIDE
                 | OsCode::BTN_EXTRA
+                | OsCode::BTN_FORWARD
+                | OsCode::BTN_BACK
+                | OsCode::BTN_TASK
                 | OsCode::MouseWheelUp
                 | OsCode::MouseWheelDown
                 | OsCode::MouseWheelLeft
@@ -1327,6 +1421,16 @@ impl ParserState {
     }
 }
 
+#[cfg(feature = "lsp")]
+impl ParserState {
+    /// Returns the parsed aliases and their actions, for tools such as the language server.
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &KanataAction)> {
+        self.aliases
+            .iter()
+            .map(|(name, action)| (name.as_str(), *action))
+    }
+}
+
 impl Default for ParserState {
     fn default() -> Self {
         let default_cfg = CfgOptions::default();
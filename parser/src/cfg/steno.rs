@@ -0,0 +1,544 @@
+//! Steno (stenography) parsing, using Plover-format JSON dictionaries.
+//!
+//! A stroke is made up of all steno keys pressed
+//! between the first press and the release of all keys.
+//! Strokes are written in the Plover steno order, e.g.
+//!
+//! - (S- T- K- P- W-)  -> "STKPW"
+//! - (-T)              -> "-T"
+//! - (K- A- -T)        -> "KAT"
+//! - (# S- -D)         -> "1-D"
+//!
+//! Dictionary entries can contain multiple strokes separated by `/`,
+//! e.g. "KAT/-S": "cats".
+use super::*;
+
+use crate::bail_expr;
+
+/// Steno keys in steno order.
+/// The index of a key in this list is its bit position in a stroke.
+pub const STENO_ORDER: [&str; 23] = [
+    "#", "S-", "T-", "K-", "P-", "W-", "H-", "R-", "A-", "O-", "*", "-E", "-U", "-F", "-R", "-P",
+    "-B", "-L", "-G", "-T", "-S", "-D", "-Z",
+];
+
+const STN_NUMBER_BAR: usize = 0;
+const STN_FIRST_MIDDLE: usize = 8;
+const STN_LAST_MIDDLE: usize = 12;
+
+/// The digits typed by the keys in steno order when combined with the number bar.
+const STENO_DIGITS: [Option<char>; 23] = [
+    None,
+    Some('1'),
+    Some('2'),
+    None,
+    Some('3'),
+    None,
+    Some('4'),
+    None,
+    Some('5'),
+    Some('0'),
+    None,
+    None,
+    None,
+    Some('6'),
+    None,
+    Some('7'),
+    None,
+    Some('8'),
+    None,
+    Some('9'),
+    None,
+    None,
+    None,
+];
+
+/// A set of steno keys pressed together, as bits indexed by `STENO_ORDER`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StenoStroke(pub u32);
+
+impl StenoStroke {
+    pub fn stn_insert(&mut self, key: StenoKey) {
+        self.0 |= 1 << key.0;
+    }
+    pub fn stn_contains(self, key: usize) -> bool {
+        self.0 & (1 << key) != 0
+    }
+    pub fn stn_is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl std::fmt::Display for StenoStroke {
+    /// Writes the stroke in the normalized form used by Plover dictionaries.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let has_digit = self.stn_contains(STN_NUMBER_BAR)
+            && (0..STENO_ORDER.len()).any(|i| self.stn_contains(i) && STENO_DIGITS[i].is_some());
+        let has_middle = (STN_FIRST_MIDDLE..=STN_LAST_MIDDLE).any(|i| self.stn_contains(i));
+        let mut s = String::new();
+        let mut needs_hyphen = !has_middle;
+        for (i, key) in STENO_ORDER.iter().enumerate() {
+            if !self.stn_contains(i) {
+                continue;
+            }
+            if i == STN_NUMBER_BAR && has_digit {
+                continue;
+            }
+            if i > STN_LAST_MIDDLE && needs_hyphen {
+                needs_hyphen = false;
+                s.push('-');
+            }
+            match (has_digit, STENO_DIGITS[i]) {
+                (true, Some(digit)) => s.push(digit),
+                _ => s.push_str(key.trim_matches('-')),
+            }
+        }
+        write!(f, "{s}")
+    }
+}
+
+/// Index of a key in `STENO_ORDER`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StenoKey(pub u8);
+
+impl StenoKey {
+    pub fn stn_from_str(s: &str) -> Option<Self> {
+        let s = match s {
+            "A" => "A-",
+            "O" => "O-",
+            "E" => "-E",
+            "U" => "-U",
+            s => s,
+        };
+        STENO_ORDER
+            .iter()
+            .position(|k| *k == s)
+            .map(|i| StenoKey(i as u8))
+    }
+}
+
+/// A character to type for a steno translation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StenoOutput {
+    pub stn_osc: OsCode,
+    pub stn_shifted: bool,
+}
+
+impl StenoOutput {
+    /// Convert a character to the key to type for it, assuming a US layout.
+    pub fn stn_from_char(c: char) -> Option<Self> {
+        const SHIFTED: &[(char, char)] = &[
+            ('!', '1'),
+            ('@', '2'),
+            ('#', '3'),
+            ('$', '4'),
+            ('%', '5'),
+            ('^', '6'),
+            ('&', '7'),
+            ('*', '8'),
+            ('(', '9'),
+            (')', '0'),
+            ('_', '-'),
+            ('+', '='),
+            ('{', '['),
+            ('}', ']'),
+            ('|', '\\'),
+            (':', ';'),
+            ('"', '\''),
+            ('<', ','),
+            ('>', '.'),
+            ('?', '/'),
+            ('~', '`'),
+        ];
+        let (c, stn_shifted) = match SHIFTED.iter().find(|(shifted, _)| *shifted == c) {
+            Some((_, unshifted)) => (*unshifted, true),
+            None if c.is_ascii_uppercase() => (c.to_ascii_lowercase(), true),
+            None => (c, false),
+        };
+        let stn_osc = match c {
+            ' ' => OsCode::KEY_SPACE,
+            c if c.is_ascii_graphic() => {
+                let mut buf = [0; 4];
+                str_to_oscode(c.encode_utf8(&mut buf))?
+            }
+            _ => return None,
+        };
+        Some(Self {
+            stn_osc,
+            stn_shifted,
+        })
+    }
+    pub fn stn_is_letter(self) -> bool {
+        use OsCode::*;
+        matches!(
+            self.stn_osc,
+            KEY_A
+                | KEY_B
+                | KEY_C
+                | KEY_D
+                | KEY_E
+                | KEY_F
+                | KEY_G
+                | KEY_H
+                | KEY_I
+                | KEY_J
+                | KEY_K
+                | KEY_L
+                | KEY_M
+                | KEY_N
+                | KEY_O
+                | KEY_P
+                | KEY_Q
+                | KEY_R
+                | KEY_S
+                | KEY_T
+                | KEY_U
+                | KEY_V
+                | KEY_W
+                | KEY_X
+                | KEY_Y
+                | KEY_Z
+        )
+    }
+}
+
+/// A dictionary translation, with the formatting supported from Plover.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StenoTranslation {
+    /// Characters to type, not including any space added before the translation.
+    pub stn_text: Box<[StenoOutput]>,
+    /// Do not add a space before this translation, e.g. `{^ing}`.
+    pub stn_attach_start: bool,
+    /// Do not add a space before the next translation, e.g. `{pre^}`.
+    pub stn_attach_end: bool,
+    /// Fingerspelling glue, e.g. `{&a}`.
+    /// Attaches to the previous translation if it was also glued.
+    pub stn_glue: bool,
+    /// Capitalize the next translation, e.g. `{.}` or `{-|}`.
+    pub stn_capitalize_next: bool,
+    /// This translation undoes the previous one, i.e. `=undo`.
+    pub stn_undo: bool,
+}
+
+impl StenoTranslation {
+    /// Parse a Plover translation.
+    /// Returns `None` if the translation uses unsupported features
+    /// or contains characters that cannot be typed.
+    pub fn stn_parse(translation: &str) -> Option<Self> {
+        let mut t = StenoTranslation::default();
+        if translation == "=undo" {
+            t.stn_undo = true;
+            return Some(t);
+        }
+        let mut text = String::new();
+        let mut rem = translation;
+        let mut is_start = true;
+        while !rem.is_empty() {
+            let Some(meta_start) = rem.find('{') else {
+                text.push_str(rem);
+                t.stn_attach_end = false;
+                t.stn_capitalize_next = false;
+                break;
+            };
+            if meta_start > 0 {
+                text.push_str(&rem[..meta_start]);
+                is_start = false;
+                t.stn_attach_end = false;
+                t.stn_capitalize_next = false;
+            }
+            let meta_end = meta_start + rem[meta_start..].find('}')?;
+            let meta = &rem[meta_start + 1..meta_end];
+            rem = &rem[meta_end + 1..];
+            let is_end = rem.is_empty();
+            match meta {
+                "^" | "" => {
+                    t.stn_attach_start |= is_start;
+                    t.stn_attach_end = is_end;
+                }
+                "." | "?" | "!" => {
+                    t.stn_attach_start |= is_start;
+                    text.push_str(meta);
+                    t.stn_attach_end = false;
+                    t.stn_capitalize_next = true;
+                }
+                "," | ":" | ";" => {
+                    t.stn_attach_start |= is_start;
+                    text.push_str(meta);
+                    t.stn_attach_end = false;
+                    t.stn_capitalize_next = false;
+                }
+                "-|" => {
+                    t.stn_capitalize_next = true;
+                }
+                m if m.starts_with('&') && m.len() > 1 => {
+                    t.stn_glue |= is_start;
+                    text.push_str(&m[1..]);
+                    t.stn_attach_end = false;
+                    t.stn_capitalize_next = false;
+                }
+                m if m.starts_with('^') || m.ends_with('^') => {
+                    t.stn_attach_start |= is_start && m.starts_with('^');
+                    text.push_str(m.trim_matches('^'));
+                    t.stn_attach_end = is_end && m.ends_with('^') && m.len() > 1;
+                    t.stn_capitalize_next = false;
+                }
+                _ => return None,
+            }
+            is_start = false;
+        }
+        t.stn_text = text
+            .chars()
+            .map(StenoOutput::stn_from_char)
+            .collect::<Option<Vec<_>>>()?
+            .into_boxed_slice();
+        Some(t)
+    }
+}
+
+/// Steno configuration and dictionary.
+#[derive(Debug, Clone, Default)]
+pub struct StenoConfig {
+    /// Dictionary keyed by strokes joined with `/`.
+    pub stn_dictionary: HashMap<String, Arc<StenoTranslation>>,
+    /// The largest number of strokes of any dictionary entry.
+    pub stn_max_strokes: usize,
+    /// Output keys that are steno keys.
+    pub stn_keys: HashMap<OsCode, StenoKey>,
+    /// Layers on which steno is active. If empty, steno is active on all layers.
+    pub stn_layers: Vec<usize>,
+}
+
+impl StenoConfig {
+    pub fn stn_is_empty(&self) -> bool {
+        self.stn_keys.is_empty()
+    }
+}
+
+/// Plover's default mapping of a QWERTY keyboard to steno keys.
+#[cfg(feature = "steno")]
+const DEFAULT_STENO_KEYS: &[(&str, &str)] = &[
+    ("1", "#"),
+    ("2", "#"),
+    ("3", "#"),
+    ("4", "#"),
+    ("5", "#"),
+    ("6", "#"),
+    ("7", "#"),
+    ("8", "#"),
+    ("9", "#"),
+    ("0", "#"),
+    ("q", "S-"),
+    ("a", "S-"),
+    ("w", "T-"),
+    ("s", "K-"),
+    ("e", "P-"),
+    ("d", "W-"),
+    ("r", "H-"),
+    ("f", "R-"),
+    ("c", "A-"),
+    ("v", "O-"),
+    ("t", "*"),
+    ("g", "*"),
+    ("y", "*"),
+    ("h", "*"),
+    ("n", "-E"),
+    ("m", "-U"),
+    ("u", "-F"),
+    ("j", "-R"),
+    ("i", "-P"),
+    ("k", "-B"),
+    ("o", "-L"),
+    ("l", "-G"),
+    ("p", "-T"),
+    (";", "-S"),
+    ("[", "-D"),
+    ("'", "-Z"),
+];
+
+pub(crate) fn parse_steno(
+    exprs: &[SExpr],
+    s: &ParserState,
+    f: &mut FileContentProvider,
+) -> Result<StenoConfig> {
+    parse_steno_inner(exprs, s, f)
+}
+
+#[cfg(not(feature = "steno"))]
+fn parse_steno_inner(
+    exprs: &[SExpr],
+    _s: &ParserState,
+    _f: &mut FileContentProvider,
+) -> Result<StenoConfig> {
+    bail_expr!(
+        &exprs[0],
+        "Kanata was not compiled with the \"steno\" feature. This configuration is unsupported"
+    )
+}
+
+#[cfg(feature = "steno")]
+fn parse_steno_inner(
+    exprs: &[SExpr],
+    s: &ParserState,
+    f: &mut FileContentProvider,
+) -> Result<StenoConfig> {
+    use crate::anyhow_expr;
+
+    if exprs.len() < 2 {
+        bail_expr!(
+            &exprs[0],
+            "There must be a dictionary filename or a list of filenames following defsteno."
+        );
+    }
+
+    let file_names: Vec<&str> = match &exprs[1] {
+        SExpr::Atom(_) => vec![exprs[1].atom(s.vars()).expect("is atom")],
+        SExpr::List(_) => exprs[1]
+            .list(s.vars())
+            .expect("is list")
+            .iter()
+            .map(|e| {
+                e.atom(s.vars())
+                    .ok_or_else(|| anyhow_expr!(e, "Filename must be a string, not a list."))
+            })
+            .collect::<Result<_>>()?,
+    };
+    if file_names.is_empty() {
+        bail_expr!(&exprs[1], "At least one dictionary filename is required.");
+    }
+
+    let mut config = StenoConfig::default();
+
+    const STENO_KEYS: &str = "steno-keys";
+    const LAYERS: &str = "layers";
+
+    let mut steno_keys_seen = false;
+    let mut layers_seen = false;
+
+    let mut pairs = exprs[2..].chunks_exact(2);
+    for pair in pairs.by_ref() {
+        let config_name = &pair[0];
+        let config_value = &pair[1];
+
+        match config_name.atom(s.vars()).ok_or_else(|| {
+            anyhow_expr!(
+                config_name,
+                "A configuration name must be a string, not a list"
+            )
+        })? {
+            STENO_KEYS => {
+                if steno_keys_seen {
+                    bail_expr!(
+                        config_name,
+                        "This is the 2nd instance; it can only be defined once"
+                    );
+                }
+                steno_keys_seen = true;
+                let mut mappings = config_value
+                    .list(s.vars())
+                    .ok_or_else(|| {
+                        anyhow_expr!(config_value, "{STENO_KEYS} must be followed by a list")
+                    })?
+                    .chunks_exact(2);
+                for mapping_pair in mappings.by_ref() {
+                    let osc = mapping_pair[0]
+                        .atom(s.vars())
+                        .and_then(str_to_oscode)
+                        .ok_or_else(|| anyhow_expr!(&mapping_pair[0], "Unknown key name"))?;
+                    let steno_key = mapping_pair[1]
+                        .atom(s.vars())
+                        .and_then(StenoKey::stn_from_str)
+                        .ok_or_else(|| {
+                            anyhow_expr!(
+                                &mapping_pair[1],
+                                "Unknown steno key. Valid steno keys are:\n{}",
+                                STENO_ORDER.join(" ")
+                            )
+                        })?;
+                    if config.stn_keys.insert(osc, steno_key).is_some() {
+                        bail_expr!(&mapping_pair[0], "Duplicate key, not allowed");
+                    }
+                }
+                let rem = mappings.remainder();
+                if !rem.is_empty() {
+                    bail_expr!(&rem[0], "Key is missing its steno key mapping");
+                }
+            }
+            LAYERS => {
+                if layers_seen {
+                    bail_expr!(
+                        config_name,
+                        "This is the 2nd instance; it can only be defined once"
+                    );
+                }
+                layers_seen = true;
+                config.stn_layers = config_value
+                    .list(s.vars())
+                    .ok_or_else(|| {
+                        anyhow_expr!(config_value, "{LAYERS} must be followed by a list")
+                    })?
+                    .iter()
+                    .map(|layer| {
+                        layer
+                            .atom(s.vars())
+                            .and_then(|l| s.layer_idxs.get(l))
+                            .copied()
+                            .ok_or_else(|| anyhow_expr!(layer, "Not a known layer name."))
+                    })
+                    .collect::<Result<_>>()?;
+            }
+            _ => bail_expr!(config_name, "Unknown defsteno configuration name"),
+        }
+    }
+
+    let rem = pairs.remainder();
+    if !rem.is_empty() {
+        bail_expr!(&rem[0], "defsteno config name is missing its value");
+    }
+
+    if !steno_keys_seen {
+        config.stn_keys = DEFAULT_STENO_KEYS
+            .iter()
+            .map(|(key, steno_key)| {
+                (
+                    str_to_oscode(key).expect("valid default key"),
+                    StenoKey::stn_from_str(steno_key).expect("valid default steno key"),
+                )
+            })
+            .collect();
+    }
+
+    // Like Plover, earlier dictionaries have priority over later ones.
+    for file_name in file_names {
+        let content = f
+            .get_file_content(file_name.as_ref())
+            .map_err(|e| anyhow_expr!(&exprs[1], "Failed to read file {file_name}:\n{e}"))?;
+        let entries: HashMap<String, String> = serde_json::from_str(&content).map_err(|e| {
+            anyhow_expr!(
+                &exprs[1],
+                "Failed to parse {file_name} as a Plover JSON dictionary:\n{e}"
+            )
+        })?;
+        let mut unsupported_count = 0;
+        for (strokes, translation) in entries {
+            match StenoTranslation::stn_parse(&translation) {
+                Some(t) => {
+                    config.stn_max_strokes =
+                        std::cmp::max(config.stn_max_strokes, strokes.split('/').count());
+                    config
+                        .stn_dictionary
+                        .entry(strokes)
+                        .or_insert_with(|| Arc::new(t));
+                }
+                None => unsupported_count += 1,
+            }
+        }
+        if unsupported_count > 0 {
+            log::warn!(
+                "Skipped {unsupported_count} entries in {file_name} \
+                 that use unsupported formatting or characters"
+            );
+        }
+    }
+    config.stn_dictionary.shrink_to_fit();
+    Ok(config)
+}
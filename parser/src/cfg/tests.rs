@@ -35,7 +35,7 @@ fn init_log() {
     });
 }
 
-fn lock<T>(lk: &Mutex<T>) -> MutexGuard<T> {
+fn lock<T>(lk: &Mutex<T>) -> MutexGuard<'_, T> {
     match lk.lock() {
         Ok(guard) => guard,
         Err(poisoned) => poisoned.into_inner(),
@@ -321,6 +321,43 @@ fn parse_zippychord_file() {
     new_from_file(&std::path::PathBuf::from("./test_cfgs/testzch.kbd")).unwrap();
 }
 
+#[test]
+fn steno_stroke_normalization() {
+    let stroke = |keys: &[&str]| {
+        let mut stroke = StenoStroke::default();
+        for key in keys {
+            stroke.stn_insert(StenoKey::stn_from_str(key).unwrap());
+        }
+        stroke.to_string()
+    };
+    assert_eq!(stroke(&["S-", "T-", "K-", "P-", "W-"]), "STKPW");
+    assert_eq!(stroke(&["-T"]), "-T");
+    assert_eq!(stroke(&["K-", "A-", "-T"]), "KAT");
+    assert_eq!(stroke(&["-T", "-S", "H-", "R-"]), "HR-TS");
+    assert_eq!(stroke(&["*", "-E"]), "*E");
+    assert_eq!(stroke(&["#", "S-", "-D"]), "1-D");
+    assert_eq!(stroke(&["#", "S-", "T-", "A-"]), "125");
+    assert_eq!(stroke(&["#", "-D"]), "#-D");
+}
+
+#[test]
+fn steno_translation_formatting() {
+    let t = StenoTranslation::stn_parse("{^ing}").unwrap();
+    assert!(t.stn_attach_start && !t.stn_attach_end);
+    assert_eq!(t.stn_text.len(), 3);
+    let t = StenoTranslation::stn_parse("{pre^}").unwrap();
+    assert!(!t.stn_attach_start && t.stn_attach_end);
+    let t = StenoTranslation::stn_parse("{.}").unwrap();
+    assert!(t.stn_attach_start && t.stn_capitalize_next);
+    let t = StenoTranslation::stn_parse("{&b}").unwrap();
+    assert!(t.stn_glue);
+    let t = StenoTranslation::stn_parse("Hello, World!").unwrap();
+    assert!(t.stn_text[0].stn_shifted && !t.stn_text[1].stn_shifted);
+    assert!(StenoTranslation::stn_parse("=undo").unwrap().stn_undo);
+    assert!(StenoTranslation::stn_parse("{#Return}").is_none());
+    assert!(StenoTranslation::stn_parse("café").is_none());
+}
+
 #[test]
 fn disallow_nested_tap_hold() {
     let _lk = lock(&CFG_PARSE_LOCK);
@@ -385,6 +422,44 @@ fn unknown_defcfg_item_fails() {
     .expect_err("graceful failure, no panic, also no success");
 }
 
+#[test]
+fn unknown_chord_option_fails() {
+    let cfg = "(defcfg concurrent-tap-hold yes)
+(defsrc)
+(deflayer base)
+(defchordsv2
+  (a b) c 50 all-released () (require-prior-idel 100)
+  (d e) f 50 all-released ()
+)";
+    let e = parse_cfg(cfg)
+        .map(|_| ())
+        .expect_err("unknown option should fail");
+    assert!(e.msg.contains("Unknown chord option"), "{}", e.msg);
+    let span = e.span.expect("error has a span");
+    assert_eq!(&cfg[span.start()..span.end()], "require-prior-idel");
+}
+
+#[test]
+fn unknown_chord_option_on_last_chord_fails() {
+    let cfg = "(defcfg concurrent-tap-hold yes)
+(defsrc)
+(deflayer base)
+(defchordsv2
+  (a b) c 50 all-released ()
+  (d e) f 50 all-released () (prior-idle 100)
+)";
+    let e = parse_cfg(cfg)
+        .map(|_| ())
+        .expect_err("unknown option should fail");
+    assert!(
+        e.msg.contains("Valid options: require-prior-idle"),
+        "{}",
+        e.msg
+    );
+    let span = e.span.expect("error has a span");
+    assert_eq!(&cfg[span.start()..span.end()], "prior-idle");
+}
+
 #[test]
 fn recursive_multi_is_flattened() {
     macro_rules! atom {
@@ -1382,12 +1457,25 @@ fn parse_all_defcfg() {
   linux-dev /dev/input/dev1:/dev/input/dev2
   linux-dev-names-include "Name 1:Name 2"
   linux-dev-names-exclude "Name 3:Name 4"
+  linux-dev-include-match ((name "Name*" bus usb))
+  linux-dev-exclude-match ((id 046d:c52b))
   linux-continue-if-no-devs-found yes
   linux-unicode-u-code v
   linux-unicode-termination space
+  linux-unicode-output compose
+  linux-unicode-keymap-key f24
+  linux-unicode-compose-key ralt
+  linux-unicode-compose-sequences (é (' e) ñ (S-grv n))
   linux-x11-repeat-delay-rate 400,50
+  linux-key-repeat-delay-rate 400,50
+  linux-key-repeat-output repeat
   linux-use-trackpoint-property yes
+  linux-passthru-non-key-events yes
   linux-output-device-bus-type USB
+  linux-output-device-name "kanata keyboard"
+  linux-output-pointer-device-name "kanata pointer"
+  linux-output-pointer-device-bus-type I8042
+  linux-setmouse-screen-extents 1920,1080
   tray-icon symbols.ico
   icon-match-layer-name no
   tooltip-layer-changes yes
@@ -1440,6 +1528,114 @@ fn parse_defcfg_linux_output_bus() {
         .contains("Invalid value for linux-output-device-bus-type"));
 }
 
+#[test]
+#[cfg(target_os = "linux")]
+fn parse_defcfg_linux_unicode_compose_sequences() {
+    let source = r#"
+(defcfg
+  linux-unicode-output compose
+  linux-unicode-compose-sequences (é (' e) "ñ" (S-grv n) ≠ (RA-= /))
+)
+(defsrc a)
+(deflayer base a)
+"#;
+    let cfg = parse_cfg(source)
+        .map_err(|e| eprintln!("{:?}", miette::Error::from(e)))
+        .expect("parses");
+    let opts = &cfg.options.linux_opts;
+    assert_eq!(opts.linux_unicode_output, UnicodeOutput::Compose);
+    assert_eq!(opts.linux_unicode_compose_key, OsCode::KEY_COMPOSE);
+    let sequences = &opts.linux_unicode_compose_sequences;
+    assert_eq!(
+        sequences[&'é'],
+        vec![vec![OsCode::KEY_APOSTROPHE], vec![OsCode::KEY_E]]
+    );
+    assert_eq!(
+        sequences[&'ñ'],
+        vec![
+            vec![OsCode::KEY_LEFTSHIFT, OsCode::KEY_GRAVE],
+            vec![OsCode::KEY_N]
+        ]
+    );
+    assert_eq!(
+        sequences[&'≠'],
+        vec![
+            vec![OsCode::KEY_RIGHTALT, OsCode::KEY_EQUAL],
+            vec![OsCode::KEY_SLASH]
+        ]
+    );
+
+    for (sequences, err) in [
+        ("(é)", "the last character has no keys"),
+        ("(ab (a b))", "Expected a single character"),
+        ("(é ())", "Expected a non-empty list of keys"),
+        ("(é (' e) é (' e))", "Duplicate character"),
+        ("(é (' notakey))", "Unknown key name"),
+    ] {
+        let source = format!(
+            "(defcfg linux-unicode-compose-sequences {sequences}) (defsrc a) (deflayer base a)"
+        );
+        let e = parse_cfg(&source).expect_err("should err");
+        assert!(e.msg.contains(err), "{sequences}: {}", e.msg);
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn parse_defcfg_linux_output_pointer_device() {
+    let source = r#"
+(defcfg
+  linux-output-device-bus-type USB
+  linux-output-pointer-device-name "kanata pointer"
+)
+(defsrc a)
+(deflayer base a)
+"#;
+    let cfg = parse_cfg(source)
+        .map_err(|e| eprintln!("{:?}", miette::Error::from(e)))
+        .expect("parses");
+    let opts = &cfg.options.linux_opts;
+    assert_eq!(opts.linux_output_device_name, "kanata");
+    assert_eq!(
+        opts.linux_output_pointer_device_name.as_deref(),
+        Some("kanata pointer")
+    );
+    assert!(opts.linux_output_pointer_bus_type.is_none());
+    let source = r#"
+(defcfg
+  linux-output-device-name "same name"
+  linux-output-pointer-device-name "same name"
+)
+(defsrc a)
+(deflayer base a)
+"#;
+    let err = parse_cfg(source).expect_err("should err");
+    assert!(err
+        .msg
+        .contains("linux-output-pointer-device-name must be different"));
+}
+
+#[test]
+fn parse_defcfg_linux_setmouse_screen_extents() {
+    let source = r#"
+(defcfg linux-setmouse-screen-extents 2560,1440)
+(defsrc a)
+(deflayer base a)
+"#;
+    parse_cfg(source)
+        .map_err(|e| eprintln!("{:?}", miette::Error::from(e)))
+        .expect("parses");
+    for invalid in ["2560", "0,1440", "2560,1440,1", "a,b"] {
+        let source = format!(
+            "(defcfg linux-setmouse-screen-extents {invalid})\n(defsrc a)\n(deflayer base a)"
+        );
+        let err = parse_cfg(&source).expect_err("should err");
+        assert!(err
+            .msg
+            .contains("Invalid value for linux-setmouse-screen-extents"));
+    }
+}
+
 #[test]
 fn parse_unmod() {
     let source = r#"
@@ -1933,7 +2129,7 @@ fn test_defaliasenvcond() {
         ""
     })
     .expect("parses");
-    assert!(s.aliases.get("a").is_none());
+    assert!(!s.aliases.contains_key("a"));
 }
 
 #[test]
@@ -2077,3 +2273,263 @@ fn reverse_release_order_must_be_within_multi() {
         "reverse-release-order is only allowed inside of a (multi ...) action list"
     );
 }
+
+#[test]
+fn parse_defdevice() {
+    let source = r#"
+(defsrc a b c)
+(deflayer base a b c)
+(deflayer split x y z)
+(defdevice laptop name "AT Translated Set 2 keyboard" phys isa0060/serio0/input0)
+(defdevice split id 046d:C52B layer split defsrc (q w e) on-disconnect (layer-switch base))
+"#;
+    let icfg = parse_cfg(source)
+        .map_err(|e| eprintln!("{:?}", miette::Error::from(e)))
+        .expect("parses");
+    assert_eq!(icfg.devices.len(), 2);
+    assert!(icfg.mapped_keys.contains(&OsCode::KEY_Q));
+
+    let laptop = DeviceInfo {
+        name: "AT Translated Set 2 keyboard",
+        phys: "isa0060/serio0/input0",
+        uniq: "",
+        bus: 0x11,
+        vendor: 1,
+        product: 1,
+    };
+    let split = DeviceInfo {
+        name: "Logitech USB Receiver",
+        phys: "usb-0000:00:14.0-1/input0",
+        uniq: "",
+        bus: 0x03,
+        vendor: 0x046d,
+        product: 0xc52b,
+    };
+    let other = DeviceInfo {
+        phys: "isa0060/serio1/input0",
+        ..laptop
+    };
+    assert_eq!(find_device_cfg(&icfg.devices, &laptop), Some(0));
+    assert_eq!(find_device_cfg(&icfg.devices, &split), Some(1));
+    assert_eq!(find_device_cfg(&icfg.devices, &other), None);
+
+    assert_eq!(icfg.devices[0].layer, None);
+    assert_eq!(icfg.devices[0].map_key(OsCode::KEY_Q), Some(OsCode::KEY_Q));
+    assert_eq!(icfg.devices[1].layer, Some(1));
+    assert_eq!(icfg.devices[1].map_key(OsCode::KEY_W), Some(OsCode::KEY_B));
+    assert_eq!(icfg.devices[1].map_key(OsCode::KEY_A), None);
+    assert!(icfg.devices[0].on_disconnect.is_none());
+    assert!(icfg.devices[1].on_connect.is_none());
+    assert!(icfg.devices[1].on_disconnect.is_some());
+}
+
+#[test]
+fn parse_defdevice_errors() {
+    for (defdevice, err) in [
+        (
+            "(defdevice d layer base)",
+            "defdevice must have at least one of",
+        ),
+        ("(defdevice d id 046d)", "id expects a hexadecimal vendor"),
+        ("(defdevice d name n layer nope)", "Not a known layer name"),
+        ("(defdevice d name n defsrc (q))", "same number of keys"),
+        ("(defdevice d name n defsrc (q q))", "Duplicate key"),
+        ("(defdevice d name n name m)", "2nd instance"),
+        (
+            "(defdevice d id 046d:c52b vendor 046d)",
+            "can not be used together",
+        ),
+        (
+            "(defdevice d vendor 046d product c52b id 046d:c52b)",
+            "can not be used together",
+        ),
+        ("(defdevice d bus serial)", "bus expects one of"),
+        ("(defdevice d name n on-connect @nope)", "unknown alias"),
+        ("(defdevice d name (regex \"(\"))", "Invalid regex"),
+        ("(defdevice d name (glob x))", "expects a glob pattern"),
+        (
+            "(defdevice d name n) (defdevice d name m)",
+            "Duplicate defdevice name",
+        ),
+    ] {
+        let source = format!("(defsrc a b)\n(deflayer base a b)\n{defdevice}");
+        let e = parse_cfg(&source).map(|_| ()).expect_err("fails");
+        assert!(e.msg.contains(err), "{defdevice}: {}", e.msg);
+    }
+}
+
+#[test]
+fn defdevice_patterns() {
+    let source = r#"
+(defsrc a)
+(deflayer base a)
+(defdevice glob name "Logitech*Receiver" phys "usb-????:00:14.0-*")
+(defdevice regex name (regex "^Keychron K[0-9]+$") uniq (regex "dc:2c"))
+(defdevice ids bus bluetooth vendor 05ac)
+(defdevice numeric-bus bus 0x19 product 0001)
+"#;
+    let icfg = parse_cfg(source)
+        .map_err(|e| eprintln!("{:?}", miette::Error::from(e)))
+        .expect("parses");
+    let info = DeviceInfo {
+        name: "Logitech USB Receiver",
+        phys: "usb-0000:00:14.0-1/input0",
+        uniq: "",
+        bus: 0x03,
+        vendor: 0x046d,
+        product: 0xc52b,
+    };
+    assert_eq!(find_device_cfg(&icfg.devices, &info), Some(0));
+    let info = DeviceInfo {
+        phys: "usb-0000:00:14.1-1/input0",
+        ..info
+    };
+    assert_eq!(find_device_cfg(&icfg.devices, &info), None);
+    let info = DeviceInfo {
+        name: "Keychron K8",
+        uniq: "dc:2c:26:00:00:01",
+        bus: 0x05,
+        ..info
+    };
+    assert_eq!(find_device_cfg(&icfg.devices, &info), Some(1));
+    let info = DeviceInfo {
+        name: "Keychron K8 Pro",
+        ..info
+    };
+    assert_eq!(find_device_cfg(&icfg.devices, &info), None);
+    let info = DeviceInfo {
+        vendor: 0x05ac,
+        ..info
+    };
+    assert_eq!(find_device_cfg(&icfg.devices, &info), Some(2));
+    let info = DeviceInfo {
+        bus: 0x19,
+        product: 1,
+        ..info
+    };
+    assert_eq!(find_device_cfg(&icfg.devices, &info), Some(3));
+
+    for (glob, text, matches) in [
+        ("*", "", true),
+        ("a*b*c", "aXbYbZc", true),
+        ("a*b*c", "aXbYbZ", false),
+        ("a?c", "abc", true),
+        ("a?c", "ac", false),
+        ("*kbd", "my-kbd", true),
+        ("kbd", "my-kbd", false),
+    ] {
+        assert_eq!(
+            DevicePattern::Glob(glob.into()).matches(text),
+            matches,
+            "{glob} {text}"
+        );
+    }
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "unknown"))]
+fn parse_defcfg_linux_dev_match() {
+    let source = r#"
+(defcfg
+  linux-dev-include-match ((name "Logi*" bus usb) (id 046d:c52b))
+  linux-dev-exclude-match ((phys (regex "serio1")))
+)
+(defsrc a)
+(deflayer base a)
+"#;
+    let icfg = parse_cfg(source)
+        .map_err(|e| eprintln!("{:?}", miette::Error::from(e)))
+        .expect("parses");
+    let linux_opts = icfg.options.linux_opts;
+    assert_eq!(linux_opts.linux_dev_include_match.len(), 2);
+    assert_eq!(
+        linux_opts.linux_dev_include_match[0],
+        DeviceMatcher {
+            name: Some(DevicePattern::Glob("Logi*".into())),
+            bus: Some(0x03),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        linux_opts.linux_dev_include_match[1],
+        DeviceMatcher {
+            vendor: Some(0x046d),
+            product: Some(0xc52b),
+            ..Default::default()
+        }
+    );
+    assert_eq!(linux_opts.linux_dev_exclude_match.len(), 1);
+
+    for (defcfg, err) in [
+        (
+            "linux-dev-include-match (name x)",
+            "Expected a device matcher list",
+        ),
+        (
+            "linux-dev-include-match name",
+            "expects a list of device matchers",
+        ),
+        ("linux-dev-exclude-match ((nme x))", "Expected one of"),
+        ("linux-dev-exclude-match (())", "must have at least one of"),
+        ("linux-dev-exclude-match ((name))", "missing its value"),
+    ] {
+        let source = format!("(defcfg {defcfg})\n(defsrc a)\n(deflayer base a)");
+        let e = parse_cfg(&source).map(|_| ()).expect_err("fails");
+        assert!(e.msg.contains(err), "{defcfg}: {}", e.msg);
+    }
+}
+
+#[test]
+fn defsrc_keeps_written_order() {
+    let _lk = lock(&CFG_PARSE_LOCK);
+    let cfg =
+        new_from_str("(defsrc c a b) (deflayer base 1 2 3)", Default::default()).expect("parses");
+    assert_eq!(
+        cfg.defsrc,
+        vec![OsCode::KEY_C, OsCode::KEY_A, OsCode::KEY_B]
+    );
+}
+
+#[test]
+fn diagnostic_has_location_of_error() {
+    let _lk = lock(&CFG_PARSE_LOCK);
+    let err = new_from_str(
+        "(defsrc a b) ;; 😊\n(deflayer 😊 x bad-key)",
+        Default::default(),
+    )
+    .map(|_| ())
+    .expect_err("invalid key");
+    let diagnostic = CfgDiagnostic::from_report(&err);
+    assert!(
+        diagnostic.message.starts_with("Unknown key"),
+        "{}",
+        diagnostic.message
+    );
+    assert_eq!(diagnostic.file_name.as_deref(), Some("configuration"));
+    let span = diagnostic.span.expect("has span");
+    assert_eq!(span.end - span.start, "bad-key".len());
+    assert_eq!((span.start_line, span.start_column), (2, 15));
+    assert_eq!((span.end_line, span.end_column), (2, 22));
+    assert!(diagnostic.to_string().contains("Error in configuration"));
+    let help = diagnostic.help.expect("has help");
+    assert!(!help.contains("Unknown key"), "{help}");
+    assert!(help.contains("configuration guide"), "{help}");
+}
+
+#[test]
+fn diagnostic_has_location_in_included_file() {
+    let _lk = lock(&CFG_PARSE_LOCK);
+    let err = new_from_file(
+        &std::path::Path::new(".")
+            .join("test_cfgs")
+            .join("include-bad.kbd"),
+    )
+    .map(|_| ())
+    .expect_err("included file has an error");
+    let diagnostic = CfgDiagnostic::from_report(&err);
+    let file_name = diagnostic.file_name.expect("has file name");
+    assert!(file_name.ends_with("included-bad.kbd"), "{file_name}");
+    let span = diagnostic.span.expect("has span");
+    assert_eq!((span.start_line, span.start_column), (1, 1));
+    assert_eq!((span.end_line, span.end_column), (1, 31));
+}
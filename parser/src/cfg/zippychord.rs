@@ -69,7 +69,7 @@ impl ZchInputKeys {
         self.zch_inputs.zch_insert(osc.into());
     }
     pub fn zchik_remove(&mut self, osc: OsCode) {
-        self.zch_inputs.zch_keys.retain(|k| *k != osc.into());
+        self.zch_inputs.zch_keys.retain(|k| *k != u16::from(osc));
     }
     pub fn zchik_len(&self) -> usize {
         self.zch_inputs.zch_keys.len()
@@ -168,209 +168,253 @@ fn add_default_str_osc_mappings(mapping: &mut HashMap<String, OsCode>) {
 ///
 /// Do your best to keep the str side a maximum character length of 4 so that configuration file
 /// can stay clean.
-#[rustfmt::skip]
 pub fn str_to_oscode(s: &str) -> Option<OsCode> {
     if let Some(osc) = CUSTOM_STRS_TO_OSCODES.lock().get(s) {
         return Some(*osc);
     }
-    Some(match s {
-        "Backquote" | "grv" | "ˋ" | "˜" => OsCode::KEY_GRAVE,
-        "Digit1" | "1" => OsCode::KEY_1,
-        "Digit2" | "2" => OsCode::KEY_2,
-        "Digit3" | "3" => OsCode::KEY_3,
-        "Digit4" | "4" => OsCode::KEY_4,
-        "Digit5" | "5" => OsCode::KEY_5,
-        "Digit6" | "6" => OsCode::KEY_6,
-        "Digit7" | "7" => OsCode::KEY_7,
-        "Digit8" | "8" => OsCode::KEY_8,
-        "Digit9" | "9" => OsCode::KEY_9,
-        "Digit0" | "0" => OsCode::KEY_0,
-        "Minus" | "min" | "‐" => OsCode::KEY_MINUS,
-        "Equal" | "eql" | "₌" => OsCode::KEY_EQUAL,
-        "Backspace" | "bspc" | "bks" | "␈" | "⌫"  => OsCode::KEY_BACKSPACE,
-        "Tab" | "tab" | "⭾" | "↹" => OsCode::KEY_TAB,
-        "KeyQ" | "q" => OsCode::KEY_Q,
-        "KeyW" | "w" => OsCode::KEY_W,
-        "KeyE" | "e" => OsCode::KEY_E,
-        "KeyR" | "r" => OsCode::KEY_R,
-        "KeyT" | "t" => OsCode::KEY_T,
-        "KeyY" | "y" => OsCode::KEY_Y,
-        "KeyU" | "u" => OsCode::KEY_U,
-        "KeyI" | "i" => OsCode::KEY_I,
-        "KeyO" | "o" => OsCode::KEY_O,
-        "KeyP" | "p" => OsCode::KEY_P,
-        "BracketLeft" | "lbrc" | "【" | "「" | "〔" | "⎡" => OsCode::KEY_LEFTBRACE,
-        "BracketRight" | "rbrc" | "】" | "」" | "〕" | "⎣" => OsCode::KEY_RIGHTBRACE,
-        "CapsLock" | "caps" | "⇪" => OsCode::KEY_CAPSLOCK,
-        "KeyA" | "a" => OsCode::KEY_A,
-        "KeyS" | "s" => OsCode::KEY_S,
-        "KeyD" | "d" => OsCode::KEY_D,
-        "KeyF" | "f" => OsCode::KEY_F,
-        "KeyG" | "g" => OsCode::KEY_G,
-        "KeyH" | "h" => OsCode::KEY_H,
-        "KeyJ" | "j" => OsCode::KEY_J,
-        "KeyK" | "k" => OsCode::KEY_K,
-        "KeyL" | "l" => OsCode::KEY_L,
-        "Semicolon" | "scln" | "︔" => OsCode::KEY_SEMICOLON,
-        "Quote" | "apo" | "apos" => OsCode::KEY_APOSTROPHE,
-        "Enter" | "ret" | "return" | "ent" | "enter" | "⏎" | "↩" | "⌤" | "␤" => OsCode::KEY_ENTER,
-        "ShiftLeft" | "lshift" | "lshft" | "lsft" | "shft" | "sft" | "‹⇧" => OsCode::KEY_LEFTSHIFT,
-        "KeyZ" | "z" => OsCode::KEY_Z,
-        "KeyX" | "x" => OsCode::KEY_X,
-        "KeyC" | "c" => OsCode::KEY_C,
-        "KeyV" | "v" => OsCode::KEY_V,
-        "KeyB" | "b" => OsCode::KEY_B,
-        "KeyN" | "n" => OsCode::KEY_N,
-        "KeyM" | "m" => OsCode::KEY_M,
-        "Comma" | "comm" | "⸴" => OsCode::KEY_COMMA,
-        "Period" | "．" => OsCode::KEY_DOT,
-        "Slash" | "⁄" => OsCode::KEY_SLASH,
-        "Backslash" | "bksl" | "⧵" | "＼" =>  OsCode::KEY_BACKSLASH,
-        "kp=" | "clr" => OsCode::KEY_CLEAR,
-        // The kp<etc> keys are also known as the numpad keys. E.g. below is numpad enter.
-        "Numpad0" | "kp0" | "🔢₀" => OsCode::KEY_KP0,
-        "Numpad1" | "kp1" | "🔢₁" => OsCode::KEY_KP1,
-        "Numpad2" | "kp2" | "🔢₂" => OsCode::KEY_KP2,
-        "Numpad3" | "kp3" | "🔢₃" => OsCode::KEY_KP3,
-        "Numpad4" | "kp4" | "🔢₄" => OsCode::KEY_KP4,
-        "Numpad5" | "kp5" | "🔢₅" => OsCode::KEY_KP5,
-        "Numpad6" | "kp6" | "🔢₆" => OsCode::KEY_KP6,
-        "Numpad7" | "kp7" | "🔢₇" => OsCode::KEY_KP7,
-        "Numpad8" | "kp8" | "🔢₈" => OsCode::KEY_KP8,
-        "Numpad9" | "kp9" | "🔢₉" => OsCode::KEY_KP9,
-        "NumpadEnter" | "kprt" | "🔢⏎" | "🔢↩" | "🔢⌤" | "🔢␤" => OsCode::KEY_KPENTER,
-        "NumpadDivide" | "kp/" | "🔢⁄" => OsCode::KEY_KPSLASH,
-        "NumpadAdd" | "kp+" | "🔢₊" => OsCode::KEY_KPPLUS,
-        "NumpadMultiply" | "kp*" | "🔢∗" => OsCode::KEY_KPASTERISK,
-        "NumpadEqual" | "🔢₌" => OsCode::KEY_KPEQUAL,
-        "NumpadSubtract" | "kp-" | "🔢₋" => OsCode::KEY_KPMINUS,
-        "NumpadDecimal" | "kp." | "🔢．" => OsCode::KEY_KPDOT,
-        "NumpadComma" | "kp," | "🔢⸴" =>OsCode::KEY_KPCOMMA,
-        "ssrq" | "sys" => OsCode::KEY_SYSRQ,
-        // Typically the Non-US backslash, near the left shift key
-        "IntlBackslash" | "102d" | "lsgt" | "nubs" | "nonusbslash" | "﹨" | "<" => OsCode::KEY_102ND,
-        "ScrollLock" | "scrlck" | "slck" | "⇳🔒" => OsCode::KEY_SCROLLLOCK,
-        "Pause" | "pause" | "break" | "brk" => OsCode::KEY_PAUSE,
-        "WakeUp" | "wkup" => OsCode::KEY_WAKEUP,
-        "Escape" | "esc" | "⎋" => OsCode::KEY_ESC,
-        "ShiftRight" | "RightShift" | "rshift" | "rshft" | "rsft" | "⇧›" => OsCode::KEY_RIGHTSHIFT,
-        "ControlLeft" | "lctrl" | "lctl" | "ctl" | "‹⎈" | "‹⌃" => OsCode::KEY_LEFTCTRL,
-        "AltLeft" | "lalt" | "alt" | "‹⎇" | "‹⌥" => OsCode::KEY_LEFTALT,
-        "Space" | "spc" | "␠" | "␣" => OsCode::KEY_SPACE,
-        "AltRight" | "ralt" | "⎇›" | "⌥›" => OsCode::KEY_RIGHTALT,
-        "ContextMenu" | "comp" | "cmps" | "cmp" | "menu" | "apps" | "▤" | "☰" | "𝌆" => OsCode::KEY_COMPOSE,
-        "🎛" => OsCode::KEY_DASHBOARD,
-        // Also known as Windows, GUI, Comand, Super
-        "MetaLeft" | "lmeta" | "lmet" | "met" | "‹◆" | "‹⌘" | "‹❖" => OsCode::KEY_LEFTMETA,
-        "MetaRight" | "rmeta" | "rmet" | "◆›" | "⌘›" | "❖›"  => OsCode::KEY_RIGHTMETA,
-        "ControlRight" | "rctrl" | "rctl" | "⎈›" | "⌃›" => OsCode::KEY_RIGHTCTRL,
-        "Delete" | "del" | "␡" | "⌦" => OsCode::KEY_DELETE,
-        "Insert" | "ins" | "⎀" => OsCode::KEY_INSERT,
-        "BrowserBack" | "bck" => OsCode::KEY_BACK,
-        "BrowserForward" | "fwd" => OsCode::KEY_FORWARD,
-        "PageUp" | "pgup" | "⇞" => OsCode::KEY_PAGEUP,
-        "PageDown" | "pgdn" | "⇟" => OsCode::KEY_PAGEDOWN,
-        "ArrowUp" | "up" | "▲" => OsCode::KEY_UP,
-        "ArrowDown" | "down" | "▼" => OsCode::KEY_DOWN,
-        "ArrowLeft" | "lft" | "left" | "◀" => OsCode::KEY_LEFT,
-        "ArrowRight" | "rght" | "▶" => OsCode::KEY_RIGHT,
-        "Home" | "home" | "⇤" | "⤒" | "↖" => OsCode::KEY_HOME,
-        "End" | "end" | "⇥" | "⤓" | "↘" => OsCode::KEY_END,
-        "NumLock" | "nlck" | "nlk" | "⇭"=> OsCode::KEY_NUMLOCK,
-        "VolumeMute" | "mute"  | "🔇" | "🔈⓪" | "🔈⓿" | "🔈₀" => OsCode::KEY_MUTE,
-        "VolumeUp" | "volu" | "🔊" | "🔈+" | "🔈➕" | "🔈₊" | "🔈⊕" => OsCode::KEY_VOLUMEUP,
-        "VolumeDown" | "voldwn" | "vold" | "🔉" | "🔈−" | "🔈➖" | "🔈₋" | "🔈⊖" => OsCode::KEY_VOLUMEDOWN,
-        "brup" | "bru" | "🔆" => OsCode::KEY_BRIGHTNESSUP,
-        "brdown" | "brdwn" | "brdn" | "🔅" => OsCode::KEY_BRIGHTNESSDOWN,
-        "blup" | "⌨💡+" | "⌨💡➕" | "⌨💡₊" | "⌨💡⊕" => OsCode::KEY_KBDILLUMUP,
-        "bldn" | "⌨💡−" | "⌨💡➖" | "⌨💡₋" | "⌨💡⊖" => OsCode::KEY_KBDILLUMDOWN,
-        "MediaTrackNext" | "next" | "▶▶" => OsCode::KEY_NEXTSONG,
-        "MediaPlayPause" | "pp" | "▶⏸" => OsCode::KEY_PLAYPAUSE,
-        "MediaTrackPrevious" | "prev" | "◀◀" => OsCode::KEY_PREVIOUSSONG,
-        "F1" | "f1" => OsCode::KEY_F1,
-        "F2" | "f2" => OsCode::KEY_F2,
-        "F3" | "f3" => OsCode::KEY_F3,
-        "F4" | "f4" => OsCode::KEY_F4,
-        "F5" | "f5" => OsCode::KEY_F5,
-        "F6" | "f6" => OsCode::KEY_F6,
-        "F7" | "f7" => OsCode::KEY_F7,
-        "F8" | "f8" => OsCode::KEY_F8,
-        "F9" | "f9" => OsCode::KEY_F9,
-        "F10" | "f10" => OsCode::KEY_F10,
-        "F11" | "f11" => OsCode::KEY_F11,
-        "F12" | "f12" => OsCode::KEY_F12,
-        "F13" | "f13" => OsCode::KEY_F13,
-        "F14" | "f14" => OsCode::KEY_F14,
-        "F15" | "f15" => OsCode::KEY_F15,
-        "F16" | "f16" => OsCode::KEY_F16,
-        "F17" | "f17" => OsCode::KEY_F17,
-        "F18" | "f18" => OsCode::KEY_F18,
-        "F19" | "f19" => OsCode::KEY_F19,
-        "F20" | "f20" => OsCode::KEY_F20,
-        "F21" | "f21" => OsCode::KEY_F21,
-        "F22" | "f22" => OsCode::KEY_F22,
-        "F23" | "f23" => OsCode::KEY_F23,
-        "F24" | "f24" => OsCode::KEY_F24,
-        #[cfg(any(target_os = "macos", target_os = "unknown"))]
-        "fn" | "🌐" | "ƒ" | "ⓕ" | "Ⓕ" | "🄵" | "🅕" | "🅵" => OsCode::KEY_FN,
-        #[cfg(target_os = "windows")]
-        "kana" | "katakana" | "katakanahiragana" => OsCode::KEY_HANGEUL,
-        #[cfg(any(target_os = "linux", target_os = "unknown"))]
-        "kana" | "katakanahiragana" => OsCode::KEY_KATAKANAHIRAGANA,
-        #[cfg(any(target_os = "linux", target_os = "unknown"))]
-        "hiragana" => OsCode::KEY_HIRAGANA,
-        #[cfg(any(target_os = "linux", target_os = "unknown"))]
-        "katakana" => OsCode::KEY_KATAKANA,
-        "cnv" | "conv" | "henk" | "hnk" | "henkan" => OsCode::KEY_HENKAN,
-        "ncnv" | "mhnk" | "muhenkan" => OsCode::KEY_MUHENKAN,
-        "IntlRo" | "ro" => OsCode::KEY_RO,
+    builtin_str_to_oscode(s)
+}
 
-        #[cfg(any(target_os = "linux", target_os = "unknown"))]
-        "PrintScreen" | "prtsc" | "prnt" => OsCode::KEY_SYSRQ,
-        #[cfg(target_os = "windows")]
-        "PrintScreen" | "prtsc" | "prnt" => OsCode::KEY_PRINT,
+/// Defines the built-in key names of [`str_to_oscode`] and their reverse, [`oscode_to_str`],
+/// from one list of `names => key` entries. The first name of the first entry of a key is the
+/// name that `oscode_to_str` returns.
+macro_rules! key_names {
+    ($($(#[$attr:meta])* $name:literal $(| $alias:literal)* => $osc:ident,)*) => {
+        fn builtin_str_to_oscode(s: &str) -> Option<OsCode> {
+            Some(match s {
+                $($(#[$attr])* $name $(| $alias)* => OsCode::$osc,)*
+                _ => return None,
+            })
+        }
+
+        /// Returns a name of the key that [`str_to_oscode`] parses back to the same key,
+        /// ignoring `defcustomkeys`. Returns None for keys without a name.
+        pub fn oscode_to_str(osc: OsCode) -> Option<&'static str> {
+            // A key can have more than one entry, e.g. one per platform, so this is searched in
+            // order instead of matched on, which would make the later entries unreachable.
+            const NAMES: &[(OsCode, &str)] = &[$($(#[$attr])* (OsCode::$osc, $name),)*];
+            NAMES
+                .iter()
+                .find_map(|&(name_osc, name)| (name_osc == osc).then_some(name))
+        }
+    };
+}
+
+key_names! {
+    "grv" | "Backquote" | "ˋ" | "˜" => KEY_GRAVE,
+    "1" | "Digit1" => KEY_1,
+    "2" | "Digit2" => KEY_2,
+    "3" | "Digit3" => KEY_3,
+    "4" | "Digit4" => KEY_4,
+    "5" | "Digit5" => KEY_5,
+    "6" | "Digit6" => KEY_6,
+    "7" | "Digit7" => KEY_7,
+    "8" | "Digit8" => KEY_8,
+    "9" | "Digit9" => KEY_9,
+    "0" | "Digit0" => KEY_0,
+    "min" | "Minus" | "‐" => KEY_MINUS,
+    "eql" | "Equal" | "₌" => KEY_EQUAL,
+    "bspc" | "Backspace" | "bks" | "␈" | "⌫" => KEY_BACKSPACE,
+    "tab" | "Tab" | "⭾" | "↹" => KEY_TAB,
+    "q" | "KeyQ" => KEY_Q,
+    "w" | "KeyW" => KEY_W,
+    "e" | "KeyE" => KEY_E,
+    "r" | "KeyR" => KEY_R,
+    "t" | "KeyT" => KEY_T,
+    "y" | "KeyY" => KEY_Y,
+    "u" | "KeyU" => KEY_U,
+    "i" | "KeyI" => KEY_I,
+    "o" | "KeyO" => KEY_O,
+    "p" | "KeyP" => KEY_P,
+    "lbrc" | "BracketLeft" | "【" | "「" | "〔" | "⎡" => KEY_LEFTBRACE,
+    "rbrc" | "BracketRight" | "】" | "」" | "〕" | "⎣" => KEY_RIGHTBRACE,
+    "caps" | "CapsLock" | "⇪" => KEY_CAPSLOCK,
+    "a" | "KeyA" => KEY_A,
+    "s" | "KeyS" => KEY_S,
+    "d" | "KeyD" => KEY_D,
+    "f" | "KeyF" => KEY_F,
+    "g" | "KeyG" => KEY_G,
+    "h" | "KeyH" => KEY_H,
+    "j" | "KeyJ" => KEY_J,
+    "k" | "KeyK" => KEY_K,
+    "l" | "KeyL" => KEY_L,
+    "scln" | "Semicolon" | "︔" => KEY_SEMICOLON,
+    "apo" | "Quote" | "apos" => KEY_APOSTROPHE,
+    "ret" | "Enter" | "return" | "ent" | "enter" | "⏎" | "↩" | "⌤" | "␤" => KEY_ENTER,
+    "lshift" | "ShiftLeft" | "lshft" | "lsft" | "shft" | "sft" | "‹⇧" => KEY_LEFTSHIFT,
+    "z" | "KeyZ" => KEY_Z,
+    "x" | "KeyX" => KEY_X,
+    "c" | "KeyC" => KEY_C,
+    "v" | "KeyV" => KEY_V,
+    "b" | "KeyB" => KEY_B,
+    "n" | "KeyN" => KEY_N,
+    "m" | "KeyM" => KEY_M,
+    "comm" | "Comma" | "⸴" => KEY_COMMA,
+    "Period" | "．" => KEY_DOT,
+    "Slash" | "⁄" => KEY_SLASH,
+    "bksl" | "Backslash" | "⧵" | "＼" => KEY_BACKSLASH,
+    "kp=" | "clr" => KEY_CLEAR,
+    // The kp<etc> keys are also known as the numpad keys. E.g. below is numpad enter.
+    "kp0" | "Numpad0" | "🔢₀" => KEY_KP0,
+    "kp1" | "Numpad1" | "🔢₁" => KEY_KP1,
+    "kp2" | "Numpad2" | "🔢₂" => KEY_KP2,
+    "kp3" | "Numpad3" | "🔢₃" => KEY_KP3,
+    "kp4" | "Numpad4" | "🔢₄" => KEY_KP4,
+    "kp5" | "Numpad5" | "🔢₅" => KEY_KP5,
+    "kp6" | "Numpad6" | "🔢₆" => KEY_KP6,
+    "kp7" | "Numpad7" | "🔢₇" => KEY_KP7,
+    "kp8" | "Numpad8" | "🔢₈" => KEY_KP8,
+    "kp9" | "Numpad9" | "🔢₉" => KEY_KP9,
+    "kprt" | "NumpadEnter" | "🔢⏎" | "🔢↩" | "🔢⌤" | "🔢␤" => KEY_KPENTER,
+    "kp/" | "NumpadDivide" | "🔢⁄" => KEY_KPSLASH,
+    "kp+" | "NumpadAdd" | "🔢₊" => KEY_KPPLUS,
+    "kp*" | "NumpadMultiply" | "🔢∗" => KEY_KPASTERISK,
+    "NumpadEqual" | "🔢₌" => KEY_KPEQUAL,
+    "kp-" | "NumpadSubtract" | "🔢₋" => KEY_KPMINUS,
+    "kp." | "NumpadDecimal" | "🔢．" => KEY_KPDOT,
+    "kp," | "NumpadComma" | "🔢⸴" => KEY_KPCOMMA,
+    "ssrq" | "sys" => KEY_SYSRQ,
+    // Typically the Non-US backslash, near the left shift key
+    "102d" | "IntlBackslash" | "lsgt" | "nubs" | "nonusbslash" | "﹨" | "<" => KEY_102ND,
+    "scrlck" | "ScrollLock" | "slck" | "⇳🔒" => KEY_SCROLLLOCK,
+    "pause" | "Pause" | "break" | "brk" => KEY_PAUSE,
+    "wkup" | "WakeUp" => KEY_WAKEUP,
+    "esc" | "Escape" | "⎋" => KEY_ESC,
+    "rshift" | "ShiftRight" | "RightShift" | "rshft" | "rsft" | "⇧›" => KEY_RIGHTSHIFT,
+    "lctrl" | "ControlLeft" | "lctl" | "ctl" | "‹⎈" | "‹⌃" => KEY_LEFTCTRL,
+    "lalt" | "AltLeft" | "alt" | "‹⎇" | "‹⌥" => KEY_LEFTALT,
+    "spc" | "Space" | "␠" | "␣" => KEY_SPACE,
+    "ralt" | "AltRight" | "⎇›" | "⌥›" => KEY_RIGHTALT,
+    "comp" | "ContextMenu" | "cmps" | "cmp" | "menu" | "apps" | "▤" | "☰" | "𝌆" => KEY_COMPOSE,
+    "🎛" => KEY_DASHBOARD,
+    // Also known as Windows, GUI, Comand, Super
+    "lmeta" | "MetaLeft" | "lmet" | "met" | "‹◆" | "‹⌘" | "‹❖" => KEY_LEFTMETA,
+    "rmeta" | "MetaRight" | "rmet" | "◆›" | "⌘›" | "❖›" => KEY_RIGHTMETA,
+    "rctrl" | "ControlRight" | "rctl" | "⎈›" | "⌃›" => KEY_RIGHTCTRL,
+    "del" | "Delete" | "␡" | "⌦" => KEY_DELETE,
+    "ins" | "Insert" | "⎀" => KEY_INSERT,
+    "bck" | "BrowserBack" => KEY_BACK,
+    "fwd" | "BrowserForward" => KEY_FORWARD,
+    "pgup" | "PageUp" | "⇞" => KEY_PAGEUP,
+    "pgdn" | "PageDown" | "⇟" => KEY_PAGEDOWN,
+    "up" | "ArrowUp" | "▲" => KEY_UP,
+    "down" | "ArrowDown" | "▼" => KEY_DOWN,
+    "lft" | "ArrowLeft" | "left" | "◀" => KEY_LEFT,
+    "rght" | "ArrowRight" | "▶" => KEY_RIGHT,
+    "home" | "Home" | "⇤" | "⤒" | "↖" => KEY_HOME,
+    "end" | "End" | "⇥" | "⤓" | "↘" => KEY_END,
+    "nlck" | "NumLock" | "nlk" | "⇭" => KEY_NUMLOCK,
+    "mute" | "VolumeMute" | "🔇" | "🔈⓪" | "🔈⓿" | "🔈₀" => KEY_MUTE,
+    "volu" | "VolumeUp" | "🔊" | "🔈+" | "🔈➕" | "🔈₊" | "🔈⊕" => KEY_VOLUMEUP,
+    "voldwn" | "VolumeDown" | "vold" | "🔉" | "🔈−" | "🔈➖" | "🔈₋" | "🔈⊖" => KEY_VOLUMEDOWN,
+    "brup" | "bru" | "🔆" => KEY_BRIGHTNESSUP,
+    "brdown" | "brdwn" | "brdn" | "🔅" => KEY_BRIGHTNESSDOWN,
+    "blup" | "⌨💡+" | "⌨💡➕" | "⌨💡₊" | "⌨💡⊕" => KEY_KBDILLUMUP,
+    "bldn" | "⌨💡−" | "⌨💡➖" | "⌨💡₋" | "⌨💡⊖" => KEY_KBDILLUMDOWN,
+    "next" | "MediaTrackNext" | "▶▶" => KEY_NEXTSONG,
+    "pp" | "MediaPlayPause" | "▶⏸" => KEY_PLAYPAUSE,
+    "prev" | "MediaTrackPrevious" | "◀◀" => KEY_PREVIOUSSONG,
+    "f1" | "F1" => KEY_F1,
+    "f2" | "F2" => KEY_F2,
+    "f3" | "F3" => KEY_F3,
+    "f4" | "F4" => KEY_F4,
+    "f5" | "F5" => KEY_F5,
+    "f6" | "F6" => KEY_F6,
+    "f7" | "F7" => KEY_F7,
+    "f8" | "F8" => KEY_F8,
+    "f9" | "F9" => KEY_F9,
+    "f10" | "F10" => KEY_F10,
+    "f11" | "F11" => KEY_F11,
+    "f12" | "F12" => KEY_F12,
+    "f13" | "F13" => KEY_F13,
+    "f14" | "F14" => KEY_F14,
+    "f15" | "F15" => KEY_F15,
+    "f16" | "F16" => KEY_F16,
+    "f17" | "F17" => KEY_F17,
+    "f18" | "F18" => KEY_F18,
+    "f19" | "F19" => KEY_F19,
+    "f20" | "F20" => KEY_F20,
+    "f21" | "F21" => KEY_F21,
+    "f22" | "F22" => KEY_F22,
+    "f23" | "F23" => KEY_F23,
+    "f24" | "F24" => KEY_F24,
+    #[cfg(any(target_os = "macos", target_os = "unknown"))]
+    "fn" | "🌐" | "ƒ" | "ⓕ" | "Ⓕ" | "🄵" | "🅕" | "🅵" => KEY_FN,
+    #[cfg(target_os = "windows")]
+    "kana" | "katakana" | "katakanahiragana" => KEY_HANGEUL,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "kana" | "katakanahiragana" => KEY_KATAKANAHIRAGANA,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "hiragana" => KEY_HIRAGANA,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "katakana" => KEY_KATAKANA,
+    "cnv" | "conv" | "henk" | "hnk" | "henkan" => KEY_HENKAN,
+    "ncnv" | "mhnk" | "muhenkan" => KEY_MUHENKAN,
+    "ro" | "IntlRo" => KEY_RO,
 
-        // NOTE: these are linux and interception-only due to missing implementation for LLHOOK.
-        // Unknown: is macOS supported? I haven't reviewed.
-        "mlft" | "mouseleft" | "🖰1" | "‹🖰" => OsCode::BTN_LEFT,
-        "mrgt" | "mouseright" | "🖰2" | "🖰›" => OsCode::BTN_RIGHT,
-        "mmid" | "mousemid" | "🖰3" => OsCode::BTN_MIDDLE,
-        "mbck" | "mousebackward" | "🖰4" => OsCode::BTN_SIDE,
-        "mfwd" | "mouseforward" | "🖰5" => OsCode::BTN_EXTRA,
-        "mwu" | "mousewheelup" => OsCode::MouseWheelUp,
-        "mwd" | "mousewheeldown" => OsCode::MouseWheelDown,
-        "mwl" | "mousewheelleft" => OsCode::MouseWheelLeft,
-        "mwr" | "mousewheelright" => OsCode::MouseWheelRight,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "prtsc" | "PrintScreen" | "prnt" => KEY_SYSRQ,
+    #[cfg(target_os = "windows")]
+    "prtsc" | "PrintScreen" | "prnt" => KEY_PRINT,
 
-        "hmpg" | "homepage" => OsCode::KEY_HOMEPAGE,
-        "mdia" | "media" => OsCode::KEY_MEDIA,
-        "LaunchMail" | "mail" => OsCode::KEY_MAIL,
-        "email" => OsCode::KEY_EMAIL,
-        "calc" => OsCode::KEY_CALC,
+    // NOTE: these are linux and interception-only due to missing implementation for LLHOOK.
+    // Unknown: is macOS supported? I haven't reviewed.
+    "mlft" | "mouseleft" | "🖰1" | "‹🖰" => BTN_LEFT,
+    "mrgt" | "mouseright" | "🖰2" | "🖰›" => BTN_RIGHT,
+    "mmid" | "mousemid" | "🖰3" => BTN_MIDDLE,
+    "mbck" | "mousebackward" | "🖰4" => BTN_SIDE,
+    "mfwd" | "mouseforward" | "🖰5" => BTN_EXTRA,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "mbtn6" | "mousebutton6" | "🖰6" => BTN_FORWARD,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "mbtn7" | "mousebutton7" | "🖰7" => BTN_BACK,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "mbtn8" | "mousebutton8" | "🖰8" => BTN_TASK,
+    "mwu" | "mousewheelup" => MouseWheelUp,
+    "mwd" | "mousewheeldown" => MouseWheelDown,
+    "mwl" | "mousewheelleft" => MouseWheelLeft,
+    "mwr" | "mousewheelright" => MouseWheelRight,
 
-        // NOTE: these are linux-only right now due to missing the mappings in windows.rs
-        #[cfg(any(target_os = "linux", target_os = "unknown"))]
-        "plyr" | "player" => OsCode::KEY_PLAYER,
-        #[cfg(any(target_os = "linux", target_os = "unknown"))]
-        "powr" | "power" => OsCode::KEY_POWER,
-        #[cfg(any(target_os = "linux", target_os = "unknown"))]
-        "zzz" | "sleep" => OsCode::KEY_SLEEP,
+    "hmpg" | "homepage" => KEY_HOMEPAGE,
+    "mdia" | "media" => KEY_MEDIA,
+    "mail" | "LaunchMail" => KEY_MAIL,
+    "email" => KEY_EMAIL,
+    "calc" => KEY_CALC,
 
-        // Keys that behave as no-ops but can be used in sequences.
-        // Also see: POTENTIAL PROBLEM - G-keys
-        "nop0" => OsCode::KEY_676,
-        "nop1" => OsCode::KEY_677,
-        "nop2" => OsCode::KEY_678,
-        "nop3" => OsCode::KEY_679,
-        "nop4" => OsCode::KEY_680,
-        "nop5" => OsCode::KEY_681,
-        "nop6" => OsCode::KEY_682,
-        "nop7" => OsCode::KEY_683,
-        "nop8" => OsCode::KEY_684,
-        "nop9" => OsCode::KEY_685,
+    // NOTE: these are linux-only right now due to missing the mappings in windows.rs
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "plyr" | "player" => KEY_PLAYER,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "powr" | "power" => KEY_POWER,
+    #[cfg(any(target_os = "linux", target_os = "unknown"))]
+    "zzz" | "sleep" => KEY_SLEEP,
 
-        _ => return None,
-    })
+    // Keys that behave as no-ops but can be used in sequences.
+    // Also see: POTENTIAL PROBLEM - G-keys
+    "nop0" => KEY_676,
+    "nop1" => KEY_677,
+    "nop2" => KEY_678,
+    "nop3" => KEY_679,
+    "nop4" => KEY_680,
+    "nop5" => KEY_681,
+    "nop6" => KEY_682,
+    "nop7" => KEY_683,
+    "nop8" => KEY_684,
+    "nop9" => KEY_685,
+
+}
+
+#[cfg(any(target_os = "linux", target_os = "unknown"))]
+#[test]
+fn oscode_to_str_round_trips() {
+    for code in 0..u16::from(OsCode::KEY_MAX) {
+        let Some(osc) = OsCode::from_u16(code) else {
+            continue;
+        };
+        if let Some(name) = oscode_to_str(osc) {
+            assert_eq!(str_to_oscode(name), Some(osc), "{name}");
+        }
+    }
 }
 
 /// This is a shameless copy of evdev_rs::enums::EV_KEY.
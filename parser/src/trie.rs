@@ -71,6 +71,25 @@ impl<T> Trie<T> {
         }
     }
 
+    /// Returns every key in the trie that starts with `key`, along with its value.
+    /// The input key itself is included if it has a value.
+    pub fn descendants(&self, key: impl AsRef<[u16]>) -> Vec<(Vec<TrieKeyElement>, T)>
+    where
+        T: Clone,
+    {
+        self.inner
+            .iter_prefix(cast_slice(key.as_ref()))
+            .map(|(k, v)| {
+                // Undo the byte cast done on insertion.
+                let k = k
+                    .chunks_exact(2)
+                    .map(|b| TrieKeyElement::from_ne_bytes([b[0], b[1]]))
+                    .collect();
+                (k, v.clone())
+            })
+            .collect()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.inner.is_empty()
     }
@@ -3,7 +3,8 @@ use anyhow::{anyhow, bail};
 use clap::Parser;
 use kanata_state_machine::{oskbd::*, *};
 use simplelog::{format_description, *};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 pub fn default_sim() -> Vec<PathBuf> {
     let mut cfgs = Vec::new();
@@ -79,6 +80,41 @@ test/sim.txt in the current working directory and
     /// This flag generates an error if the binary is compiled without simulated output.
     #[arg(short = 'o', long, verbatim_doc_comment)]
     out: Option<String>,
+    /// Print the simulation as JSON lines instead of the output list and the log table.
+    /// Each input, tick, output key, mouse event, unicode output, layer change and custom action
+    /// is a record with a `time` in milliseconds and a `type`. With -o|--out, the JSON lines are
+    /// saved instead of the log table.
+    /// This flag generates an error if the binary is compiled without simulated output.
+    #[arg(long, verbatim_doc_comment)]
+    json: bool,
+    /// Read simulation lines from stdin instead of simulation files, printing the outputs, the
+    /// active layer and whether a tap-hold or chord decision is pending after each line.
+    /// Type `:help` for the list of commands.
+    /// This flag generates an error if the binary is compiled without simulated output.
+    #[arg(short = 'i', long, verbatim_doc_comment)]
+    interactive: bool,
+    /// Fail if the state is not clean at the end of a simulation file whose inputs are all
+    /// released, e.g. because an output key or a layer is still held, or an action such as
+    /// tap-hold, one-shot or a sequence is still pending.
+    /// This flag generates an error if the binary is compiled without simulated output.
+    #[arg(long, verbatim_doc_comment)]
+    fail_on_stuck: bool,
+    /// Emulate a keyboard layout of the target OS and print the text that the outputs type,
+    /// including characters composed with dead keys. One of: us, uk, de, fr.
+    /// This flag generates an error if the binary is compiled without simulated output.
+    #[arg(long, verbatim_doc_comment)]
+    layout: Option<String>,
+}
+
+/// Options of the simulation itself, as opposed to the ones passed on to kanata.
+struct SimOpts {
+    sim_paths: Vec<PathBuf>,
+    /// Appendix of the file name to save the output to.
+    out: Option<String>,
+    json: bool,
+    interactive: bool,
+    fail_on_stuck: bool,
+    layout: Option<String>,
 }
 
 fn log_init() {
@@ -100,11 +136,10 @@ fn log_init() {
 }
 
 /// Parse CLI arguments
-fn cli_init_fsim() -> Result<(ValidatedArgs, Vec<PathBuf>, Option<String>)> {
+fn cli_init_fsim() -> Result<(ValidatedArgs, SimOpts)> {
     let args = Args::parse();
     let cfg_paths = args.cfg.unwrap_or_else(default_cfg);
     let sim_paths = args.sim.unwrap_or_else(default_sim);
-    let sim_appendix = args.out;
 
     log::info!(
         "kanata_simulated_input v{} starting",
@@ -118,7 +153,9 @@ fn cli_init_fsim() -> Result<(ValidatedArgs, Vec<PathBuf>, Option<String>)> {
     } else {
         bail!("No config files provided\nFor more info, pass the `-h` or `--help` flags.");
     }
-    if let Some(config_sim_file) = sim_paths.first() {
+    if args.interactive {
+        // Simulation files are not used.
+    } else if let Some(config_sim_file) = sim_paths.first() {
         if !config_sim_file.exists() {
             bail!("Could not find the simulation file ({})\nFor more info, pass the `-h` or `--help` flags.",sim_paths[0].to_str().unwrap_or("?"))
         }
@@ -133,10 +170,18 @@ fn cli_init_fsim() -> Result<(ValidatedArgs, Vec<PathBuf>, Option<String>)> {
             tcp_server_address: None::<SocketAddrWrapper>,
             #[cfg(target_os = "linux")]
             symlink_path: None,
+            #[cfg(target_os = "linux")]
+            record_path: None,
             nodelay: true,
         },
-        sim_paths,
-        sim_appendix,
+        SimOpts {
+            sim_paths,
+            out: args.out,
+            json: args.json,
+            interactive: args.interactive,
+            fail_on_stuck: args.fail_on_stuck,
+            layout: args.layout,
+        },
     ))
 }
 
@@ -160,10 +205,6 @@ fn kbd_out_log(
     _key_code: Option<OsCode>,
     _tick: Option<u128>,
 ) {
-    let a = LogFmtT::InTick;
-    if a == LogFmtT::InTick {
-        println!("{}", 42)
-    };
     #[cfg(all(
         not(feature = "simulated_input"),
         not(feature = "passthru_ahk"),
@@ -174,144 +215,395 @@ fn kbd_out_log(
             LogFmtT::InTick => {
                 if let Some(tick) = _tick {
                     _kbd_out.log.in_tick(tick);
+                    _kbd_out.json.input_tick(tick);
                 }
             }
             LogFmtT::InKeyUp => {
                 if let Some(key_code) = _key_code {
                     _kbd_out.log.in_release_key(key_code);
+                    _kbd_out.json.input(key_code, KeyValue::Release);
                 }
             }
             LogFmtT::InKeyDown => {
                 if let Some(key_code) = _key_code {
                     _kbd_out.log.in_press_key(key_code);
+                    _kbd_out.json.input(key_code, KeyValue::Press);
                 }
             }
             LogFmtT::InKeyRep => {
                 if let Some(key_code) = _key_code {
                     _kbd_out.log.in_repeat_key(key_code);
+                    _kbd_out.json.input(key_code, KeyValue::Repeat);
                 }
             }
         }
     }
 }
+/// Prefix of sim file lines with the outputs expected since the previous expectation line.
+const EXPECT_PREFIX: &str = "expect:";
+
+#[cfg(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+))]
+fn sim_outputs(k: &Kanata) -> Result<&[String]> {
+    Ok(&k.kbd_out.outputs.events)
+}
+
+#[cfg(not(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+)))]
+fn sim_outputs(_k: &Kanata) -> Result<&[String]> {
+    bail!("The program was compiled without simulated output. Expectation lines are unsupported")
+}
+
+/// Create kanata, emulating the keyboard layout if one is given.
+#[cfg(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+))]
+fn new_kanata(args: &ValidatedArgs, layout: Option<&str>) -> Result<Kanata> {
+    let mut k = Kanata::new(args)?;
+    if let Some(layout) = layout {
+        let layout = layout.parse::<OsLayout>().map_err(|e| anyhow!(e))?;
+        k.kbd_out.typed = Some(TypedText::new(layout));
+    }
+    Ok(k)
+}
+
+#[cfg(not(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+)))]
+fn new_kanata(args: &ValidatedArgs, _layout: Option<&str>) -> Result<Kanata> {
+    Kanata::new(args)
+}
+
+#[cfg(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+))]
+fn sim_typed_text(k: &Kanata) -> Option<&str> {
+    k.kbd_out.typed.as_ref().map(|typed| typed.text.as_str())
+}
+
+#[cfg(not(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+)))]
+fn sim_typed_text(_k: &Kanata) -> Option<&str> {
+    None
+}
+
+/// Print the state left over at the end of a simulation. Returns false if the state is not clean
+/// even though all inputs are released.
+#[cfg(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+))]
+fn report_end_state(k: &Kanata, held: &HashSet<OsCode>, sim_file: &Path) -> bool {
+    let end_state = k.sim_end_state();
+    let sim_file = sim_file.display();
+    if !held.is_empty() {
+        let mut held: Vec<String> = held.iter().map(|osc| format!("{osc:?}")).collect();
+        held.sort();
+        eprintln!("{sim_file}: inputs still held: {}", held.join(", "));
+        eprintln!("{end_state}");
+        return true;
+    }
+    eprintln!("{sim_file}: {end_state}");
+    end_state.is_clean()
+}
+
+#[cfg(not(all(
+    not(feature = "simulated_input"),
+    not(feature = "passthru_ahk"),
+    feature = "simulated_output"
+)))]
+fn report_end_state(_k: &Kanata, _held: &HashSet<OsCode>, _sim_file: &Path) -> bool {
+    true
+}
+
+/// Convert an output item to the form used for comparisons, so that expectations can use either
+/// `out:↓a` or `dn:a`, and either `t:10ms` or `t:10`.
+fn normalize_output(item: &str) -> String {
+    let item = item.replace("out:↓", "dn:").replace("out:↑", "up:");
+    match item.strip_prefix("t:") {
+        Some(ms) if !ms.ends_with("ms") => format!("t:{ms}ms"),
+        _ => item,
+    }
+}
+
+/// Returns a readable diff if the outputs do not match the expected items.
+/// Time items are only compared if the expectation contains any.
+fn check_expectation(expected: &str, outputs: &[String]) -> Option<String> {
+    let expected: Vec<String> = expected.split_whitespace().map(normalize_output).collect();
+    let compare_time = expected.iter().any(|item| item.starts_with("t:"));
+    let actual: Vec<String> = outputs
+        .iter()
+        .map(|item| normalize_output(item))
+        .filter(|item| compare_time || !item.starts_with("t:"))
+        .collect();
+    if expected == actual {
+        return None;
+    }
+    let mut diff = format!(
+        "  expected: {}\n  actual:   {}\n",
+        expected.join(" "),
+        actual.join(" ")
+    );
+    for (change, item) in diff_items(&expected, &actual) {
+        diff += &format!("  {change} {item}\n");
+    }
+    Some(diff)
+}
+
+/// Line-based diff of the items using their longest common subsequence. Items only in the
+/// expectation are marked with `-` and items only in the outputs with `+`.
+fn diff_items<'a>(expected: &'a [String], actual: &'a [String]) -> Vec<(char, &'a str)> {
+    let (n, m) = (expected.len(), actual.len());
+    // lcs[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = vec![];
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            diff.push((' ', expected[i].as_str()));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push(('+', actual[j].as_str()));
+            j += 1;
+        } else {
+            diff.push(('-', expected[i].as_str()));
+            i += 1;
+        }
+    }
+    diff
+}
+
+/// Process the input items of one simulation line, e.g. `d:a t:50 u:a`, keeping track of the
+/// input keys that are held.
+fn sim_line(k: &mut Kanata, l: &str, held: &mut HashSet<OsCode>) -> Result<()> {
+    for pair in l.split_whitespace() {
+        match pair.split_once(':') {
+            Some((kind, val)) => match kind {
+                "tick" | "🕐" | "t" => sim_tick(k, val)?,
+                "press" | "↓" | "d" | "down" => sim_key(k, val, KeyValue::Press, held)?,
+                "release" | "↑" | "u" | "up" => sim_key(k, val, KeyValue::Release, held)?,
+                "repeat" | "⟳" | "r" => sim_key(k, val, KeyValue::Repeat, held)?,
+                _ => bail!("invalid pair prefix: {kind}"),
+            },
+            None => {
+                let (kind, val) = split_at_1(pair);
+                match kind {
+                    //allow skipping : separator for unique non-key symbols
+                    "🕐" => sim_tick(k, val)?,
+                    "↓" => sim_key(k, val, KeyValue::Press, held)?,
+                    "↑" => sim_key(k, val, KeyValue::Release, held)?,
+                    "⟳" => sim_key(k, val, KeyValue::Repeat, held)?,
+                    _ => bail!("invalid pair: {l}"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sim_tick(k: &mut Kanata, val: &str) -> Result<()> {
+    let tick = str::parse::<u128>(val)?;
+    kbd_out_log(&mut k.kbd_out, LogFmtT::InTick, None, Some(tick));
+    k.tick_ms(tick, &None)
+}
+
+fn sim_key(k: &mut Kanata, val: &str, value: KeyValue, held: &mut HashSet<OsCode>) -> Result<()> {
+    let key_code = str_to_oscode(val).ok_or_else(|| anyhow!("unknown key: {val}"))?;
+    let log_type = match value {
+        KeyValue::Press => {
+            held.insert(key_code);
+            LogFmtT::InKeyDown
+        }
+        KeyValue::Release => {
+            held.remove(&key_code);
+            LogFmtT::InKeyUp
+        }
+        _ => LogFmtT::InKeyRep,
+    };
+    kbd_out_log(&mut k.kbd_out, log_type, Some(key_code), None);
+    k.handle_input_event(&KeyEvent::new(key_code, value))
+}
+
+/// Longest time in ms that `:reset` waits for kanata to become idle.
+const RESET_MAX_TICKS: u32 = 60000;
+
+const REPL_HELP: &str = "\
+Type simulation lines such as `d:a t:50 u:a t:50`, or one of the commands:
+  :reload  parse the configuration again and start over
+  :reset   release held keys and wait for pending actions to finish
+  :help    show this help
+  :quit    exit";
+
+/// Release held keys, then tick until kanata is idle.
+fn repl_reset(k: &mut Kanata, held: &mut HashSet<OsCode>) -> Result<()> {
+    for key_code in std::mem::take(held) {
+        kbd_out_log(&mut k.kbd_out, LogFmtT::InKeyUp, Some(key_code), None);
+        k.handle_input_event(&KeyEvent::new(key_code, KeyValue::Release))?;
+    }
+    let mut ticks = 0;
+    while !k.is_idle() && ticks < RESET_MAX_TICKS {
+        k.tick_ms(1, &None)?;
+        ticks += 1;
+    }
+    k.clean_state(1)
+}
+
+/// Print the state that matters for judging timings after a line.
+fn repl_print_state(k: &Kanata, outputs_start: usize) -> Result<usize> {
+    let outputs = sim_outputs(k)?;
+    for output in &outputs[outputs_start..] {
+        println!("{output}");
+    }
+    let layer = &k.layer_info[k.layout.b().current_layer()].name;
+    let waiting = k.layout.b().waiting.is_some();
+    println!("layer: {layer}, waiting: {waiting}");
+    if let Some(text) = sim_typed_text(k) {
+        println!("typed text: {text:?}");
+    }
+    Ok(outputs.len())
+}
+
+/// Run an interactive session, reading simulation lines and commands from stdin.
+fn repl(args: &ValidatedArgs, layout: Option<&str>) -> Result<()> {
+    use std::io::{BufRead, Write};
+    let mut k = new_kanata(args, layout)?;
+    let mut held = HashSet::default();
+    let mut outputs_start = 0;
+    println!("{REPL_HELP}");
+    let mut stdin = std::io::stdin().lock();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let res = match line.trim() {
+            ":quit" | ":q" => return Ok(()),
+            ":help" | ":h" => {
+                println!("{REPL_HELP}");
+                continue;
+            }
+            ":reload" | ":r" => new_kanata(args, layout).map(|new_k| {
+                k = new_k;
+                held.clear();
+                outputs_start = 0;
+            }),
+            ":reset" => repl_reset(&mut k, &mut held),
+            l if l.starts_with(':') => Err(anyhow!("unknown command: {l}")),
+            l => sim_line(&mut k, l, &mut held),
+        };
+        if let Err(e) = res {
+            eprintln!("{e}");
+        }
+        outputs_start = repl_print_state(&k, outputs_start)?;
+    }
+}
+
 fn main_impl() -> Result<()> {
     log_init();
-    let (args, sim_paths, _sim_appendix) = cli_init_fsim()?;
+    let (args, opts) = cli_init_fsim()?;
     #[cfg(not(feature = "simulated_output"))]
     {
-        if _sim_appendix.is_some() {
+        if opts.out.is_some() {
             bail!("The program was compiled without simulated output. The -o|--out flag is unsupported");
         }
+        if opts.json {
+            bail!(
+                "The program was compiled without simulated output. The --json flag is unsupported"
+            );
+        }
+        if opts.interactive {
+            bail!("The program was compiled without simulated output. The -i|--interactive flag is unsupported");
+        }
+        if opts.fail_on_stuck {
+            bail!("The program was compiled without simulated output. The --fail-on-stuck flag is unsupported");
+        }
+        if opts.layout.is_some() {
+            bail!("The program was compiled without simulated output. The --layout flag is unsupported");
+        }
+    }
+    if opts.interactive {
+        return repl(&args, opts.layout.as_deref());
     }
 
-    for config_sim_file in &sim_paths {
-        let mut k = Kanata::new(&args)?;
+    let mut failed_expectations = 0;
+    let mut unclean_end_states = 0;
+    for config_sim_file in &opts.sim_paths {
+        let mut k = new_kanata(&args, opts.layout.as_deref())?;
         log::info!("Evaluating simulation file = {:?}", config_sim_file);
         let s = std::fs::read_to_string(config_sim_file)?;
-        for l in s.lines() {
-            for pair in l.split_whitespace() {
-                match pair.split_once(':') {
-                    Some((kind, val)) => match kind {
-                        "tick" | "🕐" | "t" => {
-                            let tick = str::parse::<u128>(val)?;
-                            kbd_out_log(&mut k.kbd_out, LogFmtT::InTick, None, Some(tick));
-                            k.tick_ms(tick, &None)?;
-                        }
-                        "press" | "↓" | "d" | "down" => {
-                            let key_code =
-                                str_to_oscode(val).ok_or_else(|| anyhow!("unknown key: {val}"))?;
-                            kbd_out_log(&mut k.kbd_out, LogFmtT::InKeyDown, Some(key_code), None);
-                            k.handle_input_event(&KeyEvent {
-                                code: key_code,
-                                value: KeyValue::Press,
-                            })?;
-                        }
-                        "release" | "↑" | "u" | "up" => {
-                            let key_code =
-                                str_to_oscode(val).ok_or_else(|| anyhow!("unknown key: {val}"))?;
-                            kbd_out_log(&mut k.kbd_out, LogFmtT::InKeyUp, Some(key_code), None);
-                            k.handle_input_event(&KeyEvent {
-                                code: key_code,
-                                value: KeyValue::Release,
-                            })?;
-                        }
-                        "repeat" | "⟳" | "r" => {
-                            let key_code =
-                                str_to_oscode(val).ok_or_else(|| anyhow!("unknown key: {val}"))?;
-                            kbd_out_log(&mut k.kbd_out, LogFmtT::InKeyRep, Some(key_code), None);
-                            k.handle_input_event(&KeyEvent {
-                                code: key_code,
-                                value: KeyValue::Repeat,
-                            })?;
-                        }
-                        _ => bail!("invalid pair prefix: {kind}"),
-                    },
-                    None => {
-                        let (kind, val) = split_at_1(pair);
-                        match kind {
-                            //allow skipping : separator for unique non-key symbols
-                            "🕐" => {
-                                let tick = str::parse::<u128>(val)?;
-                                kbd_out_log(&mut k.kbd_out, LogFmtT::InTick, None, Some(tick));
-                                k.tick_ms(tick, &None)?;
-                            }
-                            "↓" => {
-                                let key_code = str_to_oscode(val)
-                                    .ok_or_else(|| anyhow!("unknown key: {val}"))?;
-                                kbd_out_log(
-                                    &mut k.kbd_out,
-                                    LogFmtT::InKeyDown,
-                                    Some(key_code),
-                                    None,
-                                );
-                                k.handle_input_event(&KeyEvent {
-                                    code: key_code,
-                                    value: KeyValue::Press,
-                                })?;
-                            }
-                            "↑" => {
-                                let key_code = str_to_oscode(val)
-                                    .ok_or_else(|| anyhow!("unknown key: {val}"))?;
-                                kbd_out_log(&mut k.kbd_out, LogFmtT::InKeyUp, Some(key_code), None);
-                                k.handle_input_event(&KeyEvent {
-                                    code: key_code,
-                                    value: KeyValue::Release,
-                                })?;
-                            }
-                            "⟳" => {
-                                let key_code = str_to_oscode(val)
-                                    .ok_or_else(|| anyhow!("unknown key: {val}"))?;
-                                kbd_out_log(
-                                    &mut k.kbd_out,
-                                    LogFmtT::InKeyRep,
-                                    Some(key_code),
-                                    None,
-                                );
-                                k.handle_input_event(&KeyEvent {
-                                    code: key_code,
-                                    value: KeyValue::Repeat,
-                                })?;
-                            }
-                            _ => bail!("invalid pair: {l}"),
-                        }
-                    }
+        // Number of outputs already checked by expectation lines.
+        let mut checked_outputs = 0;
+        let mut held = HashSet::default();
+        for (line_idx, l) in s.lines().enumerate() {
+            if let Some(expected) = l.trim_start().strip_prefix(EXPECT_PREFIX) {
+                let outputs = sim_outputs(&k)?;
+                if let Some(diff) = check_expectation(expected, &outputs[checked_outputs..]) {
+                    failed_expectations += 1;
+                    eprintln!(
+                        "{}:{}: outputs do not match the expectation\n{diff}",
+                        config_sim_file.display(),
+                        line_idx + 1
+                    );
                 }
+                checked_outputs = outputs.len();
+                continue;
             }
+            sim_line(&mut k, l, &mut held)?;
         }
         #[cfg(all(
             not(feature = "simulated_input"),
             not(feature = "passthru_ahk"),
             feature = "simulated_output"
         ))]
-        println!("{}", k.kbd_out.outputs.events.join("\n"));
-        #[cfg(all(
-            not(feature = "simulated_input"),
-            not(feature = "passthru_ahk"),
-            feature = "simulated_output"
-        ))]
-        k.kbd_out.log.end(config_sim_file, _sim_appendix.clone());
+        if opts.json {
+            k.kbd_out.json.end(config_sim_file, opts.out.clone());
+        } else {
+            println!("{}", k.kbd_out.outputs.events.join("\n"));
+            if let Some(text) = sim_typed_text(&k) {
+                println!("typed text: {text:?}");
+            }
+            k.kbd_out.log.end(config_sim_file, opts.out.clone());
+        }
+        if !report_end_state(&k, &held, config_sim_file) {
+            unclean_end_states += 1;
+        }
     }
 
+    if failed_expectations > 0 {
+        bail!("{failed_expectations} expectation(s) did not match the outputs");
+    }
+    if opts.fail_on_stuck && unclean_end_states > 0 {
+        bail!("{unclean_end_states} simulation(s) ended with keys, layers or actions still active");
+    }
     Ok(())
 }
 
@@ -322,3 +614,48 @@ fn main() -> Result<()> {
     }
     ret
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(items: &str) -> Vec<String> {
+        items.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn expectation_matches_either_output_format() {
+        let actual = outputs("out:↓A t:10ms out:↑A");
+        assert_eq!(check_expectation("dn:A up:A", &actual), None);
+        assert_eq!(check_expectation("out:↓A t:10 out:↑A", &actual), None);
+        assert_eq!(check_expectation("dn:A t:10ms up:A", &actual), None);
+    }
+
+    #[test]
+    fn expectation_mismatch_shows_diff() {
+        let actual = outputs("out:↓LShift out:↓B out:↑B out:↑LShift");
+        let diff = check_expectation("dn:A up:A", &actual).expect("mismatch");
+        assert_eq!(
+            diff,
+            "  expected: dn:A up:A\n  actual:   dn:LShift dn:B up:B up:LShift\n  \
+             + dn:LShift\n  + dn:B\n  + up:B\n  + up:LShift\n  - dn:A\n  - up:A\n"
+        );
+        let diff = check_expectation("t:20ms dn:A up:A", &outputs("t:10ms out:↓A out:↑A"))
+            .expect("mismatch");
+        assert!(diff.contains("  + t:10ms\n  - t:20ms\n    dn:A\n    up:A\n"));
+    }
+
+    #[test]
+    fn reset_releases_held_keys() {
+        let cfg = "(defsrc a b) (deflayer base (tap-hold 200 200 a lsft) (layer-while-held l2)) (deflayer l2 x y)";
+        let mut k = Kanata::new_from_str(cfg, Default::default()).expect("valid cfg");
+        let mut held = HashSet::default();
+        sim_line(&mut k, "d:a d:b t:10 u:b d:b t:10", &mut held).expect("valid line");
+        assert_eq!(held, HashSet::from([OsCode::KEY_A, OsCode::KEY_B]));
+        assert!(k.layout.b().waiting.is_some());
+        repl_reset(&mut k, &mut held).expect("reset works");
+        assert!(held.is_empty());
+        assert!(k.is_idle());
+        assert_eq!(k.layout.b().current_layer(), 0);
+    }
+}
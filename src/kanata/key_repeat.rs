@@ -1,11 +1,68 @@
 use super::*;
 
+/// State of a key repeat generated by kanata, for the most recently pressed key.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KeyRepeatState {
+    code: OsCode,
+    ticks_until_repeat: u16,
+}
+
 impl Kanata {
+    /// Start or stop the key repeat generated by kanata, if it is enabled. Returns true if the
+    /// event is a hardware repeat, which is not processed while kanata generates repeats itself.
+    #[cfg(target_os = "linux")]
+    pub(super) fn update_key_repeat(&mut self, event: &KeyEvent) -> bool {
+        let Some(settings) = self.key_repeat else {
+            return false;
+        };
+        match event.value {
+            KeyValue::Press => {
+                // The press is output on the next tick, which also counts down the repeat delay.
+                self.key_repeat_state = Some(KeyRepeatState {
+                    code: event.code,
+                    ticks_until_repeat: settings.delay.saturating_add(1),
+                });
+            }
+            KeyValue::Release if self.key_repeat_state.is_some_and(|s| s.code == event.code) => {
+                self.key_repeat_state = None;
+            }
+            KeyValue::Repeat => return true,
+            _ => {}
+        }
+        false
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(super) fn tick_key_repeat(&mut self) -> Result<()> {
+        let (Some(settings), Some(state)) = (self.key_repeat, self.key_repeat_state.as_mut())
+        else {
+            return Ok(());
+        };
+        state.ticks_until_repeat -= 1;
+        if state.ticks_until_repeat > 0 {
+            return Ok(());
+        }
+        state.ticks_until_repeat = (1000 / settings.rate).max(1);
+        let event = KeyEvent::new(state.code, KeyValue::Repeat);
+        let repeat_value = match self.key_repeat_output {
+            KeyRepeatOutput::Repeat => KeyValue::Repeat,
+            KeyRepeatOutput::Press => KeyValue::Press,
+        };
+        self.handle_repeat_output(&event, repeat_value)
+    }
+
     /// This compares the active keys in the keyberon layout against the potential key outputs for
     /// corresponding physical key in the configuration. If any of keyberon active keys match any
     /// potential physical key output, write the repeat event to the OS.
     pub(super) fn handle_repeat(&mut self, event: &KeyEvent) -> Result<()> {
-        let ret = self.handle_repeat_actual(event);
+        self.handle_repeat_output(event, KeyValue::Repeat)
+    }
+
+    /// Same as `handle_repeat`, but writes the repeat with `repeat_value` instead of a repeat
+    /// event. A `KeyValue::Press` writes a release followed by a press.
+    fn handle_repeat_output(&mut self, event: &KeyEvent, repeat_value: KeyValue) -> Result<()> {
+        let ret = self.handle_repeat_actual(event, repeat_value);
         // The cur_keys Vec is re-used for processing, for efficiency reasons to avoid allocation.
         // Unlike prev_keys which has useful info for the next call to handle_time_ticks, cur_keys
         // can be reused and cleared — it just needs to be empty for the next handle_time_ticks
@@ -14,7 +71,11 @@ impl Kanata {
         ret
     }
 
-    pub(super) fn handle_repeat_actual(&mut self, event: &KeyEvent) -> Result<()> {
+    pub(super) fn handle_repeat_actual(
+        &mut self,
+        event: &KeyEvent,
+        repeat_value: KeyValue,
+    ) -> Result<()> {
         if let Some(state) = self.sequence_state.get_active() {
             // While in non-visible sequence mode, don't send key repeats. I can't imagine it's a
             // helpful use case for someone trying to type in a sequence that they want to rely on
@@ -48,9 +109,7 @@ impl Kanata {
                         || self.unmodded_keys.contains(&kc)
                     {
                         log::debug!("repeat    {:?}", KeyCode::from(osc));
-                        if let Err(e) = write_key(&mut self.kbd_out, osc, KeyValue::Repeat) {
-                            bail!("could not write key {e:?}")
-                        }
+                        write_repeat(&mut self.kbd_out, osc, repeat_value)?;
                         return Ok(());
                     }
                 }
@@ -77,9 +136,7 @@ impl Kanata {
                     || self.unmodded_keys.contains(&kc)
                 {
                     log::debug!("repeat    {:?}", KeyCode::from(osc));
-                    if let Err(e) = write_key(&mut self.kbd_out, osc, KeyValue::Repeat) {
-                        bail!("could not write key {e:?}")
-                    }
+                    write_repeat(&mut self.kbd_out, osc, repeat_value)?;
                     return Ok(());
                 }
             }
@@ -94,10 +151,21 @@ impl Kanata {
             || self.unshifted_keys.contains(&kc)
             || self.unmodded_keys.contains(&kc)
         {
-            if let Err(e) = write_key(&mut self.kbd_out, event.code, KeyValue::Repeat) {
-                bail!("could not write key {e:?}");
-            }
+            write_repeat(&mut self.kbd_out, event.code, repeat_value)?;
         }
         Ok(())
     }
 }
+
+/// Write a key repeat event, as a release and a press if `repeat_value` is a press.
+fn write_repeat(kbd_out: &mut KbdOut, osc: OsCode, repeat_value: KeyValue) -> Result<()> {
+    if repeat_value == KeyValue::Press {
+        if let Err(e) = write_key(kbd_out, osc, KeyValue::Release) {
+            bail!("could not write key {e:?}")
+        }
+    }
+    if let Err(e) = write_key(kbd_out, osc, repeat_value) {
+        bail!("could not write key {e:?}")
+    }
+    Ok(())
+}
@@ -8,12 +8,104 @@ use evdev::{InputEvent, InputEventKind, RelativeAxisType};
 use log::info;
 use parking_lot::Mutex;
 use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::SyncSender as Sender;
 use std::sync::Arc;
 
+use kanata_keyberon::layout::KCoord;
+
 use super::*;
 
+/// Set by live reload, so that the event loop matches the input devices against the
+/// `defdevice` sections again and applies `linux-passthru-non-key-events`.
+pub(crate) static INPUT_CFG_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Coordinate of the queued `on-connect` and `on-disconnect` actions. No input key or virtual key
+/// has this coordinate, so the actions do not interact with the state of a real key.
+const DEVICE_ACTION_COORD: KCoord = (NORMAL_KEY_ROW, OsCode::KEY_RESERVED as u16);
+
 impl Kanata {
+    /// Notify TCP clients of input device changes and run the `on-connect` and `on-disconnect`
+    /// actions of the matching `defdevice` sections.
+    pub(crate) fn handle_device_changes(&mut self, _tx: &Option<Sender<ServerMessage>>) {
+        for change in std::mem::take(&mut self.device_changes) {
+            #[cfg(feature = "tcp_server")]
+            if let Some(tx) = _tx {
+                let (name, path) = (change.name.clone(), change.path.clone());
+                let msg = match &change.kind {
+                    DeviceChangeKind::Added => ServerMessage::DeviceAdded { name, path },
+                    DeviceChangeKind::Grabbed => ServerMessage::DeviceGrabbed { name, path },
+                    DeviceChangeKind::GrabFailed { error } => ServerMessage::DeviceGrabFailed {
+                        name,
+                        path,
+                        error: error.clone(),
+                    },
+                    DeviceChangeKind::Removed => ServerMessage::DeviceRemoved { name, path },
+                };
+                if let Err(error) = tx.try_send(msg) {
+                    log::error!("could not send device change notification: {}", error);
+                }
+            }
+            let Some(device_cfg) =
+                find_device_cfg(&self.devices, &change.info()).map(|i| &self.devices[i])
+            else {
+                continue;
+            };
+            let (label, action) = match change.kind {
+                DeviceChangeKind::Grabbed => ("on-connect", device_cfg.on_connect),
+                DeviceChangeKind::Removed => ("on-disconnect", device_cfg.on_disconnect),
+                _ => continue,
+            };
+            if let Some(action) = action {
+                log::info!(
+                    "running {label} action of defdevice {} for {}",
+                    device_cfg.name,
+                    change.path
+                );
+                self.layout
+                    .bm()
+                    .action_queue
+                    .push_back(Some((DEVICE_ACTION_COORD, 0, action)));
+            }
+        }
+    }
+
+    /// Switch the base layer when typing moves to a different input device,
+    /// according to the `layer` option of `defdevice` sections.
+    ///
+    /// Each `defdevice` section with a `layer` has its own base layer, starting at that layer,
+    /// and the devices without a `layer` share one, starting at the base layer used before. The
+    /// base layer of the previous device is saved when typing moves to another device, so a
+    /// `layer-switch` is kept for the device it was made on.
+    pub(crate) fn handle_input_device_layer(&mut self, device: Option<InputDevice>) {
+        let Some(device) = device else {
+            return;
+        };
+        let group = device
+            .cfg_idx
+            .filter(|i| self.devices.get(*i).is_some_and(|d| d.layer.is_some()));
+        if self.device_layer_group == Some(group) {
+            return;
+        }
+        let layout = self.layout.bm();
+        let previous = self.device_layer_group.replace(group).flatten();
+        self.device_base_layers
+            .insert(previous, layout.default_layer);
+        let layer = match self.device_base_layers.get(&group) {
+            Some(layer) => *layer,
+            None => group
+                .and_then(|i| self.devices[i].layer)
+                .unwrap_or(layout.default_layer),
+        };
+        if layout.default_layer != layer {
+            log::info!(
+                "input device changed; switching base layer to {}",
+                self.layer_info[layer].name
+            );
+            layout.set_default_layer(layer);
+        }
+    }
+
     /// Enter an infinite loop that listens for OS key events and sends them to the processing
     /// thread.
     pub fn event_loop(kanata: Arc<Mutex<Self>>, tx: Sender<KeyEvent>) -> Result<()> {
@@ -26,6 +118,8 @@ impl Kanata {
             k.continue_if_no_devices,
             k.include_names.clone(),
             k.exclude_names.clone(),
+            k.include_matchers.clone(),
+            k.exclude_matchers.clone(),
             k.device_detect_mode,
         ) {
             Ok(kbd_in) => kbd_in,
@@ -33,18 +127,44 @@ impl Kanata {
                 bail!("failed to open keyboard device(s): {}", e)
             }
         };
+        kbd_in.set_passthru_non_key_events(k.passthru_non_key_events);
+        kbd_in.set_device_cfgs(k.devices.clone());
+        INPUT_CFG_CHANGED.store(false, Ordering::SeqCst);
+        let mut recorder = k
+            .record_path
+            .as_deref()
+            .map(sim_recorder::SimRecorder::create)
+            .transpose()?;
 
         // In some environments, this needs to be done after the input device grab otherwise it
         // does not work on kanata startup.
         Kanata::set_repeat_rate(k.x11_repeat_rate)?;
         drop(k);
+        queue_device_changes(&kanata, &mut kbd_in, &tx)?;
 
         loop {
             let events = kbd_in.read().map_err(|e| anyhow!("failed read: {}", e))?;
             log::trace!("{events:?}");
+            queue_device_changes(&kanata, &mut kbd_in, &tx)?;
+            if INPUT_CFG_CHANGED.swap(false, Ordering::SeqCst) {
+                let (device_cfgs, passthru_non_key_events) = {
+                    let k = kanata.lock();
+                    (k.devices.clone(), k.passthru_non_key_events)
+                };
+                kbd_in.set_device_cfgs(device_cfgs);
+                kbd_in.set_passthru_non_key_events(passthru_non_key_events);
+            }
 
-            for in_event in events.iter().copied() {
-                let key_event = match KeyEvent::try_from(in_event) {
+            for (in_event, device_id) in events.iter().copied() {
+                if kbd_in
+                    .passthru_non_key_event(in_event, device_id, |code| {
+                        MAPPED_KEYS.lock().contains(&code)
+                    })
+                    .map_err(|e| anyhow!("failed write: {}", e))?
+                {
+                    continue;
+                }
+                let mut key_event = match KeyEvent::try_from(in_event) {
                     Ok(ev) => ev,
                     _ => {
                         // Pass-through non-key and non-scroll events
@@ -60,6 +180,17 @@ impl Kanata {
 
                 check_for_exit(&key_event);
 
+                if !apply_device_cfg(&kbd_in, device_id, &mut key_event) {
+                    // The key is not in the defsrc of the device's defdevice section.
+                    let mut kanata = kanata.lock();
+                    #[cfg(not(feature = "simulated_output"))]
+                    kanata
+                        .kbd_out
+                        .write_raw(in_event)
+                        .map_err(|e| anyhow!("failed write: {}", e))?;
+                    continue;
+                }
+
                 if key_event.value == KeyValue::Repeat && !allow_hardware_repeat {
                     continue;
                 }
@@ -84,6 +215,11 @@ impl Kanata {
                     };
                 }
 
+                if let Some(Err(e)) = recorder.as_mut().map(|r| r.record(&key_event)) {
+                    log::error!("stopped recording: {e}");
+                    recorder = None;
+                }
+
                 // Send key events to the processing loop
                 if let Err(e) = tx.try_send(key_event) {
                     bail!("failed to send on channel: {}", e)
@@ -128,13 +264,85 @@ impl Kanata {
     }
 }
 
+/// Sends keepalives to the systemd watchdog so that systemd restarts kanata if the processing
+/// loop stops running.
+pub(crate) struct SystemdWatchdog {
+    /// Keepalives are sent at half of the watchdog timeout configured by `WatchdogSec`.
+    pub(crate) interval: time::Duration,
+    last_keepalive: instant::Instant,
+}
+
+impl SystemdWatchdog {
+    /// Returns None if the systemd watchdog is not enabled for this process.
+    pub(crate) fn from_env() -> Option<Self> {
+        let mut usec = 0;
+        if !sd_notify::watchdog_enabled(false, &mut usec) {
+            return None;
+        }
+        let interval = time::Duration::from_micros(usec / 2);
+        log::info!("systemd watchdog enabled; sending keepalives every {interval:?}");
+        Some(Self {
+            interval,
+            last_keepalive: instant::Instant::now(),
+        })
+    }
+
+    pub(crate) fn keepalive_if_due(&mut self) {
+        if self.last_keepalive.elapsed() < self.interval {
+            return;
+        }
+        self.last_keepalive = instant::Instant::now();
+        if let Err(e) = sd_notify::notify(false, &[sd_notify::NotifyState::Watchdog]) {
+            log::error!("failed to send systemd watchdog keepalive: {e}");
+        }
+    }
+}
+
+/// Pass the device changes of the input devices to the processing loop.
+fn queue_device_changes(
+    kanata: &Mutex<Kanata>,
+    kbd_in: &mut KbdIn,
+    tx: &Sender<KeyEvent>,
+) -> Result<()> {
+    let changes = kbd_in.take_device_changes();
+    if changes.is_empty() {
+        return Ok(());
+    }
+    kanata.lock().device_changes.extend(changes);
+    // Wake up the processing loop in case it is blocked waiting for input.
+    if let Err(e) = tx.try_send(KeyEvent::new(OsCode::KEY_RESERVED, KeyValue::WakeUp)) {
+        bail!("failed to send on channel: {}", e)
+    }
+    Ok(())
+}
+
+/// Attach the input device identity to the key event and apply the key mapping of the device's
+/// `defdevice` section. Returns false if the key should not be processed.
+fn apply_device_cfg(kbd_in: &KbdIn, device_id: usize, key_event: &mut KeyEvent) -> bool {
+    let device_cfg = kbd_in.device_cfg(device_id);
+    key_event.device = Some(InputDevice {
+        id: device_id,
+        cfg_idx: device_cfg.map(|(i, _)| i),
+    });
+    match device_cfg {
+        Some((_, device_cfg)) => match device_cfg.map_key(key_event.code) {
+            Some(osc) => {
+                key_event.code = osc;
+                true
+            }
+            None => false,
+        },
+        None => true,
+    }
+}
+
 /// Returns true if the scroll event should be sent to the processing loop, otherwise returns
 /// false.
 fn handle_scroll(
     kanata: &Mutex<Kanata>,
     in_event: InputEvent,
     code: OsCode,
-    all_events: &[InputEvent],
+    all_events: &[(InputEvent, usize)],
 ) -> Result<bool> {
     let direction: MWheelDirection = code.try_into().unwrap();
     let scroll_distance = in_event.value().unsigned_abs() as u16;
@@ -156,7 +364,7 @@ fn handle_scroll(
                     // scroll event. In this scenario, the hi-res event should be used to call
                     // scroll, and not the normal event. Otherwise, too much scrolling will happen.
                     let mut kanata = kanata.lock();
-                    if !all_events.iter().any(|ev| {
+                    if !all_events.iter().any(|(ev, _)| {
                         matches!(
                             ev.kind(),
                             InputEventKind::RelAxis(
@@ -6,7 +6,7 @@ use anyhow::{bail, Result};
 use kanata_parser::sequences::*;
 use log::{error, info};
 use parking_lot::Mutex;
-use std::sync::mpsc::{Receiver, SyncSender as Sender, TryRecvError};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender as Sender, TryRecvError};
 
 #[cfg(feature = "passthru_ahk")]
 use std::sync::mpsc::Sender as ASender;
@@ -28,6 +28,7 @@ use crate::ValidatedArgs;
 use kanata_parser::cfg;
 use kanata_parser::cfg::list_actions::*;
 use kanata_parser::cfg::*;
+pub use kanata_parser::cfg::{CfgDiagnostic, DiagnosticSpan};
 use kanata_parser::custom_action::*;
 pub use kanata_parser::keys::*;
 use kanata_tcp_protocol::ServerMessage;
@@ -36,6 +37,16 @@ mod dynamic_macro;
 use dynamic_macro::*;
 
 mod key_repeat;
+#[cfg(target_os = "linux")]
+use key_repeat::KeyRepeatState;
+
+#[cfg(target_os = "linux")]
+pub(crate) mod sim_recorder;
+
+#[cfg(feature = "simulated_output")]
+mod sim_end_state;
+#[cfg(feature = "simulated_output")]
+pub use sim_end_state::*;
 
 mod sequences;
 use sequences::*;
@@ -55,6 +66,8 @@ pub use windows::*;
 
 #[cfg(target_os = "linux")]
 mod linux;
+#[cfg(target_os = "linux")]
+use linux::SystemdWatchdog;
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -96,6 +109,8 @@ pub struct Kanata {
     pub prev_keys: Vec<KeyCode>,
     /// Used for printing layer info to the info log when changing layers.
     pub layer_info: Vec<LayerInfo>,
+    /// The keys of `defsrc` in the order they are written.
+    pub defsrc: Vec<OsCode>,
     /// Used to track when a layer change occurs.
     pub prev_layer: usize,
     /// Vertical scrolling state tracker. Is Some(...) when a vertical scrolling action is active
@@ -123,6 +138,14 @@ pub struct Kanata {
     pub sequence_timeout: u16,
     /// Tracks sequence progress. Is Some(...) when in sequence mode and None otherwise.
     pub sequence_state: SequenceState,
+    #[cfg(feature = "tcp_server")]
+    /// The user configuration for including virtual key names in sequence progress
+    /// notifications.
+    sequence_progress_show_names: bool,
+    #[cfg(feature = "tcp_server")]
+    /// Sequence keys in the most recent sequence progress notification. Is None when no
+    /// sequence was active at the time of the most recent notification.
+    sequence_progress_reported: Option<Vec<u16>>,
     /// Valid sequences defined in the user configuration.
     pub sequences: cfg::KeySeqsToFKeys,
     /// Stores the user recored dynamic macros.
@@ -149,6 +172,9 @@ pub struct Kanata {
     #[cfg(target_os = "linux")]
     /// Linux input paths in the user configuration.
     pub kbd_in_paths: Vec<String>,
+    /// File to record the input events to, from the `--record` argument.
+    #[cfg(target_os = "linux")]
+    record_path: Option<PathBuf>,
     #[cfg(target_os = "linux")]
     /// Tracks the Linux user configuration to continue or abort if no devices are found.
     continue_if_no_devices: bool,
@@ -160,6 +186,14 @@ pub struct Kanata {
     /// Tracks the Linux user configuration for device names (instead of paths) that should be
     /// excluded for interception and processing by kanata.
     pub exclude_names: Option<Vec<String>>,
+    #[cfg(target_os = "linux")]
+    /// Tracks the Linux user configuration for device property matchers of devices that should be
+    /// included for interception and processing by kanata.
+    pub include_matchers: Vec<DeviceMatcher>,
+    #[cfg(target_os = "linux")]
+    /// Tracks the Linux user configuration for device property matchers of devices that should be
+    /// excluded for interception and processing by kanata.
+    pub exclude_matchers: Vec<DeviceMatcher>,
     #[cfg(all(feature = "interception_driver", target_os = "windows"))]
     /// Used to know which input device to treat as a mouse for intercepting and processing inputs
     /// by kanata.
@@ -183,9 +217,33 @@ pub struct Kanata {
     /// Config items from `defcfg`.
     #[cfg(target_os = "linux")]
     pub x11_repeat_rate: Option<KeyRepeatSettings>,
+    /// Delay and rate of key repeats generated by kanata. If set, hardware repeats are dropped.
+    #[cfg(target_os = "linux")]
+    key_repeat: Option<KeyRepeatSettings>,
+    #[cfg(target_os = "linux")]
+    key_repeat_output: KeyRepeatOutput,
+    #[cfg(target_os = "linux")]
+    key_repeat_state: Option<KeyRepeatState>,
+    /// Input device changes read by the event loop that are not yet handled.
+    #[cfg(target_os = "linux")]
+    pub device_changes: Vec<DeviceChange>,
     /// Determines what types of devices to grab based on autodetection mode.
     #[cfg(target_os = "linux")]
     pub device_detect_mode: DeviceDetectMode,
+    /// Forward absolute axis, misc and switch events to mirror output devices.
+    #[cfg(target_os = "linux")]
+    pub passthru_non_key_events: bool,
+    /// Per-device configuration sections from `defdevice`.
+    #[cfg(target_os = "linux")]
+    pub devices: Vec<DeviceCfg>,
+    /// The base layer group of the input device that the most recent key press was read from.
+    /// A group is the index of a `defdevice` section with a `layer`, or None for the devices
+    /// without a `layer`.
+    #[cfg(target_os = "linux")]
+    device_layer_group: Option<Option<usize>>,
+    /// The base layer of each base layer group, saved when typing moves to another group.
+    #[cfg(target_os = "linux")]
+    device_base_layers: HashMap<Option<usize>, usize>,
     /// Fake key actions that are waiting for a certain duration of keyboard idling.
     pub waiting_for_idle: HashSet<FakeKeyOnIdle>,
     /// Fake key actions that are being held and are pending release.
@@ -300,10 +358,24 @@ impl Kanata {
             #[cfg(target_os = "linux")]
             cfg.options.linux_opts.linux_use_trackpoint_property,
             #[cfg(target_os = "linux")]
-            match cfg.options.linux_opts.linux_output_bus_type {
-                LinuxCfgOutputBusType::BusUsb => evdev::BusType::BUS_USB,
-                LinuxCfgOutputBusType::BusI8042 => evdev::BusType::BUS_I8042,
-            },
+            output_bus_type(cfg.options.linux_opts.linux_output_bus_type),
+            #[cfg(target_os = "linux")]
+            cfg.options.linux_opts.linux_setmouse_screen_extents,
+            #[cfg(target_os = "linux")]
+            &cfg.options.linux_opts.linux_output_device_name,
+            #[cfg(target_os = "linux")]
+            cfg.options
+                .linux_opts
+                .linux_output_pointer_device_name
+                .as_deref()
+                .map(|name| {
+                    let bus_type = cfg
+                        .options
+                        .linux_opts
+                        .linux_output_pointer_bus_type
+                        .unwrap_or(cfg.options.linux_opts.linux_output_bus_type);
+                    (name, output_bus_type(bus_type))
+                }),
         ) {
             Ok(kbd_out) => kbd_out,
             Err(err) => {
@@ -339,6 +411,10 @@ impl Kanata {
         {
             zch().zch_configure(cfg.zippy.unwrap_or_default());
         }
+        #[cfg(feature = "steno")]
+        {
+            steno().stn_configure(cfg.steno.unwrap_or_default());
+        }
 
         Ok(Self {
             kbd_out,
@@ -347,6 +423,7 @@ impl Kanata {
             key_outputs: cfg.key_outputs,
             layout: cfg.layout,
             layer_info: cfg.layer_info,
+            defsrc: cfg.defsrc,
             cur_keys: Vec::new(),
             prev_keys: Vec::new(),
             prev_layer: 0,
@@ -360,6 +437,10 @@ impl Kanata {
             sequence_input_mode: cfg.options.sequence_input_mode,
             sequence_timeout: cfg.options.sequence_timeout,
             sequence_state: SequenceState::new(),
+            #[cfg(feature = "tcp_server")]
+            sequence_progress_show_names: cfg.options.sequence_progress_show_names,
+            #[cfg(feature = "tcp_server")]
+            sequence_progress_reported: None,
             sequences: cfg.sequences,
             last_tick: instant::Instant::now(),
             time_remainder: 0,
@@ -371,11 +452,17 @@ impl Kanata {
             #[cfg(target_os = "linux")]
             kbd_in_paths: cfg.options.linux_opts.linux_dev,
             #[cfg(target_os = "linux")]
+            record_path: args.record_path.clone(),
+            #[cfg(target_os = "linux")]
             continue_if_no_devices: cfg.options.linux_opts.linux_continue_if_no_devs_found,
             #[cfg(target_os = "linux")]
             include_names: cfg.options.linux_opts.linux_dev_names_include,
             #[cfg(target_os = "linux")]
             exclude_names: cfg.options.linux_opts.linux_dev_names_exclude,
+            #[cfg(target_os = "linux")]
+            include_matchers: cfg.options.linux_opts.linux_dev_include_match,
+            #[cfg(target_os = "linux")]
+            exclude_matchers: cfg.options.linux_opts.linux_dev_exclude_match,
             #[cfg(all(feature = "interception_driver", target_os = "windows"))]
             intercept_mouse_hwids: cfg.options.wintercept_opts.windows_interception_mouse_hwids,
             #[cfg(all(feature = "interception_driver", target_os = "windows"))]
@@ -409,11 +496,27 @@ impl Kanata {
             #[cfg(target_os = "linux")]
             x11_repeat_rate: cfg.options.linux_opts.linux_x11_repeat_delay_rate,
             #[cfg(target_os = "linux")]
+            key_repeat: cfg.options.linux_opts.linux_key_repeat_delay_rate,
+            #[cfg(target_os = "linux")]
+            key_repeat_output: cfg.options.linux_opts.linux_key_repeat_output,
+            #[cfg(target_os = "linux")]
+            key_repeat_state: None,
+            #[cfg(target_os = "linux")]
+            device_changes: vec![],
+            #[cfg(target_os = "linux")]
             device_detect_mode: cfg
                 .options
                 .linux_opts
                 .linux_device_detect_mode
                 .expect("parser should default to some"),
+            #[cfg(target_os = "linux")]
+            passthru_non_key_events: cfg.options.linux_opts.linux_passthru_non_key_events,
+            #[cfg(target_os = "linux")]
+            devices: cfg.devices,
+            #[cfg(target_os = "linux")]
+            device_layer_group: None,
+            #[cfg(target_os = "linux")]
+            device_base_layers: HashMap::default(),
             waiting_for_idle: HashSet::default(),
             vkeys_pending_release: HashMap::default(),
             ticks_since_idle: 0,
@@ -439,11 +542,12 @@ impl Kanata {
         Ok(Arc::new(Mutex::new(Self::new(args)?)))
     }
 
+    /// Parse errors of the configuration are returned as a [`CfgDiagnostic`].
     pub fn new_from_str(cfg: &str, file_content: HashMap<String, String>) -> Result<Self> {
         let cfg = match cfg::new_from_str(cfg, file_content) {
             Ok(c) => c,
             Err(e) => {
-                bail!("{e:?}");
+                return Err(CfgDiagnostic::from_report(&e).into());
             }
         };
 
@@ -453,10 +557,24 @@ impl Kanata {
             #[cfg(target_os = "linux")]
             cfg.options.linux_opts.linux_use_trackpoint_property,
             #[cfg(target_os = "linux")]
-            match cfg.options.linux_opts.linux_output_bus_type {
-                LinuxCfgOutputBusType::BusUsb => evdev::BusType::BUS_USB,
-                LinuxCfgOutputBusType::BusI8042 => evdev::BusType::BUS_I8042,
-            },
+            output_bus_type(cfg.options.linux_opts.linux_output_bus_type),
+            #[cfg(target_os = "linux")]
+            cfg.options.linux_opts.linux_setmouse_screen_extents,
+            #[cfg(target_os = "linux")]
+            &cfg.options.linux_opts.linux_output_device_name,
+            #[cfg(target_os = "linux")]
+            cfg.options
+                .linux_opts
+                .linux_output_pointer_device_name
+                .as_deref()
+                .map(|name| {
+                    let bus_type = cfg
+                        .options
+                        .linux_opts
+                        .linux_output_pointer_bus_type
+                        .unwrap_or(cfg.options.linux_opts.linux_output_bus_type);
+                    (name, output_bus_type(bus_type))
+                }),
         ) {
             Ok(kbd_out) => kbd_out,
             Err(err) => {
@@ -470,6 +588,10 @@ impl Kanata {
         {
             zch().zch_configure(cfg.zippy.unwrap_or_default());
         }
+        #[cfg(feature = "steno")]
+        {
+            steno().stn_configure(cfg.steno.unwrap_or_default());
+        }
 
         Ok(Self {
             kbd_out,
@@ -478,6 +600,7 @@ impl Kanata {
             key_outputs: cfg.key_outputs,
             layout: cfg.layout,
             layer_info: cfg.layer_info,
+            defsrc: cfg.defsrc,
             cur_keys: Vec::new(),
             prev_keys: Vec::new(),
             prev_layer: 0,
@@ -491,6 +614,10 @@ impl Kanata {
             sequence_input_mode: cfg.options.sequence_input_mode,
             sequence_timeout: cfg.options.sequence_timeout,
             sequence_state: SequenceState::new(),
+            #[cfg(feature = "tcp_server")]
+            sequence_progress_show_names: cfg.options.sequence_progress_show_names,
+            #[cfg(feature = "tcp_server")]
+            sequence_progress_reported: None,
             sequences: cfg.sequences,
             last_tick: instant::Instant::now(),
             time_remainder: 0,
@@ -502,11 +629,17 @@ impl Kanata {
             #[cfg(target_os = "linux")]
             kbd_in_paths: cfg.options.linux_opts.linux_dev,
             #[cfg(target_os = "linux")]
+            record_path: None,
+            #[cfg(target_os = "linux")]
             continue_if_no_devices: cfg.options.linux_opts.linux_continue_if_no_devs_found,
             #[cfg(target_os = "linux")]
             include_names: cfg.options.linux_opts.linux_dev_names_include,
             #[cfg(target_os = "linux")]
             exclude_names: cfg.options.linux_opts.linux_dev_names_exclude,
+            #[cfg(target_os = "linux")]
+            include_matchers: cfg.options.linux_opts.linux_dev_include_match,
+            #[cfg(target_os = "linux")]
+            exclude_matchers: cfg.options.linux_opts.linux_dev_exclude_match,
             #[cfg(all(feature = "interception_driver", target_os = "windows"))]
             intercept_mouse_hwids: cfg.options.wintercept_opts.windows_interception_mouse_hwids,
             #[cfg(all(feature = "interception_driver", target_os = "windows"))]
@@ -540,11 +673,27 @@ impl Kanata {
             #[cfg(target_os = "linux")]
             x11_repeat_rate: cfg.options.linux_opts.linux_x11_repeat_delay_rate,
             #[cfg(target_os = "linux")]
+            key_repeat: cfg.options.linux_opts.linux_key_repeat_delay_rate,
+            #[cfg(target_os = "linux")]
+            key_repeat_output: cfg.options.linux_opts.linux_key_repeat_output,
+            #[cfg(target_os = "linux")]
+            key_repeat_state: None,
+            #[cfg(target_os = "linux")]
+            device_changes: vec![],
+            #[cfg(target_os = "linux")]
             device_detect_mode: cfg
                 .options
                 .linux_opts
                 .linux_device_detect_mode
                 .expect("parser should default to some"),
+            #[cfg(target_os = "linux")]
+            passthru_non_key_events: cfg.options.linux_opts.linux_passthru_non_key_events,
+            #[cfg(target_os = "linux")]
+            devices: cfg.devices,
+            #[cfg(target_os = "linux")]
+            device_layer_group: None,
+            #[cfg(target_os = "linux")]
+            device_base_layers: HashMap::default(),
             waiting_for_idle: HashSet::default(),
             vkeys_pending_release: HashMap::default(),
             ticks_since_idle: 0,
@@ -590,9 +739,14 @@ impl Kanata {
         self.sequence_always_on = cfg.options.sequence_always_on;
         self.sequence_input_mode = cfg.options.sequence_input_mode;
         self.sequence_timeout = cfg.options.sequence_timeout;
+        #[cfg(feature = "tcp_server")]
+        {
+            self.sequence_progress_show_names = cfg.options.sequence_progress_show_names;
+        }
         self.layout = cfg.layout;
         self.key_outputs = cfg.key_outputs;
         self.layer_info = cfg.layer_info;
+        self.defsrc = cfg.defsrc;
         self.sequences = cfg.sequences;
         self.overrides = cfg.overrides;
         self.log_layer_changes =
@@ -609,6 +763,14 @@ impl Kanata {
             self.virtual_keys = cfg.fake_keys;
         }
         self.switch_max_key_timing = cfg.switch_max_key_timing;
+        #[cfg(target_os = "linux")]
+        {
+            self.devices = cfg.devices;
+            self.passthru_non_key_events = cfg.options.linux_opts.linux_passthru_non_key_events;
+            linux::INPUT_CFG_CHANGED.store(true, std::sync::atomic::Ordering::SeqCst);
+            self.device_layer_group = None;
+            self.device_base_layers.clear();
+        }
         #[cfg(all(target_os = "windows", feature = "gui"))]
         {
             self.gui_opts.tray_icon = cfg.options.gui_opts.tray_icon;
@@ -626,10 +788,19 @@ impl Kanata {
         {
             zch().zch_configure(cfg.zippy.unwrap_or_default());
         }
+        #[cfg(feature = "steno")]
+        {
+            steno().stn_configure(cfg.steno.unwrap_or_default());
+        }
 
         *MAPPED_KEYS.lock() = cfg.mapped_keys;
         #[cfg(target_os = "linux")]
-        Kanata::set_repeat_rate(cfg.options.linux_opts.linux_x11_repeat_delay_rate)?;
+        {
+            Kanata::set_repeat_rate(cfg.options.linux_opts.linux_x11_repeat_delay_rate)?;
+            self.key_repeat = cfg.options.linux_opts.linux_key_repeat_delay_rate;
+            self.key_repeat_output = cfg.options.linux_opts.linux_key_repeat_output;
+            self.key_repeat_state = None;
+        }
         log::info!("Live reload successful");
         #[cfg(feature = "tcp_server")]
         if let Some(tx) = _tx {
@@ -679,8 +850,14 @@ impl Kanata {
         log::debug!("process recv ev {event:?}");
         let evc: u16 = event.code.into();
         self.ticks_since_idle = 0;
+        #[cfg(target_os = "linux")]
+        if self.update_key_repeat(event) {
+            return Ok(());
+        }
         let kbrn_ev = match event.value {
             KeyValue::Press => {
+                #[cfg(target_os = "linux")]
+                self.handle_input_device_layer(event.device);
                 if let Some((macro_id, recorded_macro)) = record_press(
                     &mut self.dynamic_macro_record_state,
                     event.code,
@@ -732,6 +909,9 @@ impl Kanata {
 
         self.tick_ms(ms_elapsed, tx)?;
 
+        #[cfg(target_os = "linux")]
+        self.handle_device_changes(tx);
+
         self.last_tick = match ms_elapsed {
             0 => self.last_tick,
             1..=10 => now,
@@ -820,7 +1000,13 @@ impl Kanata {
     }
 
     fn tick_states(&mut self, _tx: &Option<Sender<ServerMessage>>) -> Result<()> {
+        // Must happen before the key state changes are handled because handling a repeat clears
+        // cur_keys.
+        #[cfg(target_os = "linux")]
+        self.tick_key_repeat()?;
         self.live_reload_requested |= self.handle_keystate_changes(_tx)?;
+        #[cfg(feature = "tcp_server")]
+        self.check_handle_sequence_progress(_tx);
         self.handle_scrolling()?;
         self.handle_move_mouse()?;
         self.tick_sequence_state()?;
@@ -833,6 +1019,8 @@ impl Kanata {
         self.tick_held_vkeys();
         #[cfg(feature = "simulated_output")]
         {
+            // The simulator only calls tick_ms, so check for layer changes here instead.
+            self.check_handle_layer_change(_tx);
             self.kbd_out.tick();
         }
         Ok(())
@@ -995,6 +1183,7 @@ impl Kanata {
     fn handle_keystate_changes(&mut self, _tx: &Option<Sender<ServerMessage>>) -> Result<bool> {
         let layout = self.layout.bm();
         let custom_event = layout.tick();
+        steno_tick(layout.current_layer());
         let mut live_reload_requested = false;
         let cur_keys = &mut self.cur_keys;
         cur_keys.extend(layout.keycodes());
@@ -1209,6 +1398,8 @@ impl Kanata {
                 let mut cmds = vec![];
                 let mut prev_mouse_btn = None;
                 for custact in custacts.iter() {
+                    #[cfg(feature = "simulated_output")]
+                    self.kbd_out.json.custom_action(custact);
                     match custact {
                         // For unicode, only send on the press. No repeat action is supported for this for
                         // now.
@@ -1596,7 +1787,7 @@ impl Kanata {
                         | CustomAction::Unmodded { .. }
                         | CustomAction::Unshifted { .. }
                         // Note: ReverseReleaseOrder is already handled earlier on.
-                        | CustomAction::ReverseReleaseOrder { .. }
+                        | CustomAction::ReverseReleaseOrder
                         | CustomAction::CancelMacroOnRelease => {}
                     }
                 }
@@ -1736,6 +1927,8 @@ impl Kanata {
             let new = self.layer_info[cur_layer].name.clone();
             self.prev_layer = cur_layer;
             self.print_layer(cur_layer);
+            #[cfg(feature = "simulated_output")]
+            self.kbd_out.json.layer(&new);
 
             #[cfg(feature = "tcp_server")]
             if let Some(tx) = tx {
@@ -1751,6 +1944,38 @@ impl Kanata {
         }
     }
 
+    /// Sends a notification to all connected clients if the keys of the active sequence changed,
+    /// or if sequence mode was entered or exited.
+    #[cfg(feature = "tcp_server")]
+    fn check_handle_sequence_progress(&mut self, tx: &Option<Sender<ServerMessage>>) {
+        let Some(tx) = tx else {
+            return;
+        };
+        let progress = self
+            .sequence_state
+            .is_active()
+            .then_some(&self.sequence_state.sequence);
+        if progress == self.sequence_progress_reported.as_ref() {
+            return;
+        }
+        self.sequence_progress_reported = progress.cloned();
+        let msg = match self.sequence_progress_reported {
+            Some(_) => sequence_progress_message(
+                &self.sequence_state,
+                &self.sequences,
+                self.sequence_progress_show_names
+                    .then_some(&self.virtual_keys),
+            ),
+            None => ServerMessage::SequenceEnd {},
+        };
+        match tx.try_send(msg) {
+            Ok(_) => {}
+            Err(error) => {
+                log::error!("could not send sequence event notification: {}", error);
+            }
+        }
+    }
+
     fn print_layer(&self, layer: usize) {
         if self.log_layer_changes {
             log::info!("Entered layer:\n\n{}", self.layer_info[layer].cfg_text);
@@ -1843,7 +2068,14 @@ impl Kanata {
             #[cfg(all(not(feature = "interception_driver"), target_os = "windows"))]
             let mut last_input_time = instant::Instant::now();
 
+            #[cfg(target_os = "linux")]
+            let mut watchdog = SystemdWatchdog::from_env();
+
             let err = loop {
+                #[cfg(target_os = "linux")]
+                if let Some(watchdog) = watchdog.as_mut() {
+                    watchdog.keepalive_if_due();
+                }
                 let can_block = {
                     let mut k = kanata.lock();
                     k.can_block_update_idle_waiting(ms_elapsed)
@@ -1857,7 +2089,16 @@ impl Kanata {
                     kanata.lock().win_synchronize_keystates();
 
                     log::trace!("blocking on channel");
-                    match rx.recv() {
+                    // With the systemd watchdog, wake up in time to send the next keepalive even
+                    // when there is no input.
+                    #[cfg(target_os = "linux")]
+                    let recv = match watchdog.as_ref() {
+                        Some(watchdog) => rx.recv_timeout(watchdog.interval),
+                        None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    };
+                    #[cfg(not(target_os = "linux"))]
+                    let recv = rx.recv().map_err(|_| RecvTimeoutError::Disconnected);
+                    match recv {
                         Ok(kev) => {
                             let mut k = kanata.lock();
                             let now = instant::Instant::now()
@@ -1936,7 +2177,8 @@ impl Kanata {
                                 (start.elapsed()).as_nanos()
                             );
                         }
-                        Err(_) => {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => {
                             log::error!("channel disconnected");
                             return;
                         }
@@ -2088,11 +2330,17 @@ impl Kanata {
     }
 
     pub fn is_idle(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if self.key_repeat_state.is_some() || !self.device_changes.is_empty() {
+            return false;
+        }
         let pressed_keys_means_not_idle =
             !self.waiting_for_idle.is_empty() || self.live_reload_requested;
         self.layout.b().queue.is_empty()
             && zippy_is_idle()
+            && steno_is_idle()
             && self.layout.b().waiting.is_none()
+            && !self.layout.b().is_autoshift_pending()
             && self.layout.b().last_press_tracker.tap_hold_timeout == 0
             && (self.layout.b().oneshot.timeout == 0 || self.layout.b().oneshot.keys.is_empty())
             && self.layout.b().active_sequences.is_empty()
@@ -2121,6 +2369,14 @@ impl Kanata {
     }
 }
 
+#[cfg(target_os = "linux")]
+fn output_bus_type(bus_type: LinuxCfgOutputBusType) -> evdev::BusType {
+    match bus_type {
+        LinuxCfgOutputBusType::BusUsb => evdev::BusType::BUS_USB,
+        LinuxCfgOutputBusType::BusI8042 => evdev::BusType::BUS_I8042,
+    }
+}
+
 #[test]
 fn test_unmodmods_bits() {
     assert_eq!(UnmodMods::empty().bits(), 0u8);
@@ -2185,21 +2441,25 @@ fn apply_speed_modifiers() {
 #[cfg(feature = "passthru_ahk")]
 /// Clean kanata's state without exiting
 pub fn clean_state(kanata: &Arc<Mutex<Kanata>>, tick: u128) -> Result<()> {
-    let mut k = kanata.lock();
-    #[cfg(all(not(feature = "interception_driver"), target_os = "windows"))]
-    let layout = k.layout.bm();
-    #[cfg(all(not(feature = "interception_driver"), target_os = "windows"))]
-    release_normalkey_states(layout);
-    k.tick_ms(tick, &None)?;
-    #[cfg(not(target_os = "linux"))]
-    {
-        let mut k_pressed = PRESSED_KEYS.lock();
-        for key_os in k_pressed.clone() {
-            k.kbd_out.release_key(key_os)?;
+    kanata.lock().clean_state(tick)
+}
+
+impl Kanata {
+    /// Clean kanata's state without exiting
+    pub fn clean_state(&mut self, tick: u128) -> Result<()> {
+        #[cfg(all(not(feature = "interception_driver"), target_os = "windows"))]
+        release_normalkey_states(self.layout.bm());
+        self.tick_ms(tick, &None)?;
+        #[cfg(not(target_os = "linux"))]
+        {
+            let mut k_pressed = PRESSED_KEYS.lock();
+            for key_os in k_pressed.clone() {
+                self.kbd_out.release_key(key_os)?;
+            }
+            k_pressed.clear();
         }
-        k_pressed.clear();
+        Ok(())
     }
-    Ok(())
 }
 
 /// Checks if kanata should exit based on the fixed key combination of:
@@ -2256,6 +2516,7 @@ fn update_kbd_out(_cfg: &CfgOptions, _kbd_out: &KbdOut) -> Result<()> {
     {
         _kbd_out.update_unicode_termination(_cfg.linux_opts.linux_unicode_termination);
         _kbd_out.update_unicode_u_code(_cfg.linux_opts.linux_unicode_u_code);
+        _kbd_out.update_unicode_output(&_cfg.linux_opts);
     }
     Ok(())
 }
@@ -5,6 +5,11 @@ mod zippychord;
 #[cfg(feature = "zippychord")]
 pub(crate) use zippychord::*;
 
+#[cfg(feature = "steno")]
+mod steno;
+#[cfg(feature = "steno")]
+pub(crate) use steno::*;
+
 // Functions to send keys except those that fall in the ignorable range.
 // And also have been repurposed to have additional logic to send mouse events, out of convenience.
 //
@@ -98,6 +103,10 @@ fn osc_to_wheel_direction(osc: OsCode) -> MWheelDirection {
 }
 
 fn post_filter_press(kb: &mut KbdOut, osc: OsCode) -> Result<(), std::io::Error> {
+    #[cfg(feature = "steno")]
+    if steno().stn_press_key(osc) {
+        return Ok(());
+    }
     #[cfg(not(feature = "zippychord"))]
     {
         kb.press_key(osc)
@@ -109,6 +118,10 @@ fn post_filter_press(kb: &mut KbdOut, osc: OsCode) -> Result<(), std::io::Error>
 }
 
 fn post_filter_release(kb: &mut KbdOut, osc: OsCode) -> Result<(), std::io::Error> {
+    #[cfg(feature = "steno")]
+    if steno().stn_release_key(kb, osc)? {
+        return Ok(());
+    }
     #[cfg(not(feature = "zippychord"))]
     {
         kb.release_key(osc)
@@ -136,3 +149,21 @@ pub(super) fn zippy_tick(_caps_word_is_active: bool) {
         zch().zch_tick(_caps_word_is_active)
     }
 }
+
+pub(super) fn steno_is_idle() -> bool {
+    #[cfg(not(feature = "steno"))]
+    {
+        true
+    }
+    #[cfg(feature = "steno")]
+    {
+        steno().stn_is_idle()
+    }
+}
+
+pub(super) fn steno_tick(_active_layer: usize) {
+    #[cfg(feature = "steno")]
+    {
+        steno().stn_set_active_layer(_active_layer)
+    }
+}
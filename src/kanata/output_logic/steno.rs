@@ -0,0 +1,308 @@
+use super::*;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+static STENO: Lazy<Mutex<StenoState>> = Lazy::new(|| Mutex::new(Default::default()));
+
+pub(crate) fn steno() -> MutexGuard<'static, StenoState> {
+    match STENO.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            let mut inner = poisoned.into_inner();
+            inner.stn_reset();
+            inner
+        }
+    }
+}
+
+/// The maximum number of translations remembered for undo and multi-stroke entries.
+const STENO_HISTORY_LEN: usize = 100;
+
+/// Formatting state that carries over from one translation to the next.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct StenoFormatState {
+    /// No space should be added before the next translation.
+    /// This is initially true so that no space is typed before the first translation.
+    stn_attach_next: bool,
+    /// The first character of the next translation should be capitalized.
+    stn_capitalize_next: bool,
+    /// The previous translation was fingerspelling glue.
+    stn_prev_glue: bool,
+}
+
+/// A translation that has been typed.
+#[derive(Debug, Clone)]
+struct StenoRecord {
+    /// Strokes that produced this translation.
+    stn_strokes: Vec<String>,
+    /// Characters that were typed, including any added space.
+    stn_typed: Vec<StenoOutput>,
+    /// Formatting state before and after this translation.
+    stn_fmt_before: StenoFormatState,
+    stn_fmt_after: StenoFormatState,
+    /// Previous translations that were erased to type this multi-stroke translation.
+    /// These are restored if this translation is undone.
+    stn_replaced: Vec<StenoRecord>,
+}
+
+#[derive(Debug)]
+pub(crate) struct StenoState {
+    /// Configuration and dictionary. This is fixed at runtime other than live-reloads replacing
+    /// the state.
+    stn_cfg: StenoConfig,
+    /// Steno output keys that are currently held.
+    stn_held_keys: Vec<OsCode>,
+    /// All steno keys pressed since the first press of the current stroke.
+    stn_stroke: StenoStroke,
+    /// Previously typed translations, most recent last.
+    stn_history: Vec<StenoRecord>,
+    /// Current formatting state.
+    stn_fmt: StenoFormatState,
+    /// Currently active layer, which determines if steno is active.
+    stn_active_layer: usize,
+}
+
+impl Default for StenoState {
+    fn default() -> Self {
+        Self {
+            stn_cfg: StenoConfig::default(),
+            stn_held_keys: vec![],
+            stn_stroke: StenoStroke::default(),
+            stn_history: vec![],
+            stn_fmt: StenoFormatState {
+                stn_attach_next: true,
+                ..Default::default()
+            },
+            stn_active_layer: 0,
+        }
+    }
+}
+
+impl StenoState {
+    /// Configure steno behaviour.
+    pub(crate) fn stn_configure(&mut self, cfg: StenoConfig) {
+        self.stn_cfg = cfg;
+        self.stn_reset();
+    }
+
+    fn stn_reset(&mut self) {
+        log::debug!("steno reset state");
+        let cfg = std::mem::take(&mut self.stn_cfg);
+        *self = Self {
+            stn_cfg: cfg,
+            ..Default::default()
+        };
+    }
+
+    pub(crate) fn stn_set_active_layer(&mut self, layer: usize) {
+        self.stn_active_layer = layer;
+    }
+
+    /// Returns true if steno state has no further processing so the idling optimization can
+    /// activate.
+    pub(crate) fn stn_is_idle(&self) -> bool {
+        self.stn_held_keys.is_empty()
+    }
+
+    fn stn_is_active(&self) -> bool {
+        !self.stn_cfg.stn_is_empty()
+            && (self.stn_cfg.stn_layers.is_empty()
+                || self.stn_cfg.stn_layers.contains(&self.stn_active_layer))
+    }
+
+    /// Steno handling for key presses.
+    /// Returns true if the key was consumed as a steno key.
+    pub(crate) fn stn_press_key(&mut self, osc: OsCode) -> bool {
+        if !self.stn_is_active() {
+            return false;
+        }
+        let Some(steno_key) = self.stn_cfg.stn_keys.get(&osc).copied() else {
+            return false;
+        };
+        if !self.stn_held_keys.contains(&osc) {
+            self.stn_held_keys.push(osc);
+        }
+        self.stn_stroke.stn_insert(steno_key);
+        true
+    }
+
+    /// Steno handling for key releases.
+    /// Returns true if the key was consumed as a steno key.
+    /// Once all steno keys are released, the stroke is translated and typed.
+    pub(crate) fn stn_release_key(
+        &mut self,
+        kb: &mut KbdOut,
+        osc: OsCode,
+    ) -> Result<bool, std::io::Error> {
+        let Some(idx) = self.stn_held_keys.iter().position(|k| *k == osc) else {
+            return Ok(false);
+        };
+        self.stn_held_keys.swap_remove(idx);
+        if self.stn_held_keys.is_empty() && !self.stn_stroke.stn_is_empty() {
+            let stroke = std::mem::take(&mut self.stn_stroke).to_string();
+            self.stn_handle_stroke(kb, stroke)?;
+        }
+        Ok(true)
+    }
+
+    fn stn_handle_stroke(&mut self, kb: &mut KbdOut, stroke: String) -> Result<(), std::io::Error> {
+        log::debug!("steno stroke: {stroke}");
+        // Find the longest dictionary entry that ends with this stroke,
+        // combining it with previously typed translations.
+        let mut translation = None;
+        let mut combined_record_count = 0;
+        let mut stroke_count = 1;
+        let mut strokes = vec![stroke.clone()];
+        for (i, record) in self.stn_history.iter().rev().enumerate() {
+            stroke_count += record.stn_strokes.len();
+            if stroke_count > self.stn_cfg.stn_max_strokes {
+                break;
+            }
+            strokes.splice(0..0, record.stn_strokes.iter().cloned());
+            if let Some(t) = self.stn_cfg.stn_dictionary.get(&strokes.join("/")) {
+                if !t.stn_undo {
+                    translation = Some(t.clone());
+                    combined_record_count = i + 1;
+                }
+            }
+        }
+        let translation = match translation {
+            Some(t) => t,
+            None => {
+                combined_record_count = 0;
+                match self.stn_cfg.stn_dictionary.get(&stroke) {
+                    Some(t) if t.stn_undo => return self.stn_undo(kb),
+                    Some(t) => t.clone(),
+                    None if stroke == "*" => return self.stn_undo(kb),
+                    None => Arc::new(untranslated(&stroke)),
+                }
+            }
+        };
+
+        let replaced = self
+            .stn_history
+            .split_off(self.stn_history.len() - combined_record_count);
+        for record in replaced.iter().rev() {
+            backspace(kb, record.stn_typed.len())?;
+        }
+        let fmt_before = replaced
+            .first()
+            .map(|r| r.stn_fmt_before)
+            .unwrap_or(self.stn_fmt);
+        let strokes = replaced
+            .iter()
+            .flat_map(|r| r.stn_strokes.iter().cloned())
+            .chain(std::iter::once(stroke))
+            .collect();
+
+        let (typed, fmt_after) = format_translation(&translation, fmt_before);
+        type_outputs(kb, &typed)?;
+        self.stn_fmt = fmt_after;
+        self.stn_history.push(StenoRecord {
+            stn_strokes: strokes,
+            stn_typed: typed,
+            stn_fmt_before: fmt_before,
+            stn_fmt_after: fmt_after,
+            stn_replaced: replaced,
+        });
+        if self.stn_history.len() > STENO_HISTORY_LEN {
+            self.stn_history.remove(0);
+        }
+        Ok(())
+    }
+
+    /// Erase the most recent translation,
+    /// restoring the translations it replaced if it was a multi-stroke translation.
+    fn stn_undo(&mut self, kb: &mut KbdOut) -> Result<(), std::io::Error> {
+        let Some(record) = self.stn_history.pop() else {
+            return Ok(());
+        };
+        backspace(kb, record.stn_typed.len())?;
+        self.stn_fmt = record.stn_fmt_before;
+        for replaced in record.stn_replaced {
+            type_outputs(kb, &replaced.stn_typed)?;
+            self.stn_fmt = replaced.stn_fmt_after;
+            self.stn_history.push(replaced);
+        }
+        Ok(())
+    }
+}
+
+/// A stroke that is not in the dictionary is typed as-is.
+fn untranslated(stroke: &str) -> StenoTranslation {
+    StenoTranslation {
+        stn_text: stroke
+            .chars()
+            .filter_map(StenoOutput::stn_from_char)
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Determine the characters to type for a translation given the current formatting state,
+/// and the formatting state afterwards.
+fn format_translation(
+    t: &StenoTranslation,
+    fmt: StenoFormatState,
+) -> (Vec<StenoOutput>, StenoFormatState) {
+    let mut typed = Vec::with_capacity(t.stn_text.len() + 1);
+    let attach = fmt.stn_attach_next || t.stn_attach_start || (t.stn_glue && fmt.stn_prev_glue);
+    if !attach && !t.stn_text.is_empty() {
+        typed.push(StenoOutput {
+            stn_osc: OsCode::KEY_SPACE,
+            stn_shifted: false,
+        });
+    }
+    let mut capitalize = fmt.stn_capitalize_next;
+    for out in t.stn_text.iter().copied() {
+        if capitalize && out.stn_osc != OsCode::KEY_SPACE {
+            capitalize = false;
+            if out.stn_is_letter() {
+                typed.push(StenoOutput {
+                    stn_shifted: true,
+                    ..out
+                });
+                continue;
+            }
+        }
+        typed.push(out);
+    }
+    let fmt_after = if t.stn_text.is_empty() && !t.stn_attach_start && !t.stn_attach_end {
+        // Formatting-only translations like {-|} keep the current attach state.
+        StenoFormatState {
+            stn_capitalize_next: t.stn_capitalize_next || capitalize,
+            ..fmt
+        }
+    } else {
+        StenoFormatState {
+            stn_attach_next: t.stn_attach_end,
+            stn_capitalize_next: t.stn_capitalize_next || capitalize,
+            stn_prev_glue: t.stn_glue,
+        }
+    };
+    (typed, fmt_after)
+}
+
+fn backspace(kb: &mut KbdOut, count: usize) -> Result<(), std::io::Error> {
+    for _ in 0..count {
+        kb.press_key(OsCode::KEY_BACKSPACE)?;
+        kb.release_key(OsCode::KEY_BACKSPACE)?;
+    }
+    Ok(())
+}
+
+fn type_outputs(kb: &mut KbdOut, outputs: &[StenoOutput]) -> Result<(), std::io::Error> {
+    for out in outputs.iter().copied() {
+        if out.stn_shifted {
+            kb.press_key(OsCode::KEY_LEFTSHIFT)?;
+        }
+        kb.press_key(out.stn_osc)?;
+        kb.release_key(out.stn_osc)?;
+        if out.stn_shifted {
+            kb.release_key(OsCode::KEY_LEFTSHIFT)?;
+        }
+    }
+    Ok(())
+}
@@ -357,3 +357,68 @@ pub(super) fn cancel_sequence(state: &mut SequenceState, kbd_out: &mut KbdOut) -
     }
     Ok(())
 }
+
+/// Builds the notification describing the typed keys of the active sequence and the sequences
+/// that can still be completed from it.
+#[cfg(feature = "tcp_server")]
+pub(super) fn sequence_progress_message(
+    state: &SequenceState,
+    sequences: &kanata_parser::trie::Trie<(u8, u16)>,
+    vkey_names: Option<&HashMap<String, usize>>,
+) -> ServerMessage {
+    use kanata_tcp_protocol::SequenceCompletion;
+    let typed_len = state.sequence.len();
+    let completions = sequences
+        .descendants(&state.sequence)
+        .into_iter()
+        .map(|(keys, (_, y))| SequenceCompletion {
+            remaining: seq_keys_to_strings(&keys[typed_len..]),
+            name: vkey_names.and_then(|names| {
+                names
+                    .iter()
+                    .find(|(_, idx)| **idx == usize::from(y))
+                    .map(|(name, _)| name.clone())
+            }),
+        })
+        .collect();
+    ServerMessage::SequenceProgress {
+        typed: seq_keys_to_strings(&state.sequence),
+        completions,
+    }
+}
+
+/// Converts the internal sequence representation into readable key names, using the same
+/// modifier prefixes as `defseq`.
+#[cfg(feature = "tcp_server")]
+fn seq_keys_to_strings(keys: &[u16]) -> Vec<String> {
+    const PREFIXES: [(u16, &str); 6] = [
+        (KEY_OVERLAP_MARKER, "O-"),
+        (0x8000, "S-"),
+        (0x4000, "C-"),
+        (0x2000, "A-"),
+        (0x1000, "AG-"),
+        (0x0800, "M-"),
+    ];
+    keys.iter()
+        .copied()
+        // A lone overlap marker terminates an O-(...) group and is not a key.
+        .filter(|k| *k != KEY_OVERLAP_MARKER)
+        .filter_map(|k| {
+            let osc = OsCode::from_u16(k & MASK_KEYCODES)?;
+            let kc = KeyCode::from(osc);
+            if k & mod_mask_for_keycode(kc) & MASK_MODDED != 0 {
+                // The modifier press of a chord like S-(a b) is shown via its prefix on the
+                // chorded keys instead.
+                return None;
+            }
+            let mut key_str = String::new();
+            for (mask, prefix) in PREFIXES {
+                if k & mask != 0 {
+                    key_str.push_str(prefix);
+                }
+            }
+            key_str.push_str(&kc.to_string());
+            Some(key_str)
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+//! Report of the state left over at the end of a simulation, to help find keys or layers that
+//! stay active when they should not.
+
+use std::fmt;
+
+use super::Kanata;
+
+/// State that is still active at the end of a simulation.
+pub struct SimEndState {
+    /// Output keys and mouse buttons that are still pressed.
+    pub held_outputs: Vec<String>,
+    /// Names of the layers that are active while held, most recent first.
+    pub held_layers: Vec<String>,
+    /// Name of the base layer if it is not the first layer, e.g. after `layer-switch`.
+    pub switched_base_layer: Option<String>,
+    /// Actions that are still waiting for more input or for a timeout.
+    pub pending: Vec<&'static str>,
+}
+
+impl SimEndState {
+    /// Returns true if no output, held layer or pending action is left over. A switched base
+    /// layer is reported but considered clean since switching is usually intended.
+    pub fn is_clean(&self) -> bool {
+        self.held_outputs.is_empty() && self.held_layers.is_empty() && self.pending.is_empty()
+    }
+}
+
+impl fmt::Display for SimEndState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_clean() && self.switched_base_layer.is_none() {
+            return write!(f, "end state is clean");
+        }
+        let mut items = vec![];
+        if !self.held_outputs.is_empty() {
+            items.push(format!(
+                "output keys still pressed: {}",
+                self.held_outputs.join(", ")
+            ));
+        }
+        if !self.held_layers.is_empty() {
+            items.push(format!(
+                "layers still held: {}",
+                self.held_layers.join(", ")
+            ));
+        }
+        if let Some(layer) = &self.switched_base_layer {
+            items.push(format!("base layer switched to: {layer}"));
+        }
+        if !self.pending.is_empty() {
+            items.push(format!("still pending: {}", self.pending.join(", ")));
+        }
+        write!(f, "{}", items.join("\n"))
+    }
+}
+
+impl Kanata {
+    pub fn sim_end_state(&self) -> SimEndState {
+        let layout = self.layout.b();
+        let layer_name = |layer: usize| self.layer_info[layer].name.clone();
+        let mut pending = vec![];
+        if layout.waiting.is_some() {
+            pending.push("tap-hold or chord");
+        }
+        if layout.is_autoshift_pending() {
+            pending.push("auto-shift");
+        }
+        if layout.tap_dance_eager.is_some() {
+            pending.push("tap-dance");
+        }
+        if !layout.oneshot.keys.is_empty() {
+            pending.push("one-shot");
+        }
+        if layout
+            .chords_v2
+            .as_ref()
+            .is_some_and(|cv2| !cv2.is_idle_chv2())
+        {
+            pending.push("chord");
+        }
+        if !layout.queue.is_empty() {
+            pending.push("queued inputs");
+        }
+        if self.sequence_state.is_active() {
+            pending.push("sequence");
+        }
+        if self.dynamic_macro_record_state.is_some() {
+            pending.push("dynamic macro recording");
+        }
+        if self.dynamic_macro_replay_state.is_some() {
+            pending.push("dynamic macro replay");
+        }
+        if self.caps_word.is_some() {
+            pending.push("caps-word");
+        }
+        SimEndState {
+            held_outputs: self.kbd_out.outputs.held.clone(),
+            held_layers: layout
+                .active_held_layers()
+                .map(|layer| layer_name(layer.into()))
+                .collect(),
+            switched_base_layer: (layout.default_layer != 0)
+                .then(|| layer_name(layout.default_layer)),
+            pending,
+        }
+    }
+}
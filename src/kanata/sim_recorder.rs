@@ -0,0 +1,55 @@
+//! Records the key events read from the input devices in the input format of
+//! `kanata_simulated_input`, so that a typing session can be replayed against a configuration.
+
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use crate::oskbd::{KeyEvent, KeyValue};
+use kanata_parser::keys::oscode_to_str;
+
+pub(crate) struct SimRecorder {
+    file: File,
+    last_event: Option<instant::Instant>,
+}
+
+impl SimRecorder {
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| anyhow!("failed to create recording file {path:?}: {e}"))?;
+        log::info!("recording input events to {path:?}");
+        Ok(Self {
+            file,
+            last_event: None,
+        })
+    }
+
+    /// Write the event, preceded by the time since the previous event. Each event is written on
+    /// its own line and written immediately so that the recording is complete when kanata is
+    /// stopped.
+    ///
+    /// Returns an error for keys without a name, which the simulator cannot replay.
+    pub(crate) fn record(&mut self, event: &KeyEvent) -> Result<()> {
+        let name = oscode_to_str(event.code)
+            .ok_or_else(|| anyhow!("cannot record key without a name: {}", event.code))?;
+        let mut line = String::new();
+        let now = instant::Instant::now();
+        if let Some(last_event) = self.last_event {
+            let ms = now.duration_since(last_event).as_millis();
+            if ms > 0 {
+                line += &format!("t:{ms} ");
+            }
+        }
+        match event.value {
+            KeyValue::Press => line += &format!("d:{name}"),
+            KeyValue::Release => line += &format!("u:{name}"),
+            KeyValue::Repeat => line += &format!("r:{name}"),
+            // Scroll events are handled as a press followed by a release.
+            KeyValue::Tap => line += &format!("d:{name} u:{name}"),
+            KeyValue::WakeUp => return Ok(()),
+        }
+        self.last_event = Some(now);
+        writeln!(self.file, "{line}").map_err(|e| anyhow!("failed to record event: {e}"))
+    }
+}
@@ -22,6 +22,9 @@ pub struct ValidatedArgs {
     pub tcp_server_address: Option<SocketAddrWrapper>,
     #[cfg(target_os = "linux")]
     pub symlink_path: Option<String>,
+    /// File to record the input events to, in the simulator input format.
+    #[cfg(target_os = "linux")]
+    pub record_path: Option<PathBuf>,
     pub nodelay: bool,
 }
 
@@ -58,6 +58,13 @@ kanata.kbd in the current working directory and
     #[arg(short, long, verbatim_doc_comment)]
     symlink_path: Option<String>,
 
+    /// Record the key events read from the input devices to a file in the
+    /// input format of kanata_simulated_input, so that typing can be replayed
+    /// against a configuration.
+    #[cfg(target_os = "linux")]
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    record: Option<PathBuf>,
+
     /// List the keyboards available for grabbing and exit.
     #[cfg(target_os = "macos")]
     #[arg(short, long)]
@@ -185,6 +192,8 @@ mod cli {
             tcp_server_address: args.tcp_server_address,
             #[cfg(target_os = "linux")]
             symlink_path: args.symlink_path,
+            #[cfg(target_os = "linux")]
+            record_path: args.record,
             nodelay: args.nodelay,
         })
     }
@@ -206,17 +215,26 @@ mod cli {
 
         let (tx, rx) = std::sync::mpsc::sync_channel(100);
 
-        let (server, ntx, nrx) = if let Some(address) = {
+        // With systemd socket activation, the TCP server uses the socket passed in by systemd
+        // instead of binding to the address given on the command line.
+        #[cfg(all(feature = "tcp_server", target_os = "linux"))]
+        let activated_server = TcpServer::systemd_activated_listener()
+            .map(|listener| TcpServer::from_listener(listener, tx.clone()))
+            .transpose()?;
+        #[cfg(not(all(feature = "tcp_server", target_os = "linux")))]
+        let activated_server = None::<TcpServer>;
+
+        let (server, ntx, nrx) = if let Some(mut server) = activated_server.or_else(|| {
             #[cfg(feature = "tcp_server")]
             {
                 args.tcp_server_address
+                    .map(|address| TcpServer::new(address.into_inner(), tx.clone()))
             }
             #[cfg(not(feature = "tcp_server"))]
             {
-                None::<SocketAddrWrapper>
+                None
             }
-        } {
-            let mut server = TcpServer::new(address.into_inner(), tx.clone());
+        }) {
             server.start(kanata_arc.clone());
             let (ntx, nrx) = std::sync::mpsc::sync_channel(100);
             (Some(server), Some(ntx), Some(nrx))
@@ -231,8 +249,9 @@ mod cli {
             Kanata::start_notification_loop(nrx, server.connections);
         }
 
+        // Keep NOTIFY_SOCKET set because the processing loop sends watchdog keepalives.
         #[cfg(target_os = "linux")]
-        sd_notify::notify(true, &[sd_notify::NotifyState::Ready])?;
+        sd_notify::notify(false, &[sd_notify::NotifyState::Ready])?;
 
         Kanata::event_loop(kanata_arc, tx)
     }
@@ -3,7 +3,10 @@
 #![cfg_attr(feature = "simulated_output", allow(dead_code, unused_imports))]
 
 pub use evdev::BusType;
-use evdev::{uinput, Device, EventType, InputEvent, PropType, RelativeAxisType};
+use evdev::{
+    uinput, AbsInfo, AbsoluteAxisType, Device, EventType, InputEvent, PropType, RelativeAxisType,
+    UinputAbsSetup,
+};
 use inotify::{Inotify, WatchMask};
 use mio::{unix::SourceFd, Events, Interest, Poll, Token};
 use nix::ioctl_read_buf;
@@ -19,17 +22,28 @@ use std::io;
 use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 
 use super::*;
 use crate::{kanata::CalculatedMouseMove, oskbd::KeyEvent};
+use kanata_parser::cfg::find_device_cfg;
+use kanata_parser::cfg::CfgLinuxOptions;
+use kanata_parser::cfg::DeviceCfg;
 use kanata_parser::cfg::DeviceDetectMode;
+use kanata_parser::cfg::DeviceInfo;
+use kanata_parser::cfg::DeviceMatcher;
+use kanata_parser::cfg::ScreenExtents;
+use kanata_parser::cfg::UnicodeOutput;
 use kanata_parser::cfg::UnicodeTermination;
 use kanata_parser::custom_action::*;
 use kanata_parser::keys::*;
 
 pub struct KbdIn {
-    devices: HashMap<Token, (Device, String)>,
+    /// Registered devices with their path and the index of their `defdevice` section.
+    devices: HashMap<Token, (Device, String, Option<usize>)>,
+    /// The `defdevice` sections that registered devices are matched against.
+    device_cfgs: Vec<DeviceCfg>,
     /// Some(_) if devices are explicitly listed, otherwise None.
     missing_device_paths: Option<Vec<String>>,
     poll: Poll,
@@ -39,7 +53,16 @@ pub struct KbdIn {
     _inotify: Inotify,
     include_names: Option<Vec<String>>,
     exclude_names: Option<Vec<String>>,
+    include_matchers: Vec<DeviceMatcher>,
+    exclude_matchers: Vec<DeviceMatcher>,
     device_detect_mode: DeviceDetectMode,
+    /// Device changes that have not yet been taken by the event loop.
+    device_changes: Vec<DeviceChange>,
+    /// Whether non-key events are forwarded to mirror devices.
+    passthru_non_key_events: bool,
+    /// Mirror output devices of the registered devices that have non-key events, created when
+    /// the device is registered.
+    mirrors: HashMap<Token, Mirror>,
 }
 
 const INOTIFY_TOKEN_VALUE: usize = 0;
@@ -53,6 +76,8 @@ impl KbdIn {
         continue_if_no_devices: bool,
         include_names: Option<Vec<String>>,
         exclude_names: Option<Vec<String>>,
+        include_matchers: Vec<DeviceMatcher>,
+        exclude_matchers: Vec<DeviceMatcher>,
         device_detect_mode: DeviceDetectMode,
     ) -> Result<Self, io::Error> {
         let poll = Poll::new()?;
@@ -68,6 +93,8 @@ impl KbdIn {
             discover_devices(
                 include_names.as_deref(),
                 exclude_names.as_deref(),
+                &include_matchers,
+                &exclude_matchers,
                 device_detect_mode,
             )
         };
@@ -97,15 +124,20 @@ impl KbdIn {
             _inotify,
             events: Events::with_capacity(32),
             devices: HashMap::default(),
+            device_cfgs: vec![],
             token_counter: INOTIFY_TOKEN_VALUE + 1,
             include_names,
             exclude_names,
+            include_matchers,
+            exclude_matchers,
             device_detect_mode,
+            device_changes: vec![],
+            passthru_non_key_events: false,
+            mirrors: HashMap::default(),
         };
 
         for (device, dev_path) in devices.into_iter() {
-            if let Err(e) = kbdin.register_device(device, dev_path.clone()) {
-                log::warn!("found device {dev_path} but could not register it {e:?}");
+            if !kbdin.try_register_device(device, dev_path.clone()) {
                 if let Some(ref mut missing) = kbdin.missing_device_paths {
                     missing.push(dev_path);
                 }
@@ -115,6 +147,35 @@ impl KbdIn {
         Ok(kbdin)
     }
 
+    /// Register the device and record the device changes. Returns true if the device was
+    /// registered.
+    fn try_register_device(&mut self, dev: Device, path: String) -> bool {
+        self.device_changes
+            .push(device_change(DeviceChangeKind::Added, &dev, &path));
+        let grabbed = device_change(DeviceChangeKind::Grabbed, &dev, &path);
+        match self.register_device(dev, path.clone()) {
+            Ok(()) => {
+                self.device_changes.push(grabbed);
+                true
+            }
+            Err(e) => {
+                log::warn!("found device {path} but could not register it {e:?}");
+                self.device_changes.push(DeviceChange {
+                    kind: DeviceChangeKind::GrabFailed {
+                        error: e.to_string(),
+                    },
+                    ..grabbed
+                });
+                false
+            }
+        }
+    }
+
+    /// Returns the device changes since the previous call.
+    pub fn take_device_changes(&mut self) -> Vec<DeviceChange> {
+        std::mem::take(&mut self.device_changes)
+    }
+
     fn register_device(&mut self, mut dev: Device, path: String) -> Result<(), io::Error> {
         log::info!("registering {path}: {:?}", dev.name().unwrap_or(""));
         wait_for_all_keys_unpressed(&dev)?;
@@ -130,11 +191,101 @@ impl KbdIn {
         self.poll
             .registry()
             .register(&mut SourceFd(&fd), tok, Interest::READABLE)?;
-        self.devices.insert(tok, (dev, path));
+        let cfg_idx = find_device_cfg(&self.device_cfgs, &device_info(&dev));
+        if self.passthru_non_key_events {
+            self.create_mirror(tok, &dev);
+        }
+        self.devices.insert(tok, (dev, path, cfg_idx));
         Ok(())
     }
 
-    pub fn read(&mut self) -> Result<Vec<InputEvent>, io::Error> {
+    /// Create the mirror device of an input device that has non-key events. Without a mirror
+    /// device, the events of the input device are processed as if passthru were disabled.
+    fn create_mirror(&mut self, tok: Token, dev: &Device) {
+        if !has_mirrored_events(dev) {
+            return;
+        }
+        match Mirror::new(dev) {
+            Ok(mirror) => {
+                self.mirrors.insert(tok, mirror);
+            }
+            Err(e) => log::error!(
+                "could not create mirror device for {:?}: {e}",
+                dev.name().unwrap_or("")
+            ),
+        }
+    }
+
+    /// Set the `defdevice` sections and match the registered devices against them again.
+    /// Devices registered later are matched when they are registered.
+    pub fn set_device_cfgs(&mut self, device_cfgs: Vec<DeviceCfg>) {
+        self.device_cfgs = device_cfgs;
+        for (device, _, cfg_idx) in self.devices.values_mut() {
+            *cfg_idx = find_device_cfg(&self.device_cfgs, &device_info(device));
+        }
+    }
+
+    /// Returns the index and the `defdevice` section that matches a registered device.
+    pub fn device_cfg(&self, id: usize) -> Option<(usize, &DeviceCfg)> {
+        let (_, _, cfg_idx) = self.devices.get(&Token(id))?;
+        cfg_idx.map(|i| (i, &self.device_cfgs[i]))
+    }
+
+    /// Enable forwarding of absolute axis, misc and switch events, together with the joystick,
+    /// gamepad and digitizer buttons that are not mapped, to a mirror device for each input
+    /// device. Without this, these events are written to the kanata output device, which does
+    /// not support them, so they are lost.
+    ///
+    /// Mirror devices are created for the registered devices when enabled and removed when
+    /// disabled.
+    pub fn set_passthru_non_key_events(&mut self, enabled: bool) {
+        if self.passthru_non_key_events == enabled {
+            return;
+        }
+        self.passthru_non_key_events = enabled;
+        if !enabled {
+            self.mirrors.clear();
+            return;
+        }
+        let devices = std::mem::take(&mut self.devices);
+        for (tok, (dev, _, _)) in devices.iter() {
+            self.create_mirror(*tok, dev);
+        }
+        self.devices = devices;
+    }
+
+    /// Forward the event to the mirror device of the input device if it is a non-key event.
+    /// Returns true if the event was handled and should not be processed further.
+    ///
+    /// Synchronization events write the buffered mirror events but are not consumed because the
+    /// kanata output device needs them too.
+    pub fn passthru_non_key_event(
+        &mut self,
+        event: InputEvent,
+        id: usize,
+        is_mapped: impl Fn(OsCode) -> bool,
+    ) -> Result<bool, io::Error> {
+        let Some(mirror) = self.mirrors.get_mut(&Token(id)) else {
+            return Ok(false);
+        };
+        match event.event_type() {
+            EventType::SYNCHRONIZATION => {
+                mirror.flush()?;
+                return Ok(false);
+            }
+            EventType::ABSOLUTE | EventType::MISC | EventType::SWITCH => {}
+            EventType::KEY
+                if is_mirrored_btn(event.code())
+                    && !OsCode::from_u16(event.code()).is_some_and(is_mapped) => {}
+            _ => return Ok(false),
+        }
+        mirror.buf.push(event);
+        Ok(true)
+    }
+
+    /// Read events from the registered devices. Each event is paired with the id of the device
+    /// that it was read from.
+    pub fn read(&mut self) -> Result<Vec<(InputEvent, usize)>, io::Error> {
         let mut input_events = vec![];
         loop {
             log::trace!("polling");
@@ -146,10 +297,11 @@ impl KbdIn {
 
             let mut do_rediscover = false;
             for event in &self.events {
-                if let Some((device, _)) = self.devices.get_mut(&event.token()) {
+                if let Some((device, _, _)) = self.devices.get_mut(&event.token()) {
+                    let id = event.token().0;
                     if let Err(e) = device
                         .fetch_events()
-                        .map(|evs| evs.into_iter().for_each(|ev| input_events.push(ev)))
+                        .map(|evs| evs.into_iter().for_each(|ev| input_events.push((ev, id))))
                     {
                         // Currently the kind() is uncategorized... not helpful, need to match
                         // on os error (19)
@@ -158,8 +310,15 @@ impl KbdIn {
                                 self.poll
                                     .registry()
                                     .deregister(&mut SourceFd(&device.as_raw_fd()))?;
-                                if let Some((_, path)) = self.devices.remove(&event.token()) {
+                                self.mirrors.remove(&event.token());
+                                if let Some((device, path, _)) = self.devices.remove(&event.token())
+                                {
                                     log::warn!("removing kbd device: {path}");
+                                    self.device_changes.push(device_change(
+                                        DeviceChangeKind::Removed,
+                                        &device,
+                                        &path,
+                                    ));
                                     if let Some(ref mut missing) = self.missing_device_paths {
                                         missing.push(path);
                                     }
@@ -181,7 +340,7 @@ impl KbdIn {
                 log::info!("watch found file changes, looking for new devices");
                 self.rediscover_devices()?;
             }
-            if !input_events.is_empty() {
+            if !input_events.is_empty() || !self.device_changes.is_empty() {
                 return Ok(input_events);
             }
         }
@@ -210,9 +369,7 @@ impl KbdIn {
                 })
                 .collect::<Vec<(_, _)>>();
             for (device, dev_path) in discovered_devices {
-                if let Err(e) = self.register_device(device, dev_path.clone()) {
-                    log::warn!("found device {dev_path} but could not register it {e:?}");
-                } else {
+                if self.try_register_device(device, dev_path.clone()) {
                     paths_registered.push(dev_path);
                 }
             }
@@ -224,23 +381,115 @@ impl KbdIn {
             std::thread::sleep(std::time::Duration::from_millis(
                 WAIT_DEVICE_MS.load(Ordering::SeqCst),
             ));
-            discover_devices(
+            let discovered_devices = discover_devices(
                 self.include_names.as_deref(),
                 self.exclude_names.as_deref(),
+                &self.include_matchers,
+                &self.exclude_matchers,
                 self.device_detect_mode,
-            )
-            .into_iter()
-            .try_for_each(|(dev, path)| {
+            );
+            for (dev, path) in discovered_devices {
                 if !self
                     .devices
                     .values()
-                    .any(|(_, registered_path)| &path == registered_path)
+                    .any(|(_, registered_path, _)| &path == registered_path)
                 {
-                    self.register_device(dev, path)
-                } else {
-                    Ok(())
+                    self.try_register_device(dev, path);
                 }
-            })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prefix of the names of mirror devices, followed by the name of the input device.
+const MIRROR_NAME_PREFIX: &str = "kanata mirror: ";
+
+/// Joystick, gamepad, digitizer and wheel buttons (BTN_JOYSTICK through BTN_GEAR_UP) belong
+/// with the absolute axes on the mirror device, e.g. BTN_TOUCH and BTN_TOOL_PEN of a drawing
+/// tablet.
+fn is_mirrored_btn(code: u16) -> bool {
+    (0x120..=0x151).contains(&code)
+}
+
+/// Whether the device has events that are forwarded to a mirror device.
+fn has_mirrored_events(device: &Device) -> bool {
+    device.supported_absolute_axes().is_some()
+        || device.supported_switches().is_some()
+        || device.misc_properties().is_some()
+        || device
+            .supported_keys()
+            .is_some_and(|keys| keys.iter().any(|k| is_mirrored_btn(k.0)))
+}
+
+/// Output device that forwards the non-key events of an input device unchanged. It has the same
+/// absolute axes, misc events, switches and properties as the input device.
+struct Mirror {
+    device: uinput::VirtualDevice,
+    buf: Vec<InputEvent>,
+}
+
+impl Mirror {
+    fn new(source: &Device) -> Result<Self, io::Error> {
+        let source_name = source.name().unwrap_or("unknown device name");
+        // uinput device names are limited to 80 bytes including the nul terminator.
+        let name: String = MIRROR_NAME_PREFIX
+            .chars()
+            .chain(source_name.chars())
+            .scan(0, |len, c| {
+                *len += c.len_utf8();
+                (*len < 80).then_some(c)
+            })
+            .collect();
+        let mut builder = uinput::VirtualDeviceBuilder::new()?
+            .name(&name)
+            .input_id(source.input_id())
+            .with_properties(source.properties())?;
+        if let Some(keys) = source.supported_keys() {
+            let keys = evdev::AttributeSet::from_iter(keys.iter().filter(|k| is_mirrored_btn(k.0)));
+            if keys.iter().next().is_some() {
+                builder = builder.with_keys(&keys)?;
+            }
+        }
+        if let Some(axes) = source.supported_absolute_axes() {
+            let abs_state = source.get_abs_state()?;
+            for axis in axes.iter() {
+                let info = &abs_state[usize::from(axis.0)];
+                builder = builder.with_absolute_axis(&UinputAbsSetup::new(
+                    axis,
+                    AbsInfo::new(
+                        info.value,
+                        info.minimum,
+                        info.maximum,
+                        info.fuzz,
+                        info.flat,
+                        info.resolution,
+                    ),
+                ))?;
+            }
+        }
+        if let Some(switches) = source.supported_switches() {
+            builder = builder.with_switches(switches)?;
+        }
+        if let Some(misc) = source.misc_properties() {
+            builder = builder.with_msc(misc)?;
+        }
+        let mut device = builder.build()?;
+        let devnode = device
+            .enumerate_dev_nodes_blocking()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "devnode is not found"))??;
+        log::info!("Created mirror device {devnode:#?} for {source_name}");
+        Ok(Self {
+            device,
+            buf: vec![],
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        if !self.buf.is_empty() {
+            self.device.emit(&self.buf)?;
+            self.buf.clear();
         }
         Ok(())
     }
@@ -254,23 +503,40 @@ enum DeviceType {
     Other,
 }
 
-pub fn is_input_device(device: &Device, detect_mode: DeviceDetectMode) -> bool {
+fn device_type(device: &Device) -> DeviceType {
     use evdev::Key;
-    if device.name() == Some("kanata") {
-        return false;
-    }
     let is_keyboard = device
         .supported_keys()
-        .map_or(false, |keys| keys.contains(Key::KEY_ENTER));
+        .is_some_and(|keys| keys.contains(Key::KEY_ENTER));
     let is_mouse = device
         .supported_relative_axes()
-        .map_or(false, |axes| axes.contains(RelativeAxisType::REL_X));
-    let device_type = match (is_keyboard, is_mouse) {
+        .is_some_and(|axes| axes.contains(RelativeAxisType::REL_X));
+    match (is_keyboard, is_mouse) {
         (true, true) => DeviceType::KeyboardMouse,
         (true, false) => DeviceType::Keyboard,
         (false, true) => DeviceType::Mouse,
         (false, false) => DeviceType::Other,
-    };
+    }
+}
+
+/// Names of the uinput devices created by kanata. These are never used as input devices.
+static OUTPUT_DEVICE_NAMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn is_output_device_name(name: &str) -> bool {
+    name == "kanata"
+        || name.starts_with(MIRROR_NAME_PREFIX)
+        || OUTPUT_DEVICE_NAMES
+            .lock()
+            .expect("output device names lock")
+            .iter()
+            .any(|n| n == name)
+}
+
+pub fn is_input_device(device: &Device, detect_mode: DeviceDetectMode) -> bool {
+    if device.name().is_some_and(is_output_device_name) {
+        return false;
+    }
+    let device_type = device_type(device);
     let device_name = device.name().unwrap_or("unknown device name");
     match (detect_mode, device_type) {
         (_, DeviceType::Other) => {
@@ -305,15 +571,41 @@ pub fn is_input_device(device: &Device, detect_mode: DeviceDetectMode) -> bool {
     }
 }
 
+fn device_change(kind: DeviceChangeKind, device: &Device, path: &str) -> DeviceChange {
+    let info = device_info(device);
+    DeviceChange {
+        kind,
+        path: path.to_owned(),
+        name: info.name.to_owned(),
+        phys: info.phys.to_owned(),
+        uniq: info.uniq.to_owned(),
+        bus: info.bus,
+        vendor: info.vendor,
+        product: info.product,
+    }
+}
+
+fn device_info(device: &Device) -> DeviceInfo<'_> {
+    let input_id = device.input_id();
+    DeviceInfo {
+        name: device.name().unwrap_or(""),
+        phys: device.physical_path().unwrap_or(""),
+        uniq: device.unique_name().unwrap_or(""),
+        bus: input_id.bus_type().0,
+        vendor: input_id.vendor(),
+        product: input_id.product(),
+    }
+}
+
 impl TryFrom<InputEvent> for KeyEvent {
     type Error = ();
     fn try_from(item: InputEvent) -> Result<Self, Self::Error> {
         use OsCode::*;
         match item.kind() {
-            evdev::InputEventKind::Key(k) => Ok(Self {
-                code: OsCode::from_u16(k.0).ok_or(())?,
-                value: KeyValue::from(item.value()),
-            }),
+            evdev::InputEventKind::Key(k) => Ok(Self::new(
+                OsCode::from_u16(k.0).ok_or(())?,
+                KeyValue::from(item.value()),
+            )),
             evdev::InputEventKind::RelAxis(axis_type) => {
                 let dist = item.value();
                 let code: OsCode = match axis_type {
@@ -333,10 +625,7 @@ impl TryFrom<InputEvent> for KeyEvent {
                     }
                     _ => return Err(()),
                 };
-                Ok(KeyEvent {
-                    code,
-                    value: KeyValue::Tap,
-                })
+                Ok(KeyEvent::new(code, KeyValue::Tap))
             }
             _ => Err(()),
         }
@@ -349,16 +638,193 @@ impl From<KeyEvent> for InputEvent {
     }
 }
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 
 #[cfg(all(not(feature = "simulated_output"), not(feature = "passthru_ahk")))]
 pub struct KbdOut {
     device: uinput::VirtualDevice,
+    /// Separate device for mouse buttons and relative axes, if configured. Otherwise all events
+    /// are written to `device`.
+    pointer: Option<uinput::VirtualDevice>,
+    /// Virtual tablet used for absolute mouse positioning by setmouse, created by the first
+    /// setmouse action.
+    tablet: Option<AbsMouse>,
+    /// Bus type and screen extents of the tablet.
+    tablet_cfg: (BusType, Option<ScreenExtents>),
     accumulated_scroll: u16,
     accumulated_hscroll: u16,
     raw_buf: Vec<InputEvent>,
     pub unicode_termination: Cell<UnicodeTermination>,
     pub unicode_u_code: Cell<OsCode>,
+    unicode_output: RefCell<UnicodeOutputCfg>,
+    /// Unicode keymap output, set up when that output is configured.
+    unicode_keymap: RefCell<Option<UnicodeKeymap>>,
+}
+
+/// Configuration for unicode output methods other than Ctrl+Shift+U.
+struct UnicodeOutputCfg {
+    output: UnicodeOutput,
+    compose_key: OsCode,
+    compose_sequences: HashMap<char, Vec<Vec<OsCode>>>,
+}
+
+/// Time for a key press to be handled before its keycode is remapped for the next character.
+const UNICODE_KEYMAP_DELAY: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Time without unicode keymap output after which the remapped keycode is restored.
+const UNICODE_KEYMAP_RESTORE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// State of unicode keymap output, shared with the thread that restores the remapped keycode.
+struct UnicodeKeymapState {
+    /// Whether the keycode is mapped to a character.
+    remapped: bool,
+    last_use: std::time::Instant,
+    /// Set when the output is stopped, e.g. by live reload.
+    stop: bool,
+}
+
+/// Unicode keymap output. Characters are typed on the processing thread, in order with the other
+/// output. The keycode is restored by a separate thread once no character has been typed for
+/// [`UNICODE_KEYMAP_RESTORE_DELAY`], so that consecutive characters only run xmodmap once each.
+struct UnicodeKeymap {
+    /// The configured key to remap, if any.
+    key: Option<OsCode>,
+    /// The evdev code of the remapped key.
+    code: u16,
+    state: Arc<(Mutex<UnicodeKeymapState>, Condvar)>,
+}
+
+impl UnicodeKeymap {
+    fn new(key: Option<OsCode>) -> Result<Self, io::Error> {
+        let is_wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+            || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+        if is_wayland || std::env::var_os("DISPLAY").is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unicode keymap output requires an X11 session; use compose output on Wayland",
+            ));
+        }
+        let keymap = run_xmodmap(&["-pke"])?;
+        let code = match key {
+            Some(key) => u16::from(key),
+            None => {
+                let code = find_unused_keycode(&keymap).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no unused keycode in the X11 keymap; configure linux-unicode-keymap-key",
+                    )
+                })?;
+                log::info!("using unused X11 keycode {} for unicode output", code + 8);
+                code
+            }
+        };
+        // X11 keycodes are the evdev codes offset by 8.
+        let restore = format!(
+            "keycode {} = {}",
+            code + 8,
+            keycode_keysyms(&keymap, code + 8).unwrap_or_default()
+        );
+        let state = Arc::new((
+            Mutex::new(UnicodeKeymapState {
+                remapped: false,
+                last_use: std::time::Instant::now(),
+                stop: false,
+            }),
+            Condvar::new(),
+        ));
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name("unicode keymap restore".into())
+            .spawn(move || {
+                let (lock, cvar) = &*thread_state;
+                let mut state = lock.lock().expect("unicode keymap lock");
+                loop {
+                    let idle = state.last_use.elapsed();
+                    if state.remapped && (state.stop || idle >= UNICODE_KEYMAP_RESTORE_DELAY) {
+                        if let Err(e) = run_xmodmap(&["-e", &restore]) {
+                            log::error!("could not restore X11 keycode {}: {e}", code + 8);
+                        }
+                        state.remapped = false;
+                    }
+                    if state.stop {
+                        return;
+                    }
+                    state = if state.remapped {
+                        let timeout = UNICODE_KEYMAP_RESTORE_DELAY.saturating_sub(idle);
+                        cvar.wait_timeout(state, timeout)
+                            .expect("unicode keymap lock")
+                            .0
+                    } else {
+                        cvar.wait(state).expect("unicode keymap lock")
+                    };
+                }
+            })?;
+        Ok(Self { key, code, state })
+    }
+}
+
+impl Drop for UnicodeKeymap {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.state;
+        lock.lock().expect("unicode keymap lock").stop = true;
+        cvar.notify_one();
+    }
+}
+
+/// Run xmodmap and return its output.
+fn run_xmodmap(args: &[&str]) -> Result<String, io::Error> {
+    let output = std::process::Command::new("xmodmap")
+        .args(args)
+        .output()
+        .map_err(|e| {
+            log::error!("failed to run xmodmap: {e:?}");
+            e
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "xmodmap failed: {}",
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns the X11 keycodes and keysyms in the output of `xmodmap -pke`, where lines look like
+/// `keycode  38 = a A a A`.
+fn keymap_entries(keymap: &str) -> impl Iterator<Item = (u16, &str)> {
+    keymap.lines().filter_map(|line| {
+        let (keycode, keysyms) = line.strip_prefix("keycode")?.split_once('=')?;
+        Some((keycode.trim().parse::<u16>().ok()?, keysyms.trim()))
+    })
+}
+
+/// Returns the keysyms of the X11 keycode in the output of `xmodmap -pke`.
+fn keycode_keysyms(keymap: &str, keycode: u16) -> Option<&str> {
+    keymap_entries(keymap).find_map(|(code, keysyms)| (code == keycode).then_some(keysyms))
+}
+
+/// Returns the evdev code of the highest keycode without keysyms in the output of
+/// `xmodmap -pke`.
+fn find_unused_keycode(keymap: &str) -> Option<u16> {
+    keymap_entries(keymap)
+        .filter_map(|(keycode, keysyms)| keysyms.is_empty().then_some(keycode))
+        .filter(|keycode| (9..=255).contains(keycode))
+        .max()
+        .map(|keycode| keycode - 8)
+}
+
+/// Mouse buttons are BTN_LEFT (0x110) through BTN_TASK (0x117).
+fn is_mouse_btn(code: u16) -> bool {
+    (0x110..=0x117).contains(&code)
+}
+
+fn is_pointer_event(event: &InputEvent) -> bool {
+    match event.event_type() {
+        EventType::RELATIVE => true,
+        EventType::KEY => is_mouse_btn(event.code()),
+        _ => false,
+    }
 }
 
 #[cfg(all(not(feature = "simulated_output"), not(feature = "passthru_ahk")))]
@@ -367,12 +833,15 @@ impl KbdOut {
         symlink_path: &Option<String>,
         trackpoint: bool,
         bus_type: BusType,
+        setmouse_extents: Option<ScreenExtents>,
+        name: &str,
+        pointer: Option<(&str, BusType)>,
     ) -> Result<Self, io::Error> {
         // Support pretty much every feature of a Keyboard or a Mouse in a VirtualDevice so that no event from the original input devices gets lost
-        // TODO investigate the rare possibility that a device is e.g. a Joystick and a Keyboard or a Mouse at the same time, which could lead to lost events
+        // Events of other device types, e.g. a Joystick or a drawing tablet that is also a Keyboard, are lost unless forwarded to a mirror device with linux-passthru-non-key-events
 
         // For some reason 0..0x300 (max value for a key) doesn't work, the closest that I've got to work is 560
-        let keys = evdev::AttributeSet::from_iter((0..560).map(evdev::Key));
+        let all_keys = (0..560).map(evdev::Key);
         let relative_axes = evdev::AttributeSet::from_iter([
             RelativeAxisType::REL_WHEEL,
             RelativeAxisType::REL_HWHEEL,
@@ -388,19 +857,69 @@ impl KbdOut {
             RelativeAxisType::REL_HWHEEL_HI_RES,
         ]);
 
+        {
+            let mut names = OUTPUT_DEVICE_NAMES
+                .lock()
+                .expect("output device names lock");
+            names.clear();
+            names.push(name.to_owned());
+            names.push(TABLET_NAME.to_owned());
+            if let Some((pointer_name, _)) = pointer {
+                names.push(pointer_name.to_owned());
+            }
+        }
+
         let device = uinput::VirtualDeviceBuilder::new()?
-            .name("kanata")
+            .name(name)
             // libinput's "disable while typing" feature don't work when bus_type
             // is set to BUS_USB, but appears to work when it's set to BUS_I8042.
-            .input_id(evdev::InputId::new(bus_type, 1, 1, 1))
-            .with_keys(&keys)?
-            .with_relative_axes(&relative_axes)?;
-        let device = if trackpoint {
-            device.with_properties(&evdev::AttributeSet::from_iter([PropType::POINTING_STICK]))?
-        } else {
-            device
+            .input_id(evdev::InputId::new(bus_type, 1, 1, 1));
+        let (device, pointer) = match pointer {
+            None => {
+                let keys = evdev::AttributeSet::from_iter(all_keys);
+                let device = device
+                    .with_keys(&keys)?
+                    .with_relative_axes(&relative_axes)?;
+                let device = if trackpoint {
+                    device.with_properties(&evdev::AttributeSet::from_iter([
+                        PropType::POINTING_STICK,
+                    ]))?
+                } else {
+                    device
+                };
+                (device.build()?, None)
+            }
+            Some((pointer_name, pointer_bus_type)) => {
+                let keys =
+                    evdev::AttributeSet::from_iter(all_keys.clone().filter(|k| !is_mouse_btn(k.0)));
+                let device = device.with_keys(&keys)?.build()?;
+                let buttons =
+                    evdev::AttributeSet::from_iter(all_keys.filter(|k| is_mouse_btn(k.0)));
+                let pointer = uinput::VirtualDeviceBuilder::new()?
+                    .name(pointer_name)
+                    .input_id(evdev::InputId::new(pointer_bus_type, 1, 3, 1))
+                    .with_keys(&buttons)?
+                    .with_relative_axes(&relative_axes)?;
+                let pointer = if trackpoint {
+                    pointer.with_properties(&evdev::AttributeSet::from_iter([
+                        PropType::POINTING_STICK,
+                    ]))?
+                } else {
+                    pointer
+                };
+                let mut pointer = pointer.build()?;
+                let devnode =
+                    pointer
+                        .enumerate_dev_nodes_blocking()?
+                        .next()
+                        .ok_or_else(|| {
+                            io::Error::new(io::ErrorKind::NotFound, "devnode is not found")
+                        })??;
+                log::info!("Created pointer device {:#?}", devnode);
+                (device, Some(pointer))
+            }
         };
-        let mut device = device.build()?;
+        let mut device = device;
         let devnode = device
             .enumerate_dev_nodes_blocking()?
             .next() // Expect only one. Using fold or calling next again blocks indefinitely
@@ -417,6 +936,9 @@ impl KbdOut {
 
         Ok(KbdOut {
             device,
+            pointer,
+            tablet: None,
+            tablet_cfg: (bus_type, setmouse_extents),
             accumulated_scroll: 0,
             accumulated_hscroll: 0,
             raw_buf: vec![],
@@ -426,9 +948,33 @@ impl KbdOut {
 
             // historically was the only option, so make KEY_U the default
             unicode_u_code: Cell::new(OsCode::KEY_U),
+
+            unicode_output: RefCell::new(UnicodeOutputCfg {
+                output: UnicodeOutput::CtrlShiftU,
+                compose_key: OsCode::KEY_COMPOSE,
+                compose_sequences: HashMap::default(),
+            }),
+            unicode_keymap: RefCell::new(None),
         })
     }
 
+    /// Write events to the output devices. With a separate pointer device, mouse button and
+    /// relative axis events are written to it and all other events to the keyboard device.
+    fn emit(&mut self, events: &[InputEvent]) -> Result<(), io::Error> {
+        let Some(pointer) = self.pointer.as_mut() else {
+            return self.device.emit(events);
+        };
+        let (pointer_events, key_events): (Vec<_>, Vec<_>) =
+            events.iter().partition(|ev| is_pointer_event(ev));
+        if !key_events.is_empty() {
+            self.device.emit(&key_events)?;
+        }
+        if !pointer_events.is_empty() {
+            pointer.emit(&pointer_events)?;
+        }
+        Ok(())
+    }
+
     pub fn update_unicode_termination(&self, t: UnicodeTermination) {
         self.unicode_termination.replace(t);
     }
@@ -437,6 +983,26 @@ impl KbdOut {
         self.unicode_u_code.replace(u);
     }
 
+    /// Also starts unicode keymap output when it is configured, or stops it, which restores the
+    /// remapped keycode.
+    pub fn update_unicode_output(&self, opts: &CfgLinuxOptions) {
+        let mut keymap = self.unicode_keymap.borrow_mut();
+        if opts.linux_unicode_output != UnicodeOutput::Keymap {
+            *keymap = None;
+        } else if keymap.as_ref().map(|w| w.key) != Some(opts.linux_unicode_keymap_key) {
+            *keymap = None;
+            match UnicodeKeymap::new(opts.linux_unicode_keymap_key) {
+                Ok(unicode_keymap) => *keymap = Some(unicode_keymap),
+                Err(e) => log::error!("could not start unicode keymap output: {e}"),
+            }
+        }
+        self.unicode_output.replace(UnicodeOutputCfg {
+            output: opts.linux_unicode_output,
+            compose_key: opts.linux_unicode_compose_key,
+            compose_sequences: opts.linux_unicode_compose_sequences.clone(),
+        });
+    }
+
     pub fn write_raw(&mut self, event: InputEvent) -> Result<(), io::Error> {
         if event.event_type() == EventType::SYNCHRONIZATION {
             // Possible codes are:
@@ -449,8 +1015,8 @@ impl KbdOut {
             //     this correctly.
             //
             // With this knowledge, seems fine to not bother checking.
-            self.device.emit(&self.raw_buf)?;
-            self.raw_buf.clear();
+            let buf = std::mem::take(&mut self.raw_buf);
+            self.emit(&buf)?;
         } else {
             self.raw_buf.push(event);
         }
@@ -459,19 +1025,19 @@ impl KbdOut {
 
     pub fn write(&mut self, event: InputEvent) -> Result<(), io::Error> {
         if !self.raw_buf.is_empty() {
-            self.device.emit(&self.raw_buf)?;
-            self.raw_buf.clear();
+            let buf = std::mem::take(&mut self.raw_buf);
+            self.emit(&buf)?;
         }
-        self.device.emit(&[event])?;
+        self.emit(&[event])?;
         Ok(())
     }
 
     pub fn write_many(&mut self, events: &[InputEvent]) -> Result<(), io::Error> {
         if !self.raw_buf.is_empty() {
-            self.device.emit(&self.raw_buf)?;
-            self.raw_buf.clear();
+            let buf = std::mem::take(&mut self.raw_buf);
+            self.emit(&buf)?;
         }
-        self.device.emit(events)?;
+        self.emit(events)?;
         Ok(())
     }
 
@@ -479,13 +1045,13 @@ impl KbdOut {
         let key_ev = KeyEvent::new(key, value);
         let input_ev = key_ev.into();
         log::debug!("send to uinput: {:?}", input_ev);
-        self.device.emit(&[input_ev])?;
+        self.emit(&[input_ev])?;
         Ok(())
     }
 
     pub fn write_code(&mut self, code: u32, value: KeyValue) -> Result<(), io::Error> {
         let event = InputEvent::new(EventType::KEY, code as u16, value as i32);
-        self.device.emit(&[event])?;
+        self.emit(&[event])?;
         Ok(())
     }
 
@@ -497,9 +1063,18 @@ impl KbdOut {
         self.write_key(key, KeyValue::Release)
     }
 
-    /// Send using C-S-u + <unicode hex number> + spc
     pub fn send_unicode(&mut self, c: char) -> Result<(), io::Error> {
         log::debug!("sending unicode {c}");
+        let output = self.unicode_output.borrow().output;
+        match output {
+            UnicodeOutput::CtrlShiftU => self.send_unicode_ctrl_shift_u(c),
+            UnicodeOutput::Keymap => self.send_unicode_keymap(c),
+            UnicodeOutput::Compose => self.send_unicode_compose(c),
+        }
+    }
+
+    /// Send using C-S-u + <unicode hex number> + spc
+    fn send_unicode_ctrl_shift_u(&mut self, c: char) -> Result<(), io::Error> {
         let hex = format!("{:x}", c as u32);
         self.press_key(OsCode::KEY_LEFTCTRL)?;
         self.press_key(OsCode::KEY_LEFTSHIFT)?;
@@ -540,6 +1115,57 @@ impl KbdOut {
         Ok(())
     }
 
+    /// Send by mapping a spare keycode to the character in the X11 keymap using xmodmap, then
+    /// pressing that keycode. This is the same approach as xdotool. X11 only.
+    fn send_unicode_keymap(&mut self, c: char) -> Result<(), io::Error> {
+        let (code, state) = {
+            let keymap = self.unicode_keymap.borrow();
+            let keymap = keymap.as_ref().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "unicode keymap output could not be set up",
+                )
+            })?;
+            (keymap.code, keymap.state.clone())
+        };
+        let (lock, cvar) = &*state;
+        // Holding the lock keeps the keycode from being restored before the press is handled.
+        let mut state = lock.lock().expect("unicode keymap lock");
+        let keysym = format!("U{:04X}", u32::from(c));
+        run_xmodmap(&["-e", &format!("keycode {} = {keysym} {keysym}", code + 8)])?;
+        state.remapped = true;
+        self.write_code(code.into(), KeyValue::Press)?;
+        self.write_code(code.into(), KeyValue::Release)?;
+        thread::sleep(UNICODE_KEYMAP_DELAY);
+        state.last_use = std::time::Instant::now();
+        cvar.notify_one();
+        Ok(())
+    }
+
+    /// Send by pressing the compose key followed by the configured sequence. Characters without
+    /// a configured sequence are sent with Ctrl+Shift+U.
+    fn send_unicode_compose(&mut self, c: char) -> Result<(), io::Error> {
+        let (compose_key, steps) = {
+            let cfg = self.unicode_output.borrow();
+            (cfg.compose_key, cfg.compose_sequences.get(&c).cloned())
+        };
+        let Some(steps) = steps else {
+            log::warn!("no compose sequence for {c} in linux-unicode-compose-sequences");
+            return self.send_unicode_ctrl_shift_u(c);
+        };
+        self.press_key(compose_key)?;
+        self.release_key(compose_key)?;
+        for keys in steps {
+            for key in keys.iter().copied() {
+                self.press_key(key)?;
+            }
+            for key in keys.iter().rev().copied() {
+                self.release_key(key)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn click_btn(&mut self, btn: Btn) -> Result<(), io::Error> {
         self.press_key(btn.into())
     }
@@ -635,10 +1261,100 @@ impl KbdOut {
         self.write_many(&events)
     }
 
-    pub fn set_mouse(&mut self, _x: u16, _y: u16) -> Result<(), io::Error> {
-        log::warn!("setmouse does not work in Linux yet. Maybe try out warpd:\n\thttps://github.com/rvaiya/warpd");
+    pub fn set_mouse(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
+        log::debug!("setting mouse {x} {y}");
+        let tablet = match &mut self.tablet {
+            Some(tablet) => tablet,
+            None => {
+                let (bus_type, extents) = self.tablet_cfg;
+                let tablet = AbsMouse::new(bus_type, extents)?;
+                // Give the desktop time to open the new device so that the first position is
+                // not lost.
+                thread::sleep(TABLET_READY_DELAY);
+                self.tablet.insert(tablet)
+            }
+        };
+        tablet.set_position(x, y)
+    }
+}
+
+/// Name of the virtual tablet device used by setmouse.
+const TABLET_NAME: &str = "kanata-tablet";
+
+/// Time for a new tablet device to be opened by the desktop before it is used.
+#[cfg(all(not(feature = "simulated_output"), not(feature = "passthru_ahk")))]
+const TABLET_READY_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// A uinput device with absolute X and Y axes and mouse buttons. Such a device is treated as an
+/// absolute pointer, the same way as the tablet device of a virtual machine, where the axis
+/// range is mapped onto the whole screen.
+#[cfg(all(not(feature = "simulated_output"), not(feature = "passthru_ahk")))]
+struct AbsMouse {
+    device: uinput::VirtualDevice,
+    max_x: u16,
+    max_y: u16,
+    /// Last position written, if any.
+    position: Option<(u16, u16)>,
+}
+
+#[cfg(all(not(feature = "simulated_output"), not(feature = "passthru_ahk")))]
+impl AbsMouse {
+    fn new(bus_type: BusType, extents: Option<ScreenExtents>) -> Result<Self, io::Error> {
+        // Without configured screen extents, use the full u16 range so that setmouse values
+        // have the same meaning as on Windows. Otherwise setmouse values are in pixels.
+        let (max_x, max_y) = match extents {
+            Some(ScreenExtents { width, height }) => (width - 1, height - 1),
+            None => (u16::MAX, u16::MAX),
+        };
+        let axis =
+            |axis, max: u16| UinputAbsSetup::new(axis, AbsInfo::new(0, 0, i32::from(max), 0, 0, 0));
+        let buttons = evdev::AttributeSet::from_iter([
+            evdev::Key::BTN_LEFT,
+            evdev::Key::BTN_RIGHT,
+            evdev::Key::BTN_MIDDLE,
+        ]);
+        let mut device = uinput::VirtualDeviceBuilder::new()?
+            .name(TABLET_NAME)
+            .input_id(evdev::InputId::new(bus_type, 1, 2, 1))
+            .with_keys(&buttons)?
+            .with_absolute_axis(&axis(AbsoluteAxisType::ABS_X, max_x))?
+            .with_absolute_axis(&axis(AbsoluteAxisType::ABS_Y, max_y))?
+            .build()?;
+        let devnode = device
+            .enumerate_dev_nodes_blocking()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "devnode is not found"))??;
+        log::info!("Created device {:#?}", devnode);
+        Ok(Self {
+            device,
+            max_x,
+            max_y,
+            position: None,
+        })
+    }
+
+    fn set_position(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
+        let (x, y) = (x.min(self.max_x), y.min(self.max_y));
+        // The kernel drops absolute events that have the same value as the previous event, so the
+        // pointer would not move back to the same position after being moved by another device.
+        // Nudge the position first to make sure the final position is always reported.
+        if let Some((prev_x, prev_y)) = self.position {
+            if prev_x == x || prev_y == y {
+                let nudge = |v: u16, max: u16| if v < max { v + 1 } else { v.saturating_sub(1) };
+                self.emit_position(nudge(x, self.max_x), nudge(y, self.max_y))?;
+            }
+        }
+        self.emit_position(x, y)?;
+        self.position = Some((x, y));
         Ok(())
     }
+
+    fn emit_position(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
+        self.device.emit(&[
+            InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, i32::from(x)),
+            InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_Y.0, i32::from(y)),
+        ])
+    }
 }
 
 fn devices_from_input_paths(
@@ -662,6 +1378,8 @@ fn devices_from_input_paths(
 fn discover_devices(
     include_names: Option<&[String]>,
     exclude_names: Option<&[String]>,
+    include_matchers: &[DeviceMatcher],
+    exclude_matchers: &[DeviceMatcher],
     device_detect_mode: DeviceDetectMode,
 ) -> Vec<(Device, String)> {
     log::info!("looking for devices in /dev/input");
@@ -674,37 +1392,74 @@ fn discover_devices(
                     .to_owned(),
             )
         })
-        .filter(|pd| {
-            let is_input = is_input_device(&pd.0, device_detect_mode);
-            (match include_names {
-                None => is_input,
-                Some(include_names) => {
-                    let name = pd.0.name().unwrap_or("");
-                    if include_names.iter().any(|include| name == include) {
-                        log::info!("device [{}:{name}] is included", &pd.1);
-                        true
-                    } else {
-                        log::info!("device [{}:{name}] is ignored", &pd.1);
-                        false
-                    }
-                }
-            }) && (match exclude_names {
-                None => true,
-                Some(exclude_names) => {
-                    let name = pd.0.name().unwrap_or("");
-                    if exclude_names.iter().any(|exclude| name == exclude) {
-                        log::info!("device [{}:{name}] is excluded", &pd.1);
-                        false
-                    } else {
-                        true
-                    }
-                }
-            })
+        .filter(|(device, path)| {
+            let info = device_info(device);
+            let (use_device, reason) = select_device(
+                device,
+                &info,
+                include_names,
+                exclude_names,
+                include_matchers,
+                exclude_matchers,
+                device_detect_mode,
+            );
+            log::info!(
+                "device [{path}:{}] is {}: {reason}; phys {:?}, uniq {:?}, bus {:04x}, id {:04x}:{:04x}",
+                info.name,
+                if use_device { "included" } else { "ignored" },
+                info.phys,
+                info.uniq,
+                info.bus,
+                info.vendor,
+                info.product,
+            );
+            use_device
         })
         .collect();
     devices
 }
 
+/// Decides whether a discovered device should be used for input.
+/// Returns the decision along with the reason for it.
+fn select_device(
+    device: &Device,
+    info: &DeviceInfo,
+    include_names: Option<&[String]>,
+    exclude_names: Option<&[String]>,
+    include_matchers: &[DeviceMatcher],
+    exclude_matchers: &[DeviceMatcher],
+    device_detect_mode: DeviceDetectMode,
+) -> (bool, String) {
+    if is_output_device_name(info.name) {
+        return (false, "is a kanata output device".into());
+    }
+    if exclude_names.is_some_and(|names| names.iter().any(|name| name == info.name)) {
+        return (false, "name is in linux-dev-names-exclude".into());
+    }
+    if let Some(matcher) = exclude_matchers.iter().find(|m| m.matches(info)) {
+        return (false, format!("matches linux-dev-exclude-match {matcher}"));
+    }
+    if include_names.is_some() || !include_matchers.is_empty() {
+        if include_names.is_some_and(|names| names.iter().any(|name| name == info.name)) {
+            return (true, "name is in linux-dev-names-include".into());
+        }
+        if let Some(matcher) = include_matchers.iter().find(|m| m.matches(info)) {
+            return (true, format!("matches linux-dev-include-match {matcher}"));
+        }
+        return (
+            false,
+            "does not match linux-dev-names-include or linux-dev-include-match".into(),
+        );
+    }
+    (
+        is_input_device(device, device_detect_mode),
+        format!(
+            "autodetected as {:?} with linux-device-detect-mode {device_detect_mode}",
+            device_type(device)
+        ),
+    )
+}
+
 fn watch_devinput() -> Result<Inotify, io::Error> {
     let inotify = Inotify::init().expect("Failed to initialize inotify");
     inotify.watches().add("/dev/input", WatchMask::CREATE)?;
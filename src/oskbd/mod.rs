@@ -101,16 +101,77 @@ impl From<KeyValue> for bool {
 
 use kanata_parser::keys::OsCode;
 
+/// Identifies the input device that a key event was read from.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputDevice {
+    /// Unique among the input devices registered by kanata.
+    pub id: usize,
+    /// Index of the first `defdevice` section that matches the device.
+    pub cfg_idx: Option<usize>,
+}
+
+/// A change to the input devices used by kanata.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceChangeKind {
+    /// A new device that kanata will use was found.
+    Added,
+    Grabbed,
+    GrabFailed {
+        error: String,
+    },
+    Removed,
+}
+
+/// A change to an input device, along with the device properties for matching against
+/// `defdevice` sections.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceChange {
+    pub kind: DeviceChangeKind,
+    pub path: String,
+    pub name: String,
+    pub phys: String,
+    pub uniq: String,
+    pub bus: u16,
+    pub vendor: u16,
+    pub product: u16,
+}
+
+#[cfg(target_os = "linux")]
+impl DeviceChange {
+    pub fn info(&self) -> kanata_parser::cfg::DeviceInfo<'_> {
+        kanata_parser::cfg::DeviceInfo {
+            name: &self.name,
+            phys: &self.phys,
+            uniq: &self.uniq,
+            bus: self.bus,
+            vendor: self.vendor,
+            product: self.product,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct KeyEvent {
     pub code: OsCode,
     pub value: KeyValue,
+    /// The input device that the event was read from.
+    /// This is None for events that were not read from an input device.
+    #[cfg(target_os = "linux")]
+    pub device: Option<InputDevice>,
 }
 
 #[allow(dead_code, unused)]
 impl KeyEvent {
     pub fn new(code: OsCode, value: KeyValue) -> Self {
-        Self { code, value }
+        Self {
+            code,
+            value,
+            #[cfg(target_os = "linux")]
+            device: None,
+        }
     }
 }
 
@@ -132,12 +193,14 @@ impl fmt::Display for KeyEvent {
 
 impl fmt::Debug for KeyEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("KeyEvent")
-            .field(
-                "code",
-                &format_args!("{:?} ({})", self.code, self.code.as_u16()),
-            )
-            .field("value", &self.value)
-            .finish()
+        let mut f = f.debug_struct("KeyEvent");
+        f.field(
+            "code",
+            &format_args!("{:?} ({})", self.code, self.code.as_u16()),
+        )
+        .field("value", &self.value);
+        #[cfg(target_os = "linux")]
+        f.field("device", &self.device);
+        f.finish()
     }
 }
@@ -303,6 +303,9 @@ impl LogFmt {
 
 use super::*;
 
+mod layout;
+pub use layout::*;
+
 use crate::kanata::CalculatedMouseMove;
 use kanata_parser::custom_action::*;
 
@@ -314,6 +317,8 @@ use std::fmt;
 
 pub struct Outputs {
     pub events: Vec<String>,
+    /// Names of the output keys and mouse buttons that are currently pressed, in press order.
+    pub held: Vec<String>,
     ticks: u64,
 }
 
@@ -321,10 +326,22 @@ impl Outputs {
     fn new() -> Self {
         Self {
             events: vec![],
+            held: vec![],
             ticks: 0,
         }
     }
 
+    fn set_held(&mut self, name: String, pressed: bool) {
+        let pos = self.held.iter().position(|held| *held == name);
+        match (pressed, pos) {
+            (true, None) => self.held.push(name),
+            (false, Some(pos)) => {
+                self.held.remove(pos);
+            }
+            _ => {}
+        }
+    }
+
     fn push(&mut self, event: impl AsRef<str>) {
         if self.ticks > 0 {
             self.events.push(format!("t:{}ms", self.ticks));
@@ -334,10 +351,229 @@ impl Outputs {
     }
 }
 
+/// Simulation events as JSON-lines records, one JSON object per line. Every record has the
+/// simulated time in milliseconds and a `type` of `input`, `tick`, `output`, `mouse`, `unicode`,
+/// `layer` or `custom`. Custom records have the `kind` of the action and its parameters.
+#[derive(Default)]
+pub struct JsonLog {
+    time: u64,
+    pub records: Vec<String>,
+}
+
+impl JsonLog {
+    fn push(&mut self, kind: &str, mut record: serde_json::Value) {
+        record["time"] = self.time.into();
+        record["type"] = kind.into();
+        self.records.push(record.to_string());
+    }
+
+    fn key_name(key: OsCode) -> String {
+        format!("{:?}", KeyCode::from(key))
+    }
+
+    fn action_name(value: KeyValue) -> &'static str {
+        match value {
+            KeyValue::Press => "press",
+            KeyValue::Release => "release",
+            KeyValue::Repeat => "repeat",
+            KeyValue::Tap => "tap",
+            KeyValue::WakeUp => "wakeup",
+        }
+    }
+
+    pub fn input(&mut self, key: OsCode, value: KeyValue) {
+        let record = serde_json::json!({
+            "action": Self::action_name(value),
+            "key": Self::key_name(key),
+        });
+        self.push("input", record);
+    }
+    pub fn input_tick(&mut self, ms: u128) {
+        self.push("tick", serde_json::json!({ "ms": ms as u64 }));
+    }
+    pub fn layer(&mut self, name: &str) {
+        self.push("layer", serde_json::json!({ "name": name }));
+    }
+    pub fn custom_action(&mut self, action: &CustomAction) {
+        self.push("custom", Self::custom_action_record(action));
+    }
+    /// Returns the `kind` of the custom action, with its parameters as further fields.
+    fn custom_action_record(action: &CustomAction) -> serde_json::Value {
+        use serde_json::json;
+        let keys =
+            |keys: &[KeyCode]| -> Vec<String> { keys.iter().map(|k| format!("{k:?}")).collect() };
+        let (kind, mut record) = match action {
+            CustomAction::Cmd(args) => ("cmd", json!({ "args": args })),
+            CustomAction::CmdLog(_, _, args) => ("cmd-log", json!({ "args": args })),
+            CustomAction::CmdOutputKeys(args) => ("cmd-output-keys", json!({ "args": args })),
+            CustomAction::PushMessage(_) => ("push-msg", json!({})),
+            CustomAction::Unicode(c) => ("unicode", json!({ "char": c.to_string() })),
+            CustomAction::Mouse(btn) => ("mouse", json!({ "button": format!("{btn:?}") })),
+            CustomAction::MouseTap(btn) => ("mouse-tap", json!({ "button": format!("{btn:?}") })),
+            CustomAction::FakeKey { coord, action } => (
+                "fake-key",
+                json!({ "coord": [coord.x, coord.y], "action": format!("{action:?}") }),
+            ),
+            CustomAction::FakeKeyOnRelease { coord, action } => (
+                "fake-key-on-release",
+                json!({ "coord": [coord.x, coord.y], "action": format!("{action:?}") }),
+            ),
+            CustomAction::FakeKeyOnIdle(fk) => (
+                "fake-key-on-idle",
+                json!({
+                    "coord": [fk.coord.x, fk.coord.y],
+                    "action": format!("{:?}", fk.action),
+                    "ms": fk.idle_duration,
+                }),
+            ),
+            CustomAction::FakeKeyHoldForDuration(fk) => (
+                "fake-key-hold-for-duration",
+                json!({ "coord": [fk.coord.x, fk.coord.y], "ms": fk.hold_duration }),
+            ),
+            CustomAction::Delay(ms) => ("delay", json!({ "ms": ms })),
+            CustomAction::DelayOnRelease(ms) => ("delay-on-release", json!({ "ms": ms })),
+            CustomAction::MWheel {
+                direction,
+                interval,
+                distance,
+            } => (
+                "mwheel",
+                json!({
+                    "direction": format!("{direction:?}"),
+                    "interval": interval,
+                    "distance": distance,
+                }),
+            ),
+            CustomAction::MWheelNotch { direction } => (
+                "mwheel-notch",
+                json!({ "direction": format!("{direction:?}") }),
+            ),
+            CustomAction::MoveMouse {
+                direction,
+                interval,
+                distance,
+            } => (
+                "movemouse",
+                json!({
+                    "direction": format!("{direction:?}"),
+                    "interval": interval,
+                    "distance": distance,
+                }),
+            ),
+            CustomAction::MoveMouseAccel {
+                direction,
+                interval,
+                accel_time,
+                min_distance,
+                max_distance,
+            } => (
+                "movemouse-accel",
+                json!({
+                    "direction": format!("{direction:?}"),
+                    "interval": interval,
+                    "accel_time": accel_time,
+                    "min_distance": min_distance,
+                    "max_distance": max_distance,
+                }),
+            ),
+            CustomAction::MoveMouseSpeed { speed } => {
+                ("movemouse-speed", json!({ "speed": speed }))
+            }
+            CustomAction::SequenceCancel => ("sequence-cancel", json!({})),
+            CustomAction::SequenceLeader(timeout, _) => {
+                ("sequence-leader", json!({ "timeout": timeout }))
+            }
+            CustomAction::LiveReload => ("live-reload", json!({})),
+            CustomAction::LiveReloadNext => ("live-reload-next", json!({})),
+            CustomAction::LiveReloadPrev => ("live-reload-prev", json!({})),
+            CustomAction::LiveReloadNum(n) => ("live-reload-num", json!({ "index": n })),
+            CustomAction::LiveReloadFile(path) => ("live-reload-file", json!({ "path": path })),
+            CustomAction::Repeat => ("repeat", json!({})),
+            CustomAction::CancelMacroOnRelease => ("cancel-macro-on-release", json!({})),
+            CustomAction::CancelMacroOnNextPress(ms) => {
+                ("cancel-macro-on-next-press", json!({ "ms": ms }))
+            }
+            CustomAction::DynamicMacroRecord(id) => ("dynamic-macro-record", json!({ "id": id })),
+            CustomAction::DynamicMacroRecordStop(count) => {
+                ("dynamic-macro-record-stop", json!({ "truncate": count }))
+            }
+            CustomAction::DynamicMacroPlay(id) => ("dynamic-macro-play", json!({ "id": id })),
+            CustomAction::SendArbitraryCode(code) => ("arbitrary-code", json!({ "code": code })),
+            CustomAction::CapsWord(cfg) => ("caps-word", json!({ "timeout": cfg.timeout })),
+            CustomAction::SetMouse { x, y } => ("setmouse", json!({ "x": x, "y": y })),
+            CustomAction::Unmodded { keys: k, .. } => ("unmod", json!({ "keys": keys(k) })),
+            CustomAction::Unshifted { keys: k } => ("unshift", json!({ "keys": keys(k) })),
+            CustomAction::ReverseReleaseOrder => ("reverse-release-order", json!({})),
+        };
+        record["kind"] = kind.into();
+        record
+    }
+    fn output(&mut self, key: OsCode, value: KeyValue, raw: bool) {
+        let mut record = serde_json::json!({
+            "action": Self::action_name(value),
+            "key": Self::key_name(key),
+        });
+        if raw {
+            record["raw"] = true.into();
+        }
+        self.push("output", record);
+    }
+    fn output_code(&mut self, code: u32, value: KeyValue) {
+        let record = serde_json::json!({
+            "action": Self::action_name(value),
+            "code": code,
+        });
+        self.push("output", record);
+    }
+    fn unicode(&mut self, c: char) {
+        self.push("unicode", serde_json::json!({ "char": c.to_string() }));
+    }
+    fn mouse_btn(&mut self, btn: Btn, value: KeyValue) {
+        let record = serde_json::json!({
+            "action": Self::action_name(value),
+            "button": format!("{btn:?}"),
+        });
+        self.push("mouse", record);
+    }
+    fn mouse_distance(&mut self, action: &str, direction: String, distance: u16) {
+        let record = serde_json::json!({
+            "action": action,
+            "direction": direction,
+            "distance": distance,
+        });
+        self.push("mouse", record);
+    }
+    fn mouse_set(&mut self, x: u16, y: u16) {
+        let record = serde_json::json!({ "action": "set", "x": x, "y": y });
+        self.push("mouse", record);
+    }
+
+    pub fn end(&self, in_path: &PathBuf, appendix: Option<String>) {
+        let mut lines = self.records.join("\n");
+        lines.push('\n');
+        print!("{lines}");
+        if let Some(appendix_s) = appendix {
+            let out_path = append_file_name(in_path, appendix_s);
+            let out_path_s = out_path.display();
+            let mut out_file = match File::create(&out_path) {
+                Err(e) => panic!("✗ Couldn't create {}: {}", out_path_s, e),
+                Ok(out_file) => out_file,
+            };
+            match out_file.write_all(lines.as_bytes()) {
+                Err(e) => panic!("✗ Couldn't write to {}: {}", out_path_s, e),
+                Ok(_) => eprintln!("Saved output → {}", out_path_s),
+            }
+        }
+    }
+}
+
 /// Handle for writing keys to the OS.
 pub struct KbdOut {
     pub log: LogFmt,
     pub outputs: Outputs,
+    pub json: JsonLog,
+    /// The text typed by the outputs, if a keyboard layout is emulated.
+    pub typed: Option<TypedText>,
 }
 
 impl KbdOut {
@@ -345,6 +581,8 @@ impl KbdOut {
         Ok(Self {
             log: LogFmt::new(),
             outputs: Outputs::new(),
+            json: JsonLog::default(),
+            typed: None,
         })
     }
 
@@ -357,12 +595,26 @@ impl KbdOut {
         _s: &Option<String>,
         _tp: bool,
         _bustype: evdev::BusType,
+        _setmouse_extents: Option<kanata_parser::cfg::ScreenExtents>,
+        _name: &str,
+        _pointer: Option<(&str, evdev::BusType)>,
     ) -> Result<Self, io::Error> {
         Self::new_actual()
     }
     #[cfg(target_os = "linux")]
     pub fn write_raw(&mut self, event: InputEvent) -> Result<(), io::Error> {
         self.log.write_raw(event);
+        let value = if event.up {
+            KeyValue::Release
+        } else {
+            KeyValue::Press
+        };
+        self.json.output(OsCode::from(event.code), value, true);
+        if let Some(typed) = &mut self.typed {
+            typed.key(OsCode::from(event.code), value);
+        }
+        let key_name = format!("{:?}", KeyCode::from(OsCode::from(event.code)));
+        self.outputs.set_held(key_name, !event.up);
         self.outputs.push(format!("out-raw:{event:?}"));
         Ok(())
     }
@@ -371,6 +623,19 @@ impl KbdOut {
         Ok(())
     }
     pub fn write_key(&mut self, key: OsCode, value: KeyValue) -> Result<(), io::Error> {
+        self.json.output(key, value, false);
+        if let Some(typed) = &mut self.typed {
+            typed.key(key, value);
+        }
+        match value {
+            KeyValue::Press => self
+                .outputs
+                .set_held(format!("{:?}", KeyCode::from(key)), true),
+            KeyValue::Release => self
+                .outputs
+                .set_held(format!("{:?}", KeyCode::from(key)), false),
+            _ => {}
+        }
         let key_ev = KeyEvent::new(key, value);
         let event = {
             #[cfg(target_os = "macos")]
@@ -386,6 +651,7 @@ impl KbdOut {
     }
     pub fn write_code(&mut self, code: u32, value: KeyValue) -> Result<(), io::Error> {
         self.log.write_code(code, value);
+        self.json.output_code(code, value);
         self.outputs.push(format!("out-code:{code};{value:?}"));
         Ok(())
     }
@@ -399,21 +665,31 @@ impl KbdOut {
     }
     pub fn send_unicode(&mut self, c: char) -> Result<(), io::Error> {
         self.log.send_unicode(c);
+        self.json.unicode(c);
+        if let Some(typed) = &mut self.typed {
+            typed.unicode(c);
+        }
         self.outputs.push(format!("outU:{c}"));
         Ok(())
     }
     pub fn click_btn(&mut self, btn: Btn) -> Result<(), io::Error> {
         self.log.click_btn(btn);
+        self.json.mouse_btn(btn, KeyValue::Press);
+        self.outputs.set_held(format!("🖰{btn:?}"), true);
         self.outputs.push(format!("out🖰:↓{btn:?}"));
         Ok(())
     }
     pub fn release_btn(&mut self, btn: Btn) -> Result<(), io::Error> {
         self.log.release_btn(btn);
+        self.json.mouse_btn(btn, KeyValue::Release);
+        self.outputs.set_held(format!("🖰{btn:?}"), false);
         self.outputs.push(format!("out🖰:↑{btn:?}"));
         Ok(())
     }
     pub fn scroll(&mut self, direction: MWheelDirection, distance: u16) -> Result<(), io::Error> {
         self.log.scroll(direction, distance);
+        self.json
+            .mouse_distance("scroll", format!("{direction:?}"), distance);
         self.outputs
             .push(format!("scroll:{direction:?},{distance:?}"));
         Ok(())
@@ -421,6 +697,8 @@ impl KbdOut {
     pub fn move_mouse(&mut self, mv: CalculatedMouseMove) -> Result<(), io::Error> {
         let (direction, distance) = (mv.direction, mv.distance);
         self.log.move_mouse(direction, distance);
+        self.json
+            .mouse_distance("move", format!("{direction:?}"), distance);
         self.outputs
             .push(format!("out🖰:move {direction:?},{distance:?}"));
         Ok(())
@@ -429,6 +707,8 @@ impl KbdOut {
         for mv in moves {
             let (direction, distance) = (&mv.direction, &mv.distance);
             self.log.move_mouse(*direction, *distance);
+            self.json
+                .mouse_distance("move", format!("{direction:?}"), *distance);
             self.outputs
                 .push(format!("out🖰:move {direction:?},{distance:?}"));
         }
@@ -436,12 +716,14 @@ impl KbdOut {
     }
     pub fn set_mouse(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
         self.log.set_mouse(x, y);
+        self.json.mouse_set(x, y);
         log::info!("out🖰:@{x},{y}");
         Ok(())
     }
     pub fn tick(&mut self) {
         self.outputs.ticks += 1;
         self.log.ticks += 1;
+        self.json.time += 1;
     }
 }
 
@@ -476,13 +758,13 @@ impl InputEvent {
 impl TryFrom<InputEvent> for KeyEvent {
     type Error = ();
     fn try_from(item: InputEvent) -> Result<Self, Self::Error> {
-        Ok(Self {
-            code: OsCode::from_u16(item.code as u16).ok_or(())?,
-            value: match item.up {
+        Ok(Self::new(
+            OsCode::from_u16(item.code as u16).ok_or(())?,
+            match item.up {
                 true => KeyValue::Release,
                 false => KeyValue::Press,
             },
-        })
+        ))
     }
 }
 
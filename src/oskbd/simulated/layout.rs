@@ -0,0 +1,348 @@
+//! Emulation of the keyboard layout of the target OS, to show the text that an application would
+//! receive from the simulated output keys.
+//!
+//! The layouts are tables in the style of XKB symbol files: each key has up to four levels, which
+//! are chosen with Shift and AltGr, and a layout can include another one and change some keys.
+
+use std::str::FromStr;
+
+use kanata_parser::keys::OsCode;
+
+use crate::oskbd::KeyValue;
+
+/// A dead key changes the next character instead of typing one.
+struct DeadKey {
+    name: &'static str,
+    /// The character typed when the dead key is followed by space or by itself.
+    spacing: char,
+    /// Pairs of a base character and the character it is composed into.
+    compose: &'static str,
+}
+
+impl DeadKey {
+    fn compose(&self, c: char) -> Option<char> {
+        let mut chars = self.compose.chars();
+        while let (Some(base), Some(composed)) = (chars.next(), chars.next()) {
+            if base == c {
+                return Some(composed);
+            }
+        }
+        None
+    }
+}
+
+#[rustfmt::skip]
+const DEAD_KEYS: &[DeadKey] = &[
+    DeadKey { name: "dead_grave", spacing: '`', compose: "aàeèiìoòuùAÀEÈIÌOÒUÙ" },
+    DeadKey { name: "dead_acute", spacing: '´', compose: "aáeéiíoóuúyýAÁEÉIÍOÓUÚYÝ" },
+    DeadKey { name: "dead_circumflex", spacing: '^', compose: "aâeêiîoôuûAÂEÊIÎOÔUÛ" },
+    DeadKey { name: "dead_diaeresis", spacing: '¨', compose: "aäeëiïoöuüyÿAÄEËIÏOÖUÜ" },
+    DeadKey { name: "dead_tilde", spacing: '~', compose: "aãnñoõAÃNÑOÕ" },
+    DeadKey { name: "dead_cedilla", spacing: '¸', compose: "cçCÇ" },
+];
+
+/// The symbols of a key by level: base, Shift, AltGr and Shift+AltGr. Keys can have fewer
+/// levels, in which case the modifiers of the missing levels are ignored. An empty string is a
+/// level without a symbol and a name from [`DEAD_KEYS`] is a dead key.
+type Keymap = &'static [(OsCode, &'static [&'static str])];
+
+struct Layout {
+    include: Option<&'static Layout>,
+    /// Whether right Alt is AltGr, which selects the third and fourth levels.
+    altgr: bool,
+    keys: Keymap,
+}
+
+impl Layout {
+    fn levels(&self, key: OsCode) -> Option<&'static [&'static str]> {
+        match self.keys.iter().find(|(k, _)| *k == key) {
+            Some((_, levels)) => Some(levels),
+            None => self.include.and_then(|layout| layout.levels(key)),
+        }
+    }
+}
+
+use OsCode::*;
+
+#[rustfmt::skip]
+const US: Layout = Layout {
+    include: None,
+    altgr: false,
+    keys: &[
+        (KEY_GRAVE, &["`", "~"]),
+        (KEY_1, &["1", "!"]), (KEY_2, &["2", "@"]), (KEY_3, &["3", "#"]), (KEY_4, &["4", "$"]),
+        (KEY_5, &["5", "%"]), (KEY_6, &["6", "^"]), (KEY_7, &["7", "&"]), (KEY_8, &["8", "*"]),
+        (KEY_9, &["9", "("]), (KEY_0, &["0", ")"]),
+        (KEY_MINUS, &["-", "_"]), (KEY_EQUAL, &["=", "+"]),
+        (KEY_Q, &["q", "Q"]), (KEY_W, &["w", "W"]), (KEY_E, &["e", "E"]), (KEY_R, &["r", "R"]),
+        (KEY_T, &["t", "T"]), (KEY_Y, &["y", "Y"]), (KEY_U, &["u", "U"]), (KEY_I, &["i", "I"]),
+        (KEY_O, &["o", "O"]), (KEY_P, &["p", "P"]),
+        (KEY_LEFTBRACE, &["[", "{"]), (KEY_RIGHTBRACE, &["]", "}"]), (KEY_BACKSLASH, &["\\", "|"]),
+        (KEY_A, &["a", "A"]), (KEY_S, &["s", "S"]), (KEY_D, &["d", "D"]), (KEY_F, &["f", "F"]),
+        (KEY_G, &["g", "G"]), (KEY_H, &["h", "H"]), (KEY_J, &["j", "J"]), (KEY_K, &["k", "K"]),
+        (KEY_L, &["l", "L"]),
+        (KEY_SEMICOLON, &[";", ":"]), (KEY_APOSTROPHE, &["'", "\""]),
+        (KEY_102ND, &["<", ">"]),
+        (KEY_Z, &["z", "Z"]), (KEY_X, &["x", "X"]), (KEY_C, &["c", "C"]), (KEY_V, &["v", "V"]),
+        (KEY_B, &["b", "B"]), (KEY_N, &["n", "N"]), (KEY_M, &["m", "M"]),
+        (KEY_COMMA, &[",", "<"]), (KEY_DOT, &[".", ">"]), (KEY_SLASH, &["/", "?"]),
+        (KEY_SPACE, &[" "]), (KEY_TAB, &["\t"]), (KEY_ENTER, &["\n"]), (KEY_KPENTER, &["\n"]),
+        (KEY_KP0, &["0"]), (KEY_KP1, &["1"]), (KEY_KP2, &["2"]), (KEY_KP3, &["3"]),
+        (KEY_KP4, &["4"]), (KEY_KP5, &["5"]), (KEY_KP6, &["6"]), (KEY_KP7, &["7"]),
+        (KEY_KP8, &["8"]), (KEY_KP9, &["9"]),
+        (KEY_KPSLASH, &["/"]), (KEY_KPASTERISK, &["*"]), (KEY_KPMINUS, &["-"]),
+        (KEY_KPPLUS, &["+"]), (KEY_KPDOT, &["."]),
+    ],
+};
+
+#[rustfmt::skip]
+const UK: Layout = Layout {
+    include: Some(&US),
+    altgr: true,
+    keys: &[
+        (KEY_GRAVE, &["`", "¬", "¦", ""]),
+        (KEY_2, &["2", "\""]), (KEY_3, &["3", "£"]), (KEY_4, &["4", "$", "€", ""]),
+        (KEY_APOSTROPHE, &["'", "@"]), (KEY_BACKSLASH, &["#", "~"]),
+        (KEY_102ND, &["\\", "|"]),
+    ],
+};
+
+#[rustfmt::skip]
+const DE: Layout = Layout {
+    include: Some(&US),
+    altgr: true,
+    keys: &[
+        (KEY_GRAVE, &["dead_circumflex", "°", "′", "″"]),
+        (KEY_1, &["1", "!", "¹", "¡"]), (KEY_2, &["2", "\"", "²", "⅛"]),
+        (KEY_3, &["3", "§", "³", "£"]), (KEY_4, &["4", "$", "¼", "¤"]),
+        (KEY_5, &["5", "%", "½", "⅜"]), (KEY_6, &["6", "&", "¬", "⅝"]),
+        (KEY_7, &["7", "/", "{", "⅞"]), (KEY_8, &["8", "(", "[", "™"]),
+        (KEY_9, &["9", ")", "]", "±"]), (KEY_0, &["0", "=", "}", "°"]),
+        (KEY_MINUS, &["ß", "?", "\\", "¿"]),
+        (KEY_EQUAL, &["dead_acute", "dead_grave", "dead_cedilla", ""]),
+        (KEY_Q, &["q", "Q", "@", "Ω"]), (KEY_E, &["e", "E", "€", "€"]),
+        (KEY_Y, &["z", "Z", "←", "¥"]),
+        (KEY_LEFTBRACE, &["ü", "Ü", "dead_diaeresis", ""]),
+        (KEY_RIGHTBRACE, &["+", "*", "dead_tilde", "¯"]),
+        (KEY_SEMICOLON, &["ö", "Ö"]), (KEY_APOSTROPHE, &["ä", "Ä", "dead_circumflex", ""]),
+        (KEY_BACKSLASH, &["#", "'", "’", ""]),
+        (KEY_102ND, &["<", ">", "|", ""]),
+        (KEY_Z, &["y", "Y", "»", "›"]), (KEY_X, &["x", "X", "«", "‹"]),
+        (KEY_C, &["c", "C", "¢", "©"]), (KEY_V, &["v", "V", "„", "‚"]),
+        (KEY_B, &["b", "B", "“", "‘"]), (KEY_N, &["n", "N", "”", "’"]),
+        (KEY_M, &["m", "M", "µ", "º"]),
+        (KEY_COMMA, &[",", ";", "·", "×"]), (KEY_DOT, &[".", ":", "…", "÷"]),
+        (KEY_SLASH, &["-", "_", "–", "—"]),
+        (KEY_KPDOT, &[","]),
+    ],
+};
+
+#[rustfmt::skip]
+const FR: Layout = Layout {
+    include: Some(&US),
+    altgr: true,
+    keys: &[
+        (KEY_GRAVE, &["²"]),
+        (KEY_1, &["&", "1"]), (KEY_2, &["é", "2", "~", ""]), (KEY_3, &["\"", "3", "#", ""]),
+        (KEY_4, &["'", "4", "{", ""]), (KEY_5, &["(", "5", "[", ""]),
+        (KEY_6, &["-", "6", "|", ""]), (KEY_7, &["è", "7", "`", ""]),
+        (KEY_8, &["_", "8", "\\", ""]), (KEY_9, &["ç", "9", "^", ""]),
+        (KEY_0, &["à", "0", "@", ""]),
+        (KEY_MINUS, &[")", "°", "]", ""]), (KEY_EQUAL, &["=", "+", "}", ""]),
+        (KEY_Q, &["a", "A"]), (KEY_W, &["z", "Z"]), (KEY_E, &["e", "E", "€", ""]),
+        (KEY_LEFTBRACE, &["dead_circumflex", "dead_diaeresis"]),
+        (KEY_RIGHTBRACE, &["$", "£", "¤", ""]),
+        (KEY_A, &["q", "Q"]), (KEY_SEMICOLON, &["m", "M"]),
+        (KEY_APOSTROPHE, &["ù", "%"]), (KEY_BACKSLASH, &["*", "µ"]),
+        (KEY_Z, &["w", "W"]), (KEY_M, &[",", "?"]),
+        (KEY_COMMA, &[";", "."]), (KEY_DOT, &[":", "/"]), (KEY_SLASH, &["!", "§"]),
+    ],
+};
+
+/// A keyboard layout of the target OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsLayout {
+    Us,
+    Uk,
+    De,
+    Fr,
+}
+
+impl OsLayout {
+    fn layout(self) -> &'static Layout {
+        match self {
+            OsLayout::Us => &US,
+            OsLayout::Uk => &UK,
+            OsLayout::De => &DE,
+            OsLayout::Fr => &FR,
+        }
+    }
+}
+
+impl FromStr for OsLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "us" => OsLayout::Us,
+            "uk" | "gb" => OsLayout::Uk,
+            "de" => OsLayout::De,
+            "fr" => OsLayout::Fr,
+            _ => {
+                return Err(format!(
+                    "unknown layout: {s}, expected one of us, uk, de, fr"
+                ))
+            }
+        })
+    }
+}
+
+enum Symbol {
+    Char(char),
+    Dead(&'static DeadKey),
+}
+
+fn symbol(name: &str) -> Option<Symbol> {
+    if name.starts_with("dead_") {
+        let dead_key = DEAD_KEYS.iter().find(|dk| dk.name == name);
+        return dead_key.map(Symbol::Dead);
+    }
+    name.chars().next().map(Symbol::Char)
+}
+
+const SHIFTS: &[OsCode] = &[KEY_LEFTSHIFT, KEY_RIGHTSHIFT];
+/// Modifiers that make keys shortcuts instead of typing text. Right Alt is one of them unless it
+/// is AltGr.
+const SHORTCUT_MODS: &[OsCode] = &[
+    KEY_LEFTCTRL,
+    KEY_RIGHTCTRL,
+    KEY_LEFTALT,
+    KEY_LEFTMETA,
+    KEY_RIGHTMETA,
+];
+
+/// The text typed by the output keys with a keyboard layout.
+pub struct TypedText {
+    layout: &'static Layout,
+    /// Modifier keys that are currently pressed.
+    mods: Vec<OsCode>,
+    caps_lock: bool,
+    dead_key: Option<&'static DeadKey>,
+    pub text: String,
+}
+
+impl TypedText {
+    pub fn new(layout: OsLayout) -> Self {
+        Self {
+            layout: layout.layout(),
+            mods: vec![],
+            caps_lock: false,
+            dead_key: None,
+            text: String::new(),
+        }
+    }
+
+    fn held(&self, keys: &[OsCode]) -> bool {
+        self.mods.iter().any(|m| keys.contains(m))
+    }
+
+    pub fn key(&mut self, key: OsCode, value: KeyValue) {
+        match value {
+            KeyValue::Press | KeyValue::Repeat => {}
+            KeyValue::Release => {
+                self.mods.retain(|m| *m != key);
+                return;
+            }
+            _ => return,
+        }
+        if SHIFTS.contains(&key) || SHORTCUT_MODS.contains(&key) || key == KEY_RIGHTALT {
+            if !self.mods.contains(&key) {
+                self.mods.push(key);
+            }
+            return;
+        }
+        if key == KEY_CAPSLOCK {
+            if value == KeyValue::Press {
+                self.caps_lock = !self.caps_lock;
+            }
+            return;
+        }
+        let ralt = self.held(&[KEY_RIGHTALT]);
+        if self.held(SHORTCUT_MODS) || (ralt && !self.layout.altgr) {
+            // A shortcut does not type text.
+            return;
+        }
+        let altgr = ralt && self.layout.altgr;
+        if key == KEY_BACKSPACE {
+            if self.dead_key.take().is_none() {
+                self.text.pop();
+            }
+            return;
+        }
+        let shift = self.held(SHIFTS);
+        if let Some(symbol) = self.symbol(key, shift, altgr) {
+            self.type_symbol(symbol);
+        }
+    }
+
+    fn symbol(&self, key: OsCode, shift: bool, altgr: bool) -> Option<Symbol> {
+        let levels = self.layout.levels(key)?;
+        // Caps lock shifts the keys whose first two levels are a lower and upper case letter.
+        let shift = match levels {
+            [lower, upper, ..]
+                if self.caps_lock && !altgr && lower != upper && lower.to_uppercase() == *upper =>
+            {
+                !shift
+            }
+            _ => shift,
+        };
+        let mut level = usize::from(shift) + if altgr { 2 } else { 0 };
+        if level >= levels.len() {
+            level %= 2;
+        }
+        if level >= levels.len() {
+            level = 0;
+        }
+        symbol(levels[level])
+    }
+
+    fn type_symbol(&mut self, symbol: Symbol) {
+        match (self.dead_key.take(), symbol) {
+            (None, Symbol::Char(c)) => self.text.push(c),
+            (None, Symbol::Dead(dk)) => self.dead_key = Some(dk),
+            (Some(prev), Symbol::Dead(dk)) => {
+                self.text.push(prev.spacing);
+                if !std::ptr::eq(prev, dk) {
+                    self.dead_key = Some(dk);
+                }
+            }
+            (Some(dk), Symbol::Char(c)) => match dk.compose(c) {
+                Some(composed) => self.text.push(composed),
+                None if c == ' ' => self.text.push(dk.spacing),
+                None => {
+                    self.text.push(dk.spacing);
+                    self.text.push(c);
+                }
+            },
+        }
+    }
+
+    pub fn unicode(&mut self, c: char) {
+        self.text.push(c);
+    }
+}
+
+#[test]
+fn layout_tables_are_valid() {
+    for layout in [&US, &UK, &DE, &FR] {
+        for (key, levels) in layout.keys {
+            assert!((1..=4).contains(&levels.len()), "{key:?}");
+            for name in levels.iter() {
+                assert!(
+                    name.is_empty() || name.chars().count() == 1 || symbol(name).is_some(),
+                    "{key:?}: {name}"
+                );
+            }
+        }
+    }
+}
@@ -41,6 +41,9 @@ pub struct TcpServer {
     pub address: SocketAddr,
     pub connections: Connections,
     pub wakeup_channel: Sender<KeyEvent>,
+    /// A listener that is already bound, e.g. passed in by systemd socket activation.
+    /// If None, the server binds to `address` on start.
+    listener: Option<TcpListener>,
 }
 
 #[cfg(not(feature = "tcp_server"))]
@@ -55,21 +58,78 @@ impl TcpServer {
             address,
             connections: Arc::new(Mutex::new(HashMap::default())),
             wakeup_channel,
+            listener: None,
         }
     }
 
+    /// Create a server that accepts connections on a listener that is already bound.
+    #[cfg(feature = "tcp_server")]
+    pub fn from_listener(
+        listener: TcpListener,
+        wakeup_channel: Sender<KeyEvent>,
+    ) -> std::io::Result<Self> {
+        Ok(Self {
+            address: listener.local_addr()?,
+            connections: Arc::new(Mutex::new(HashMap::default())),
+            wakeup_channel,
+            listener: Some(listener),
+        })
+    }
+
     #[cfg(not(feature = "tcp_server"))]
     pub fn new(_address: SocketAddr, _wakeup_channel: Sender<KeyEvent>) -> Self {
         Self { connections: () }
     }
 
+    /// Returns the TCP listener passed in by systemd socket activation, if any.
+    #[cfg(all(feature = "tcp_server", target_os = "linux"))]
+    pub fn systemd_activated_listener() -> Option<TcpListener> {
+        use std::os::fd::{FromRawFd, OwnedFd};
+        let mut fds = match sd_notify::listen_fds() {
+            Ok(fds) => fds,
+            Err(e) => {
+                log::error!("failed to read the sockets passed by systemd: {e}");
+                return None;
+            }
+        };
+        let fd = fds.next()?;
+        // SAFETY: systemd passes ownership of the listening sockets starting at fd 3, and the
+        // environment variables that describe them were unset so they are only taken once.
+        let listener = unsafe { TcpListener::from_raw_fd(fd) };
+        let mut unused_count = 0;
+        for fd in fds {
+            // SAFETY: as above. Only the first socket is used, so close the others.
+            drop(unsafe { OwnedFd::from_raw_fd(fd) });
+            unused_count += 1;
+        }
+        if unused_count > 0 {
+            log::warn!(
+                "systemd passed more than one socket, only the first is used \
+                 and the other {unused_count} are closed"
+            );
+        }
+        match listener.local_addr() {
+            Ok(address) => {
+                log::info!("using the TCP socket {address} passed by systemd");
+                Some(listener)
+            }
+            Err(e) => {
+                log::error!("the socket passed by systemd is not a TCP socket: {e}");
+                None
+            }
+        }
+    }
+
     #[cfg(feature = "tcp_server")]
     pub fn start(&mut self, kanata: Arc<Mutex<Kanata>>) {
         use kanata_parser::cfg::FAKE_KEY_ROW;
 
         use crate::kanata::handle_fakekey_action;
 
-        let listener = TcpListener::bind(self.address).expect("TCP server starts");
+        let listener = match self.listener.take() {
+            Some(listener) => listener,
+            None => TcpListener::bind(self.address).expect("TCP server starts"),
+        };
 
         let connections = self.connections.clone();
         let wakeup_channel = self.wakeup_channel.clone();
@@ -225,10 +285,10 @@ impl TcpServer {
                                         }
                                         use kanata_parser::keys::*;
                                         wakeup_channel
-                                            .send(KeyEvent {
-                                                code: OsCode::KEY_RESERVED,
-                                                value: KeyValue::WakeUp,
-                                            })
+                                            .send(KeyEvent::new(
+                                                OsCode::KEY_RESERVED,
+                                                KeyValue::WakeUp,
+                                            ))
                                             .expect("write key event");
                                     }
                                     Err(e) => {
@@ -0,0 +1,110 @@
+use super::*;
+
+const CFG: &str = "
+(defsrc a b 1)
+(deflayer base a b 1)
+(defautoshift 200 a 1)
+";
+
+#[test]
+fn sim_autoshift_tap() {
+    let result = simulate(CFG, "d:a t:50 u:a t:50 d:1 t:50 u:1 t:50")
+        .no_time()
+        .to_ascii();
+    assert_eq!("dn:A up:A dn:Kb1 up:Kb1", result);
+}
+
+#[test]
+fn sim_autoshift_hold() {
+    let result = simulate(CFG, "d:a t:250 u:a t:50 d:1 t:250 u:1 t:50")
+        .no_time()
+        .to_ascii();
+    assert_eq!(
+        "dn:LShift dn:A up:LShift up:A dn:LShift dn:Kb1 up:LShift up:Kb1",
+        result
+    );
+}
+
+#[test]
+fn sim_autoshift_unlisted_key() {
+    let result = simulate(CFG, "d:b t:250 u:b t:50").no_time().to_ascii();
+    assert_eq!("dn:B up:B", result);
+}
+
+#[test]
+fn sim_autoshift_shift_not_applied_to_other_keys() {
+    let result = simulate(CFG, "d:a t:250 d:b t:50 u:b t:50 u:a t:50")
+        .no_time()
+        .to_ascii();
+    assert_eq!("dn:LShift dn:A up:LShift dn:B up:B up:A", result);
+}
+
+#[test]
+fn sim_autoshift_rolling() {
+    let result = simulate(CFG, "d:a t:50 d:b t:50 u:a t:50 u:b t:50")
+        .no_time()
+        .to_ascii();
+    assert_eq!("dn:A dn:B up:A up:B", result);
+}
+
+#[test]
+fn sim_autoshift_repeat() {
+    let result = simulate(CFG, "d:a t:250 r:a t:10 r:a t:10 u:a t:50")
+        .no_time()
+        .to_ascii();
+    assert_eq!("dn:LShift dn:A dn:A dn:A up:LShift up:A", result);
+}
+
+#[test]
+fn sim_autoshift_layers() {
+    let result = simulate(
+        "
+        (defsrc a b)
+        (deflayer base a (layer-while-held other))
+        (deflayer other 1 _)
+        (defautoshift 200 1)
+        ",
+        "d:a t:250 u:a t:50 d:b t:10 d:a t:250 u:a t:50 u:b t:10",
+    )
+    .no_time()
+    .to_ascii();
+    assert_eq!("dn:A up:A dn:LShift dn:Kb1 up:LShift up:Kb1", result);
+}
+
+#[test]
+fn sim_autoshift_defsrc_fallback() {
+    let result = simulate(
+        "
+        (defsrc a b)
+        (deflayer base _ (layer-while-held other))
+        (deflayer other _ _)
+        (defautoshift 200 a)
+        ",
+        "d:a t:250 u:a t:50 d:b t:10 d:a t:250 u:a t:50 u:b t:10",
+    )
+    .no_time()
+    .to_ascii();
+    assert_eq!(
+        "dn:LShift dn:A up:LShift up:A dn:LShift dn:A up:LShift up:A",
+        result
+    );
+}
+
+#[test]
+fn sim_autoshift_press_time_of_queued_key() {
+    let cfg = "
+        (defsrc a b)
+        (deflayer base a (tap-hold 200 200 b lctl))
+        (defautoshift 100 a)
+        ";
+    // The press of a waits behind the tap-hold until it times out, but a is held only shortly.
+    let result = simulate(cfg, "d:b t:10 d:a t:50 u:a t:150 u:b t:50")
+        .no_time()
+        .to_ascii();
+    assert_eq!("dn:LCtrl dn:A up:A up:LCtrl", result);
+    // The press of a waits behind the tap-hold, and a is held past the timeout.
+    let result = simulate(cfg, "d:b t:10 d:a t:150 u:a t:10 u:b t:50")
+        .no_time()
+        .to_ascii();
+    assert_eq!("dn:B dn:LShift dn:A up:LShift up:A up:B", result);
+}
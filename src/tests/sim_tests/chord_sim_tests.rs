@@ -403,3 +403,177 @@ fn sim_chord_oneshot() {
         result
     );
 }
+
+static CHORD_INTO_TAP_HOLD_VARIANTS_CFG: &str = "\
+(defcfg process-unmapped-keys yes concurrent-tap-hold yes)
+(defsrc)
+(deflayer base)
+(defchordsv2
+  (a b) (tap-hold-press 200 200 x y) 200 all-released ()
+  (c d) (tap-hold-release 200 200 x y) 200 first-release ()
+)";
+
+#[test]
+fn sim_chord_into_tap_hold_press() {
+    let result = simulate(
+        CHORD_INTO_TAP_HOLD_VARIANTS_CFG,
+        "d:a t:50 d:b t:50 u:a t:50 u:b t:500 \
+         d:a t:50 d:b t:300 u:a u:b t:500 \
+         d:a t:10 d:b t:10 d:e t:10 u:e t:10 u:a u:b t:500",
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:150ms dn:X t:10ms up:X \
+         t:689ms dn:Y t:154ms up:Y \
+         t:518ms dn:Y t:2ms dn:E t:7ms up:E t:13ms up:Y",
+        result
+    );
+}
+
+#[test]
+fn sim_chord_into_tap_hold_release() {
+    let result = simulate(
+        CHORD_INTO_TAP_HOLD_VARIANTS_CFG,
+        "d:c t:50 d:d t:50 u:c t:50 u:d t:500 \
+         d:c t:10 d:d t:10 d:e t:10 u:e t:10 u:c u:d t:500",
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:100ms dn:X t:9ms up:X \
+         t:571ms dn:Y t:2ms dn:E t:1ms up:E t:10ms up:Y",
+        result
+    );
+}
+
+static CHORD_PRIOR_IDLE_CFG: &str = "\
+(defcfg process-unmapped-keys yes concurrent-tap-hold yes
+ chords-v2-require-prior-idle 100)
+(defsrc)
+(deflayer base)
+(defchordsv2
+  (a b) c 50 all-released ()
+  (d e) f 50 all-released () (require-prior-idle 0)
+  (g h) i 50 all-released () (require-prior-idle 300)
+)";
+
+#[test]
+fn sim_chord_prior_idle_default() {
+    let result = simulate(
+        CHORD_PRIOR_IDLE_CFG,
+        "d:z t:10 u:z t:10 d:a t:10 d:b t:10 u:a u:b t:500 \
+         d:a t:10 d:b t:10 u:a u:b t:500",
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:1ms dn:Z t:9ms up:Z t:11ms dn:A t:10ms dn:B t:9ms up:A t:1ms up:B \
+         t:509ms dn:C t:13ms up:C",
+        result
+    );
+}
+
+#[test]
+fn sim_chord_prior_idle_per_chord() {
+    let result = simulate(
+        CHORD_PRIOR_IDLE_CFG,
+        "d:z t:10 u:z t:10 d:d t:10 d:e t:10 u:d u:e t:500 \
+         d:g t:10 d:h t:10 u:g u:h t:200 \
+         d:g t:10 d:h t:10 u:g u:h t:400 \
+         d:g t:10 d:h t:10 u:g u:h t:500",
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:1ms dn:Z t:9ms up:Z t:20ms dn:F t:13ms up:F \
+         t:507ms dn:I t:13ms up:I \
+         t:198ms dn:G t:10ms dn:H t:9ms up:G t:1ms up:H \
+         t:409ms dn:I t:13ms up:I",
+        result
+    );
+}
+
+#[test]
+fn sim_chord_many_simultaneously_active() {
+    let pairs = [
+        "a b", "c d", "e f", "g h", "i j", "k l", "m n", "o p", "q r", "s t", "u v", "w x",
+    ];
+    let outputs = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "f1", "f2"];
+    let chords = pairs
+        .iter()
+        .zip(outputs.iter())
+        .map(|(keys, out)| format!("({keys}) {out} 50 all-released ()"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let cfg = format!(
+        "(defcfg process-unmapped-keys yes concurrent-tap-hold yes)
+(defsrc)
+(deflayer base)
+(defchordsv2 {chords})"
+    );
+    let presses = pairs
+        .iter()
+        .map(|keys| {
+            keys.split(' ')
+                .map(|k| format!("d:{k} t:10 "))
+                .collect::<String>()
+        })
+        .collect::<String>();
+    let releases = pairs
+        .iter()
+        .map(|keys| {
+            keys.split(' ')
+                .map(|k| format!("u:{k} "))
+                .collect::<String>()
+        })
+        .collect::<String>();
+    let result = simulate(cfg, format!("{presses}{releases}t:100")).no_time();
+    assert_eq!(
+        "out:↓Kb1 out:↓Kb2 out:↓Kb3 out:↓Kb4 out:↓Kb5 out:↓Kb6 \
+         out:↓Kb7 out:↓Kb8 out:↓Kb9 out:↓Kb0 out:↓F1 out:↓F2 \
+         out:↑Kb1 out:↑Kb2 out:↑Kb3 out:↑Kb4 out:↑Kb5 out:↑Kb6 \
+         out:↑Kb7 out:↑Kb8 out:↑Kb9 out:↑Kb0 out:↑F1 out:↑F2",
+        result
+    );
+}
+
+#[test]
+fn sim_chord_many_participants() {
+    let result = simulate(
+        "(defcfg process-unmapped-keys yes concurrent-tap-hold yes)
+(defsrc)
+(deflayer base)
+(defchordsv2
+  (a b c d e f g h i j k l m n o p q r s t) z 200 all-released ()
+)",
+        "d:a d:b d:c d:d d:e d:f d:g d:h d:i d:j \
+         d:k d:l d:m d:n d:o d:p d:q d:r d:s d:t t:10 \
+         u:a u:b u:c u:d u:e u:f u:g u:h u:i u:j \
+         u:k u:l u:m u:n u:o u:p u:q u:r u:s u:t t:100",
+    )
+    .no_time();
+    assert_eq!("out:↓Z out:↑Z", result);
+}
+
+static CHORD_SMALL_QUEUE_CFG: &str = "\
+(defcfg process-unmapped-keys yes concurrent-tap-hold yes
+ chords-v2-queue-size 2)
+(defsrc)
+(deflayer base)
+(defchordsv2
+  (a b) x 50 all-released ()
+  (a b c) y 50 all-released ()
+)";
+
+#[test]
+fn sim_chord_queue_overflow() {
+    let result = simulate(
+        CHORD_SMALL_QUEUE_CFG,
+        "d:a t:10 d:b t:10 d:c t:10 u:a t:10 u:b t:10 u:c t:100 \
+         d:a t:10 d:b t:100 u:a u:b t:100",
+    )
+    .no_time();
+    // The press of c overflows the queue, forcing a out of chords processing,
+    // so the 3-key chord cannot activate. The events are still output in order.
+    assert_eq!(
+        "out:↓A out:↓B out:↓C out:↑A out:↑B out:↑C out:↓X out:↑X",
+        result
+    );
+}
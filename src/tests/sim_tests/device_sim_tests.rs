@@ -0,0 +1,185 @@
+use super::*;
+
+use crate::oskbd::{DeviceChange, DeviceChangeKind, InputDevice};
+use kanata_parser::keys::OsCode;
+
+/// Simulate key taps where each tap is read from the given device.
+fn simulate_devices(cfg: &str, taps: &[(OsCode, Option<InputDevice>)]) -> String {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut k = Kanata::new_from_str(cfg, Default::default()).expect("failed to parse cfg");
+    for (code, device) in taps.iter().copied() {
+        for value in [KeyValue::Press, KeyValue::Release] {
+            let mut event = KeyEvent::new(code, value);
+            event.device = device;
+            k.handle_input_event(&event).expect("input handles fine");
+            k.tick_ms(10, &None).unwrap();
+        }
+    }
+    drop(_lk);
+    k.kbd_out.outputs.events.join("\n")
+}
+
+const CFG: &str = "
+(defsrc a b)
+(deflayer base a (layer-switch other))
+(deflayer laptop x y)
+(deflayer other 1 2)
+(defdevice laptop name \"AT Translated Set 2 keyboard\" layer laptop)
+(defdevice split id 1234:abcd)
+";
+
+const LAPTOP: Option<InputDevice> = Some(InputDevice {
+    id: 1,
+    cfg_idx: Some(0),
+});
+const SPLIT: Option<InputDevice> = Some(InputDevice {
+    id: 2,
+    cfg_idx: Some(1),
+});
+const OTHER: Option<InputDevice> = Some(InputDevice {
+    id: 3,
+    cfg_idx: None,
+});
+
+#[test]
+fn sim_device_layer_switches_on_device_change() {
+    let result = simulate_devices(
+        CFG,
+        &[
+            (OsCode::KEY_A, OTHER),
+            (OsCode::KEY_A, LAPTOP),
+            (OsCode::KEY_B, LAPTOP),
+            (OsCode::KEY_A, SPLIT),
+            (OsCode::KEY_A, LAPTOP),
+        ],
+    )
+    .no_time()
+    .no_releases()
+    .to_ascii();
+    assert_eq!("dn:A dn:X dn:Y dn:A dn:X", result);
+}
+
+#[test]
+fn sim_device_layer_restores_previous_base_layer() {
+    let result = simulate_devices(
+        CFG,
+        &[
+            (OsCode::KEY_B, OTHER),
+            (OsCode::KEY_A, OTHER),
+            (OsCode::KEY_A, LAPTOP),
+            (OsCode::KEY_A, OTHER),
+        ],
+    )
+    .no_time()
+    .no_releases()
+    .to_ascii();
+    assert_eq!("dn:Kb1 dn:X dn:Kb1", result);
+}
+
+#[test]
+fn sim_device_layer_keeps_layer_switch_per_device() {
+    let result = simulate_devices(
+        "
+(defsrc a b)
+(deflayer base a b)
+(deflayer laptop x (layer-switch other))
+(deflayer other 1 2)
+(defdevice laptop name \"AT Translated Set 2 keyboard\" layer laptop)
+",
+        &[
+            (OsCode::KEY_A, LAPTOP),
+            (OsCode::KEY_B, LAPTOP),
+            (OsCode::KEY_A, LAPTOP),
+            (OsCode::KEY_A, OTHER),
+            (OsCode::KEY_A, LAPTOP),
+        ],
+    )
+    .no_time()
+    .no_releases()
+    .to_ascii();
+    assert_eq!("dn:X dn:Kb1 dn:A dn:Kb1", result);
+}
+
+#[test]
+fn sim_device_events_without_device_do_not_switch() {
+    let result = simulate_devices(
+        CFG,
+        &[
+            (OsCode::KEY_A, LAPTOP),
+            (OsCode::KEY_A, None),
+            (OsCode::KEY_A, OTHER),
+            (OsCode::KEY_A, None),
+        ],
+    )
+    .no_time()
+    .no_releases()
+    .to_ascii();
+    assert_eq!("dn:X dn:X dn:A dn:A", result);
+}
+
+fn dock_change(kind: DeviceChangeKind) -> DeviceChange {
+    DeviceChange {
+        kind,
+        path: "/dev/input/event9".into(),
+        name: "Dock Keyboard".into(),
+        phys: "usb-0000:00:14.0-2/input0".into(),
+        uniq: "".into(),
+        bus: 0x03,
+        vendor: 0x1234,
+        product: 0xabcd,
+    }
+}
+
+#[test]
+fn sim_device_connect_disconnect_actions() {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut k = Kanata::new_from_str(
+        "
+(defsrc a)
+(deflayer base a)
+(deflayer docked b)
+(defdevice dock
+  name \"Dock*\"
+  on-connect (layer-switch docked)
+  on-disconnect (layer-switch base))
+",
+        Default::default(),
+    )
+    .expect("failed to parse cfg");
+    let tap = |k: &mut Kanata| {
+        for value in [KeyValue::Press, KeyValue::Release] {
+            k.handle_input_event(&KeyEvent::new(OsCode::KEY_A, value))
+                .expect("input handles fine");
+            k.tick_ms(10, &None).unwrap();
+        }
+    };
+    for kind in [
+        DeviceChangeKind::Added,
+        DeviceChangeKind::Grabbed,
+        DeviceChangeKind::Removed,
+        DeviceChangeKind::GrabFailed {
+            error: "busy".into(),
+        },
+    ] {
+        tap(&mut k);
+        k.device_changes.push(dock_change(kind));
+        assert!(!k.is_idle());
+        k.handle_device_changes(&None);
+        k.tick_ms(10, &None).unwrap();
+    }
+    tap(&mut k);
+    drop(_lk);
+    let result = k.kbd_out.outputs.events.join("\n");
+    assert_eq!(
+        "dn:A dn:A dn:B dn:A dn:A",
+        result.no_time().no_releases().to_ascii()
+    );
+}
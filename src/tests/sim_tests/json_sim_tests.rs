@@ -0,0 +1,84 @@
+use super::*;
+
+/// Simulate like [`simulate`] but return the JSON-lines records, with inputs recorded as well.
+fn simulate_json(cfg: &str, sim: &str) -> String {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut k = Kanata::new_from_str(cfg, Default::default()).expect("failed to parse cfg");
+    for pair in sim.split_whitespace() {
+        let (kind, val) = pair.split_once(':').expect("valid item");
+        match kind {
+            "t" => {
+                let tick = str::parse::<u128>(val).expect("valid num for tick");
+                k.kbd_out.json.input_tick(tick);
+                k.tick_ms(tick, &None).unwrap();
+            }
+            "d" | "u" => {
+                let key_code = str_to_oscode(val).expect("valid keycode");
+                let value = if kind == "d" {
+                    KeyValue::Press
+                } else {
+                    KeyValue::Release
+                };
+                k.kbd_out.json.input(key_code, value);
+                k.handle_input_event(&KeyEvent::new(key_code, value))
+                    .expect("input handles fine");
+            }
+            _ => panic!("invalid item {pair}"),
+        }
+    }
+    drop(_lk);
+    k.kbd_out.json.records.join("\n")
+}
+
+#[test]
+fn json_records_keys_layers_and_unicode() {
+    let result = simulate_json(
+        "
+(defsrc a b)
+(deflayer base (layer-while-held other) b)
+(deflayer other _ (unicode 🙂))
+        ",
+        "d:b t:10 u:b t:10 d:a t:10 d:b t:10",
+    );
+    assert_eq!(
+        r#"{"action":"press","key":"B","time":0,"type":"input"}
+{"ms":10,"time":0,"type":"tick"}
+{"action":"press","key":"B","time":0,"type":"output"}
+{"action":"release","key":"B","time":10,"type":"input"}
+{"ms":10,"time":10,"type":"tick"}
+{"action":"release","key":"B","time":10,"type":"output"}
+{"action":"press","key":"A","time":20,"type":"input"}
+{"ms":10,"time":20,"type":"tick"}
+{"name":"other","time":20,"type":"layer"}
+{"action":"press","key":"B","time":30,"type":"input"}
+{"ms":10,"time":30,"type":"tick"}
+{"char":"🙂","kind":"unicode","time":30,"type":"custom"}
+{"char":"🙂","time":30,"type":"unicode"}"#,
+        result
+    );
+}
+
+#[test]
+fn json_records_mouse() {
+    let result = simulate_json(
+        "
+(defsrc a)
+(deflayer base mlft)
+        ",
+        "d:a t:10 u:a t:10",
+    );
+    assert_eq!(
+        r#"{"action":"press","key":"A","time":0,"type":"input"}
+{"ms":10,"time":0,"type":"tick"}
+{"button":"Left","kind":"mouse","time":0,"type":"custom"}
+{"action":"press","button":"Left","time":0,"type":"mouse"}
+{"action":"release","key":"A","time":10,"type":"input"}
+{"ms":10,"time":10,"type":"tick"}
+{"action":"release","button":"Left","time":10,"type":"mouse"}"#,
+        result
+    );
+}
@@ -12,16 +12,27 @@ use crate::{
 
 use rustc_hash::FxHashMap;
 
+mod autoshift_sim_tests;
 mod block_keys_tests;
 mod capsword_sim_tests;
 mod chord_sim_tests;
+#[cfg(target_os = "linux")]
+mod device_sim_tests;
+mod json_sim_tests;
 mod layer_sim_tests;
 mod macro_sim_tests;
+mod mouse_sim_tests;
 mod oneshot_tests;
+mod os_layout_sim_tests;
 mod override_tests;
+mod property_sim_tests;
 mod release_sim_tests;
 mod repeat_sim_tests;
 mod seq_sim_tests;
+#[cfg(target_os = "linux")]
+mod sim_recorder_tests;
+#[cfg(feature = "steno")]
+mod steno_sim_tests;
 mod switch_sim_tests;
 mod unicode_sim_tests;
 mod unmod_sim_tests;
@@ -44,7 +55,13 @@ fn simulate_with_file_content<S: AsRef<str>>(
         Err(poisoned) => poisoned.into_inner(),
     };
     let mut k = Kanata::new_from_str(cfg.as_ref(), file_content).expect("failed to parse cfg");
-    for pair in sim.as_ref().split_whitespace() {
+    run_sim(&mut k, sim.as_ref());
+    drop(_lk);
+    k.kbd_out.outputs.events.join("\n")
+}
+
+fn run_sim(k: &mut Kanata, sim: &str) {
+    for pair in sim.split_whitespace() {
         match pair.split_once(':') {
             Some((kind, val)) => match kind {
                 "t" => {
@@ -53,35 +70,24 @@ fn simulate_with_file_content<S: AsRef<str>>(
                 }
                 "d" => {
                     let key_code = str_to_oscode(val).expect("valid keycode");
-                    k.handle_input_event(&KeyEvent {
-                        code: key_code,
-                        value: KeyValue::Press,
-                    })
-                    .expect("input handles fine");
+                    k.handle_input_event(&KeyEvent::new(key_code, KeyValue::Press))
+                        .expect("input handles fine");
                 }
                 "u" => {
                     let key_code = str_to_oscode(val).expect("valid keycode");
-                    k.handle_input_event(&KeyEvent {
-                        code: key_code,
-                        value: KeyValue::Release,
-                    })
-                    .expect("input handles fine");
+                    k.handle_input_event(&KeyEvent::new(key_code, KeyValue::Release))
+                        .expect("input handles fine");
                 }
                 "r" => {
                     let key_code = str_to_oscode(val).expect("valid keycode");
-                    k.handle_input_event(&KeyEvent {
-                        code: key_code,
-                        value: KeyValue::Repeat,
-                    })
-                    .expect("input handles fine");
+                    k.handle_input_event(&KeyEvent::new(key_code, KeyValue::Repeat))
+                        .expect("input handles fine");
                 }
                 _ => panic!("invalid item {pair}"),
             },
             None => panic!("invalid item {pair}"),
         }
     }
-    drop(_lk);
-    k.kbd_out.outputs.events.join("\n")
 }
 
 #[allow(unused)]
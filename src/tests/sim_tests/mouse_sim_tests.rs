@@ -0,0 +1,41 @@
+use super::*;
+
+#[test]
+fn mouse_wheel_remapped_while_layer_held() {
+    let result = simulate(
+        "
+         (defsrc a mwu mwd)
+         (deflayer base (layer-while-held held) mwu mwd)
+         (deflayer held _ volu voldwn)
+        ",
+        "
+         d:mwu u:mwu t:10 d:a t:10 d:mwu u:mwu t:10 d:mwd u:mwd t:10 u:a t:10 d:mwd u:mwd t:10
+        ",
+    )
+    .to_ascii();
+    assert_eq!(
+        "scroll:Up,120 t:20ms dn:VolUp t:1ms up:VolUp t:9ms dn:VolDown t:1ms up:VolDown \
+         t:19ms scroll:Down,120",
+        result
+    );
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn mouse_extra_buttons_in_defsrc() {
+    let result = simulate(
+        "
+         (defsrc mbtn6 mbtn7 mbtn8)
+         (deflayer base C-c C-v mbtn8)
+        ",
+        "
+         d:mbtn6 t:10 u:mbtn6 t:10 d:mbtn7 t:10 u:mbtn7 t:10 d:mbtn8 t:10 u:mbtn8 t:10
+        ",
+    )
+    .to_ascii();
+    assert_eq!(
+        "dn:LCtrl dn:C t:10ms up:LCtrl up:C t:10ms dn:LCtrl dn:V t:10ms up:LCtrl up:V \
+         t:10ms dn:K279 t:10ms up:K279",
+        result
+    );
+}
@@ -0,0 +1,81 @@
+use super::*;
+
+use crate::oskbd::{OsLayout, TypedText};
+
+/// Simulate like [`simulate`] but return the text typed with the OS keyboard layout.
+fn simulate_typed_text<S: AsRef<str>>(cfg: S, sim: S, layout: OsLayout) -> String {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut k =
+        Kanata::new_from_str(cfg.as_ref(), Default::default()).expect("failed to parse cfg");
+    k.kbd_out.typed = Some(TypedText::new(layout));
+    run_sim(&mut k, sim.as_ref());
+    drop(_lk);
+    k.kbd_out.typed.take().expect("layout is set").text
+}
+
+#[test]
+fn de_layout_swaps_y_and_z() {
+    let result = simulate_typed_text(
+        "
+(defsrc a b c)
+(deflayer base y z (macro S-2 S-7))
+        ",
+        "d:a u:a d:b u:b d:c t:50 u:c t:10",
+        OsLayout::De,
+    );
+    assert_eq!("zy\"/", result);
+}
+
+#[test]
+fn de_layout_composes_dead_keys() {
+    let result = simulate_typed_text(
+        "
+(defsrc a b c)
+(deflayer base (macro grv e) (macro eql S-e grv grv) (macro S-eql a grv spc RA-rbrc n))
+        ",
+        "d:a t:50 u:a d:b t:50 u:b d:c t:50 u:c t:10",
+        OsLayout::De,
+    );
+    assert_eq!("êÉ^à^ñ", result);
+}
+
+#[test]
+fn fr_layout_with_altgr_and_caps_lock() {
+    let result = simulate_typed_text(
+        "
+(defsrc a b)
+(deflayer base (macro q a w Digit2 S-2) (macro caps a Digit2 caps RA-0 lbrc e))
+        ",
+        "d:a t:50 u:a d:b t:50 u:b t:10",
+        OsLayout::Fr,
+    );
+    assert_eq!("aqzé2Qé@ê", result);
+}
+
+#[test]
+fn uk_and_us_layouts() {
+    let cfg = "
+(defsrc a)
+(deflayer base (macro S-2 S-apo RA-4 C-a S-bksl))
+        ";
+    let sim = "d:a t:50 u:a t:10";
+    assert_eq!("\"@€~", simulate_typed_text(cfg, sim, OsLayout::Uk));
+    assert_eq!("@\"|", simulate_typed_text(cfg, sim, OsLayout::Us));
+}
+
+#[test]
+fn typed_text_follows_backspace_and_unicode() {
+    let result = simulate_typed_text(
+        "
+(defsrc a)
+(deflayer base (macro a b bspc (unicode 🙂) ret))
+        ",
+        "d:a t:50 u:a t:10",
+        OsLayout::Us,
+    );
+    assert_eq!("a🙂\n", result);
+}
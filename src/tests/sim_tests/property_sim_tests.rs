@@ -0,0 +1,255 @@
+//! Property tests that simulate random input streams against random configurations.
+//!
+//! The configurations are generated from a small grammar of the actions that interact the most:
+//! tap-hold, one-shot, chords, sequences, overrides and layers. After all inputs are released and
+//! enough time passes, kanata must have released every output key, be back on the first layer with
+//! nothing pending, and be idle.
+//!
+//! The number of cases and the starting seed can be changed with the environment variables
+//! `KANATA_PROPTEST_CASES` and `KANATA_PROPTEST_SEED`. A failure prints the seed, the
+//! configuration and the input, which can be saved to files and run with
+//! `kanata_simulated_input` to investigate.
+
+use super::*;
+
+const DEFAULT_CASES: u64 = 64;
+
+/// Source keys of the generated configurations and the keys used in the input streams.
+const SRC_KEYS: &[&str] = &["a", "s", "d", "f", "g", "h", "j", "k"];
+const OUT_KEYS: &[&str] = &["x", "y", "z", "lsft", "lctl", "lalt"];
+const LAYERS: &[&str] = &["base", "l1", "l2"];
+
+/// Longer than every timeout used by the generated configurations.
+const SETTLE_MS: u128 = 5000;
+
+/// SplitMix64, so that a case can be reproduced from its seed alone.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn timeout(&mut self) -> usize {
+        50 + self.below(250)
+    }
+}
+
+fn gen_other_layer(rng: &mut Rng, layer: usize) -> &'static str {
+    let other = 1 + rng.below(LAYERS.len() - 1);
+    LAYERS[if other == layer { 0 } else { other }]
+}
+
+fn gen_simple_action(rng: &mut Rng) -> String {
+    match rng.below(4) {
+        0 => format!("(multi {} {})", rng.pick(OUT_KEYS), rng.pick(OUT_KEYS)),
+        1 => format!("(macro {} {})", rng.pick(OUT_KEYS), rng.pick(OUT_KEYS)),
+        _ => rng.pick(OUT_KEYS).to_string(),
+    }
+}
+
+fn gen_action(rng: &mut Rng, layer: usize) -> String {
+    match rng.below(12) {
+        0 if layer > 0 => "_".to_string(),
+        1 => {
+            let variant = rng.pick(&["tap-hold", "tap-hold-press", "tap-hold-release"]);
+            let (tap_timeout, hold_timeout) = (rng.timeout(), rng.timeout());
+            let tap = gen_simple_action(rng);
+            let hold = if rng.chance(50) {
+                format!("(layer-while-held {})", gen_other_layer(rng, layer))
+            } else {
+                gen_simple_action(rng)
+            };
+            format!("({variant} {tap_timeout} {hold_timeout} {tap} {hold})")
+        }
+        2 => {
+            let variant = rng.pick(&["one-shot", "one-shot-press", "one-shot-release"]);
+            let timeout = rng.timeout() * 2;
+            let held = if rng.chance(30) {
+                format!("(layer-while-held {})", gen_other_layer(rng, layer))
+            } else {
+                rng.pick(&["lsft", "lctl", "lalt"]).to_string()
+            };
+            format!("({variant} {timeout} {held})")
+        }
+        3 => format!("(layer-while-held {})", gen_other_layer(rng, layer)),
+        4 => format!(
+            "(tap-dance {} ({} {}))",
+            rng.timeout(),
+            gen_simple_action(rng),
+            gen_simple_action(rng)
+        ),
+        5 => "sldr".to_string(),
+        6 => "(caps-word 500)".to_string(),
+        7 => "XX".to_string(),
+        _ => gen_simple_action(rng),
+    }
+}
+
+fn gen_cfg(rng: &mut Rng) -> String {
+    let chords = rng.chance(50);
+    let mut cfg = format!(
+        "(defcfg process-unmapped-keys {} concurrent-tap-hold {})\n(defsrc {})\n",
+        if rng.chance(50) { "yes" } else { "no" },
+        if chords || rng.chance(50) {
+            "yes"
+        } else {
+            "no"
+        },
+        SRC_KEYS.join(" "),
+    );
+    for (layer, name) in LAYERS.iter().enumerate() {
+        let actions: Vec<String> = SRC_KEYS.iter().map(|_| gen_action(rng, layer)).collect();
+        cfg += &format!("(deflayer {name} {})\n", actions.join(" "));
+    }
+    cfg += &format!(
+        "(defvirtualkeys vk1 {})\n(defseq vk1 ({} {}))\n",
+        gen_simple_action(rng),
+        rng.pick(SRC_KEYS),
+        rng.pick(SRC_KEYS),
+    );
+    if chords {
+        cfg += &format!(
+            "(defchordsv2 ({} {}) {} {} {} ())\n",
+            SRC_KEYS[0],
+            SRC_KEYS[1 + rng.below(3)],
+            gen_simple_action(rng),
+            rng.timeout(),
+            rng.pick(&["all-released", "first-release"]),
+        );
+    }
+    if rng.chance(50) {
+        cfg += &format!(
+            "(defoverrides ({} {}) ({} {}))\n",
+            rng.pick(&["lsft", "lctl"]),
+            rng.pick(&["x", "y", "z"]),
+            rng.pick(&["lsft", "lctl", "lalt"]),
+            rng.pick(&["x", "y", "z"]),
+        );
+    }
+    cfg
+}
+
+/// Generate an input stream where every pressed key is eventually released.
+fn gen_input(rng: &mut Rng) -> String {
+    let mut held: Vec<&str> = vec![];
+    let mut items = vec![];
+    for _ in 0..10 + rng.below(50) {
+        match rng.below(3) {
+            0 if !held.is_empty() => {
+                let key = held.swap_remove(rng.below(held.len()));
+                items.push(format!("u:{key}"));
+            }
+            1 => items.push(format!("t:{}", 1 + rng.below(300))),
+            _ => {
+                let key = rng.pick(SRC_KEYS);
+                if !held.contains(&key) {
+                    held.push(key);
+                    items.push(format!("d:{key}"));
+                }
+            }
+        }
+    }
+    for key in held {
+        items.push(format!("t:{}", 1 + rng.below(100)));
+        items.push(format!("u:{key}"));
+    }
+    items.push(format!("t:{SETTLE_MS}"));
+    items.join(" ")
+}
+
+/// Returns a description of the first violated property.
+fn check_case(cfg: &str, input: &str) -> Option<String> {
+    let mut k = Kanata::new_from_str(cfg, Default::default()).expect("generated cfg is valid");
+    for pair in input.split_whitespace() {
+        let (kind, val) = pair.split_once(':').expect("generated item is valid");
+        match kind {
+            "t" => k
+                .tick_ms(val.parse().expect("valid tick"), &None)
+                .expect("tick works"),
+            _ => {
+                let value = if kind == "d" {
+                    KeyValue::Press
+                } else {
+                    KeyValue::Release
+                };
+                let key_code = str_to_oscode(val).expect("valid keycode");
+                k.handle_input_event(&KeyEvent::new(key_code, value))
+                    .expect("input handles fine");
+            }
+        }
+    }
+    let end_state = k.sim_end_state();
+    if !end_state.is_clean() || end_state.switched_base_layer.is_some() {
+        return Some(end_state.to_string());
+    }
+    if !k.is_idle() {
+        return Some("kanata is not idle".to_string());
+    }
+    None
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.parse().expect("valid number"))
+}
+
+#[test]
+fn random_inputs_release_everything() {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let cases = env_u64("KANATA_PROPTEST_CASES").unwrap_or(DEFAULT_CASES);
+    let first_seed = env_u64("KANATA_PROPTEST_SEED").unwrap_or(0);
+    for seed in first_seed..first_seed + cases {
+        let mut rng = Rng(seed);
+        let cfg = gen_cfg(&mut rng);
+        let input = gen_input(&mut rng);
+        let result = std::panic::catch_unwind(|| check_case(&cfg, &input));
+        let failure = match result {
+            Ok(None) => continue,
+            Ok(Some(failure)) => failure,
+            Err(_) => "panicked".to_string(),
+        };
+        panic!("seed {seed}: {failure}\nconfig:\n{cfg}\ninput:\n{input}");
+    }
+}
+
+#[test]
+fn check_case_detects_violations() {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    assert_eq!(
+        check_case(
+            "(defsrc a) (deflayer base (layer-switch l1)) (deflayer l1 a)",
+            "d:a t:10 u:a t:10",
+        ),
+        Some("base layer switched to: l1".to_string()),
+    );
+    assert_eq!(
+        check_case("(defsrc a) (deflayer base a)", "d:a t:10"),
+        Some("output keys still pressed: A".to_string()),
+    );
+}
@@ -1,5 +1,7 @@
 use super::*;
 
+use kanata_parser::keys::OsCode;
+
 #[test]
 fn release_standard() {
     let result = simulate(
@@ -29,3 +31,41 @@ fn release_reversed() {
     .to_ascii();
     assert_eq!("dn:LAlt dn:A t:10ms up:A up:LAlt", result);
 }
+
+#[test]
+fn end_state_reports_held_layers_and_pending_actions() {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut k = Kanata::new_from_str(
+        "
+         (defsrc a b)
+         (deflayer base (layer-while-held other) (one-shot 1000 lsft))
+         (deflayer other x (one-shot 1000 lsft))
+        ",
+        Default::default(),
+    )
+    .expect("failed to parse cfg");
+    k.handle_input_event(&KeyEvent::new(OsCode::KEY_A, KeyValue::Press))
+        .expect("input handles fine");
+    k.tick_ms(10, &None).unwrap();
+    k.handle_input_event(&KeyEvent::new(OsCode::KEY_B, KeyValue::Press))
+        .expect("input handles fine");
+    k.handle_input_event(&KeyEvent::new(OsCode::KEY_B, KeyValue::Release))
+        .expect("input handles fine");
+    k.tick_ms(10, &None).unwrap();
+    let end_state = k.sim_end_state();
+    assert!(!end_state.is_clean());
+    assert_eq!(
+        "output keys still pressed: LShift\nlayers still held: other\nstill pending: one-shot",
+        end_state.to_string()
+    );
+
+    k.handle_input_event(&KeyEvent::new(OsCode::KEY_A, KeyValue::Release))
+        .expect("input handles fine");
+    k.tick_ms(1000, &None).unwrap();
+    drop(_lk);
+    assert_eq!("end state is clean", k.sim_end_state().to_string());
+}
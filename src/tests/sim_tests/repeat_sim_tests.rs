@@ -134,3 +134,41 @@ fn repeat_delegate_to_base_layer_trans() {
         result
     );
 }
+
+#[test]
+#[cfg(target_os = "linux")]
+fn repeat_generated_by_kanata() {
+    let result = simulate(
+        "
+         (defcfg
+          linux-key-repeat-delay-rate 100,50
+          linux-key-repeat-output repeat)
+         (defsrc a b)
+         (deflayer base b a)
+        ",
+        "
+         d:a t:50 r:a t:95 u:a t:50 d:b t:110 d:a t:30 u:a u:b t:10
+        ",
+    );
+    assert_eq!(
+        "out:↓B\nt:100ms\nout:↓B\nt:20ms\nout:↓B\nt:20ms\nout:↓B\nt:5ms\nout:↑B\nt:50ms\nout:↓A\nt:100ms\nout:↓A\nt:10ms\nout:↓B\nt:30ms\nout:↑B\nt:1ms\nout:↑A",
+        result
+    );
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn repeat_generated_by_kanata_press_output() {
+    let result = simulate(
+        "
+         (defcfg linux-key-repeat-delay-rate 50,100)
+         (defsrc a)
+         (deflayer base b)
+        ",
+        "
+         d:a t:65 u:a t:10
+        ",
+    )
+    .to_ascii();
+    assert_eq!("dn:B t:50ms up:B dn:B t:10ms up:B dn:B t:5ms up:B", result);
+}
@@ -178,6 +178,94 @@ fn non_overlapping_then_overlap_activate_nothing() {
     );
 }
 
+/// Presses and releases each key and returns the notifications sent to TCP clients.
+#[cfg(feature = "tcp_server")]
+fn sequence_messages(
+    cfg: &str,
+    keys: &[kanata_parser::keys::OsCode],
+) -> Vec<kanata_tcp_protocol::ServerMessage> {
+    init_log();
+    let _lk = match CFG_PARSE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut k = Kanata::new_from_str(cfg, Default::default()).expect("failed to parse cfg");
+    let (tx, rx) = std::sync::mpsc::sync_channel(100);
+    let tx = Some(tx);
+    for &code in keys {
+        for value in [KeyValue::Press, KeyValue::Release] {
+            k.handle_input_event(&KeyEvent::new(code, value))
+                .expect("input handles fine");
+            k.tick_ms(10, &tx).unwrap();
+        }
+    }
+    drop(_lk);
+    rx.try_iter().collect()
+}
+
+#[test]
+#[cfg(feature = "tcp_server")]
+fn sequence_progress_notifications() {
+    use kanata_parser::keys::OsCode;
+    use kanata_tcp_protocol::{SequenceCompletion, ServerMessage};
+
+    let msgs = sequence_messages(
+        "(defcfg sequence-progress-show-names yes)
+         (defsrc 0 a b c)
+         (deflayer base sldr a b c)
+         (defvirtualkeys ab x abc y)
+         (defseq ab (a b) abc (a S-c))",
+        &[OsCode::KEY_0, OsCode::KEY_A, OsCode::KEY_B],
+    );
+    assert_eq!(msgs.len(), 3, "{msgs:?}");
+    let completion = |remaining: &[&str], name: &str| SequenceCompletion {
+        remaining: remaining.iter().map(|s| s.to_string()).collect(),
+        name: Some(name.into()),
+    };
+    match &msgs[0] {
+        ServerMessage::SequenceProgress { typed, completions } => {
+            assert!(typed.is_empty());
+            assert_eq!(completions.len(), 2);
+            assert!(completions.contains(&completion(&["A", "B"], "ab")));
+            assert!(completions.contains(&completion(&["A", "S-C"], "abc")));
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+    match &msgs[1] {
+        ServerMessage::SequenceProgress { typed, completions } => {
+            assert_eq!(typed, &["A".to_string()]);
+            assert_eq!(completions.len(), 2);
+            assert!(completions.contains(&completion(&["B"], "ab")));
+            assert!(completions.contains(&completion(&["S-C"], "abc")));
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+    assert!(matches!(&msgs[2], ServerMessage::SequenceEnd {}));
+}
+
+#[test]
+#[cfg(feature = "tcp_server")]
+fn sequence_progress_after_backtracking() {
+    use kanata_parser::keys::OsCode;
+    use kanata_tcp_protocol::ServerMessage;
+
+    let msgs = sequence_messages(
+        "(defsrc 0 a c d)
+         (deflayer base sldr a c d)
+         (defvirtualkeys ab x cd y)
+         (defseq ab (a b) cd (c d))",
+        &[OsCode::KEY_0, OsCode::KEY_A, OsCode::KEY_C],
+    );
+    let typed: Vec<_> = msgs
+        .iter()
+        .map(|msg| match msg {
+            ServerMessage::SequenceProgress { typed, .. } => typed.join(" "),
+            msg => panic!("unexpected message {msg:?}"),
+        })
+        .collect();
+    assert_eq!(typed, ["", "A", "C"]);
+}
+
 /* BUG: chorded_hidden_delay_type
  *
  * Enable this test when fixing.
@@ -0,0 +1,48 @@
+use super::*;
+
+use crate::kanata::sim_recorder::SimRecorder;
+use kanata_parser::keys::OsCode;
+
+#[test]
+fn sim_recorder_writes_replayable_events() {
+    let path = std::env::temp_dir().join(format!("kanata-recording-{}.txt", std::process::id()));
+    let mut recorder = SimRecorder::create(&path).expect("file is created");
+    let sleep = || std::thread::sleep(std::time::Duration::from_millis(20));
+    recorder
+        .record(&KeyEvent::new(OsCode::KEY_A, KeyValue::Press))
+        .unwrap();
+    sleep();
+    recorder
+        .record(&KeyEvent::new(OsCode::KEY_A, KeyValue::Release))
+        .unwrap();
+    sleep();
+    recorder
+        .record(&KeyEvent::new(OsCode::KEY_B, KeyValue::Tap))
+        .unwrap();
+    recorder
+        .record(&KeyEvent::new(OsCode::KEY_C, KeyValue::WakeUp))
+        .unwrap();
+    drop(recorder);
+    let recording = std::fs::read_to_string(&path).expect("file is readable");
+    let _ = std::fs::remove_file(&path);
+
+    let lines: Vec<&str> = recording.lines().collect();
+    assert_eq!(lines.len(), 3, "{recording}");
+    assert_eq!(lines[0], "d:a");
+    for (line, event) in [(lines[1], "u:a"), (lines[2], "d:b u:b")] {
+        let (delta, rest) = line.split_once(' ').expect("event has a delta");
+        let ms: u128 = delta
+            .strip_prefix("t:")
+            .and_then(|ms| ms.parse().ok())
+            .unwrap_or_else(|| panic!("invalid delta: {line}"));
+        assert!(ms >= 20, "{line}");
+        assert_eq!(rest, event);
+    }
+
+    // The recording ends with the last event, so tick once more for it to be processed.
+    let sim = format!("{recording} t:10");
+    let result = simulate("(defsrc a b) (deflayer base 1 2)", &sim)
+        .no_time()
+        .to_ascii();
+    assert_eq!("dn:Kb1 up:Kb1 dn:Kb2 up:Kb2", result);
+}
@@ -0,0 +1,126 @@
+use super::*;
+
+static STENO_CFG: &str = "(defsrc)(deflayer base)(defsteno dict.json)";
+static STENO_DICT: &str = r#"{
+"KAT": "cat",
+"KAT/-S": "cats",
+"-S": "{^s}",
+"H-L": "hello",
+"TP-PL": "{.}",
+"1-D": "1st"
+}"#;
+
+fn simulate_with_steno_dict(cfg: &str, input: &str, dict: &str) -> String {
+    let mut fcontent = FxHashMap::default();
+    fcontent.insert("dict.json".into(), dict.into());
+    simulate_with_file_content(cfg, input, fcontent)
+}
+
+#[test]
+fn sim_steno_single_strokes() {
+    let result = simulate_with_steno_dict(
+        STENO_CFG,
+        "d:s d:c d:p t:10 u:s u:c t:10 u:p t:10 d:r d:o t:10 u:r u:o t:10",
+        STENO_DICT,
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:20ms dn:C up:C dn:A up:A dn:T up:T \
+         t:21ms dn:Space up:Space dn:H up:H dn:E up:E dn:L up:L dn:L up:L dn:O up:O",
+        result
+    );
+}
+
+#[test]
+fn sim_steno_multi_stroke() {
+    let result = simulate_with_steno_dict(
+        STENO_CFG,
+        "d:s d:c d:p t:10 u:s u:c u:p t:10 d:; t:10 u:; t:10",
+        STENO_DICT,
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:12ms dn:C up:C dn:A up:A dn:T up:T \
+         t:18ms dn:BSpace up:BSpace dn:BSpace up:BSpace dn:BSpace up:BSpace \
+         dn:C up:C dn:A up:A dn:T up:T dn:S up:S",
+        result
+    );
+}
+
+#[test]
+fn sim_steno_undo() {
+    let result = simulate_with_steno_dict(
+        STENO_CFG,
+        "d:s d:c d:p t:10 u:s u:c u:p t:10 d:; t:10 u:; t:10 \
+         d:t t:10 u:t t:10 d:t t:10 u:t t:10",
+        STENO_DICT,
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:12ms dn:C up:C dn:A up:A dn:T up:T \
+         t:18ms dn:BSpace up:BSpace dn:BSpace up:BSpace dn:BSpace up:BSpace \
+         dn:C up:C dn:A up:A dn:T up:T dn:S up:S \
+         t:20ms dn:BSpace up:BSpace dn:BSpace up:BSpace dn:BSpace up:BSpace dn:BSpace up:BSpace \
+         dn:C up:C dn:A up:A dn:T up:T \
+         t:20ms dn:BSpace up:BSpace dn:BSpace up:BSpace dn:BSpace up:BSpace",
+        result
+    );
+}
+
+#[test]
+fn sim_steno_untranslated_numbers_and_capitalize() {
+    let result = simulate_with_steno_dict(
+        STENO_CFG,
+        "d:w d:e d:i d:o t:10 u:w u:e u:i u:o t:10 \
+         d:r d:o t:10 u:r u:o t:10 \
+         d:1 d:q d:[ t:10 u:1 u:q u:[ t:10 \
+         d:q d:w t:10 u:q u:w t:10",
+        STENO_DICT,
+    )
+    .to_ascii();
+    assert_eq!(
+        "t:13ms dn:Dot up:Dot \
+         t:18ms dn:Space up:Space dn:LShift dn:H up:H up:LShift dn:E up:E dn:L up:L dn:L up:L dn:O up:O \
+         t:21ms dn:Space up:Space dn:Kb1 up:Kb1 dn:S up:S dn:T up:T \
+         t:19ms dn:Space up:Space dn:LShift dn:S up:S up:LShift dn:LShift dn:T up:T up:LShift",
+        result
+    );
+}
+
+#[test]
+fn sim_steno_layers() {
+    let result = simulate_with_steno_dict(
+        "(defsrc lalt)
+         (deflayer base (layer-while-held steno))
+         (deflayer steno _)
+         (defsteno dict.json layers (steno) steno-keys (r H- o -L))",
+        "d:r t:10 u:r t:10 d:lalt t:10 d:r d:o t:10 u:r u:o t:10 u:lalt t:10 d:o t:10 u:o t:10",
+        STENO_DICT,
+    )
+    .to_ascii();
+    assert_eq!(
+        "dn:R t:10ms up:R \
+         t:31ms dn:H up:H dn:E up:E dn:L up:L dn:L up:L dn:O up:O \
+         t:19ms dn:O t:10ms up:O",
+        result
+    );
+}
+
+#[test]
+fn sim_steno_first_dictionary_has_priority() {
+    let mut fcontent = FxHashMap::default();
+    fcontent.insert("user.json".into(), r#"{"KAT": "kit"}"#.into());
+    fcontent.insert("main.json".into(), STENO_DICT.into());
+    let result = simulate_with_file_content(
+        "(defsrc)(deflayer base)(defsteno (user.json main.json))",
+        "d:s d:c d:p t:10 u:s u:c u:p t:10 d:r d:o t:10 u:r u:o t:10",
+        fcontent,
+    )
+    .no_time()
+    .to_ascii();
+    assert_eq!(
+        "dn:K up:K dn:I up:I dn:T up:T \
+         dn:Space up:Space dn:H up:H dn:E up:E dn:L up:L dn:L up:L dn:O up:O",
+        result
+    );
+}
@@ -3,13 +3,64 @@ use std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ServerMessage {
-    LayerChange { new: String },
-    LayerNames { names: Vec<String> },
-    CurrentLayerInfo { name: String, cfg_text: String },
-    ConfigFileReload { new: String },
-    CurrentLayerName { name: String },
-    MessagePush { message: serde_json::Value },
-    Error { msg: String },
+    LayerChange {
+        new: String,
+    },
+    LayerNames {
+        names: Vec<String>,
+    },
+    CurrentLayerInfo {
+        name: String,
+        cfg_text: String,
+    },
+    ConfigFileReload {
+        new: String,
+    },
+    CurrentLayerName {
+        name: String,
+    },
+    MessagePush {
+        message: serde_json::Value,
+    },
+    Error {
+        msg: String,
+    },
+    SequenceProgress {
+        typed: Vec<String>,
+        completions: Vec<SequenceCompletion>,
+    },
+    SequenceEnd {},
+    /// Linux only: a new input device that kanata will use was found.
+    DeviceAdded {
+        name: String,
+        path: String,
+    },
+    /// Linux only: kanata grabbed an input device and is processing its events.
+    DeviceGrabbed {
+        name: String,
+        path: String,
+    },
+    /// Linux only: kanata failed to grab an input device.
+    DeviceGrabFailed {
+        name: String,
+        path: String,
+        error: String,
+    },
+    /// Linux only: an input device used by kanata was removed.
+    DeviceRemoved {
+        name: String,
+        path: String,
+    },
+}
+
+/// A sequence that can still be completed from the currently typed sequence keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequenceCompletion {
+    /// The keys that remain to be typed to complete the sequence.
+    pub remaining: Vec<String>,
+    /// The virtual key name that the sequence activates. Only sent when
+    /// `sequence-progress-show-names` is enabled.
+    pub name: Option<String>,
 }
 
 impl ServerMessage {
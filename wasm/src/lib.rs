@@ -24,6 +24,45 @@ pub fn check_config(cfg: &str) -> JsValue {
     })
 }
 
+/// Like [`check_config`], but returns a JSON array of diagnostics so that an editor can show the
+/// errors inline. The array is empty if the configuration is valid. Each diagnostic has a
+/// `message`, `help`, `file_name` and `span`, which can be null. The span has byte offsets
+/// `start` and `end`, and 1-based `start_line`, `start_column`, `end_line` and `end_column`
+/// where columns count characters.
+#[wasm_bindgen]
+pub fn check_config_diagnostics(cfg: &str) -> JsValue {
+    let (cfg, files) = split_cfg_and_sim_files(cfg);
+    let diagnostics = match Kanata::new_from_str(&cfg, files) {
+        Ok(_) => vec![],
+        Err(e) => match e.downcast_ref::<CfgDiagnostic>() {
+            Some(diagnostic) => vec![diagnostic_json(diagnostic)],
+            None => vec![serde_json::json!({
+                "message": format!("{e:?}"),
+                "help": null,
+                "file_name": null,
+                "span": null,
+            })],
+        },
+    };
+    JsValue::from_str(&serde_json::Value::from(diagnostics).to_string())
+}
+
+fn diagnostic_json(diagnostic: &CfgDiagnostic) -> serde_json::Value {
+    serde_json::json!({
+        "message": diagnostic.message,
+        "help": diagnostic.help,
+        "file_name": diagnostic.file_name,
+        "span": diagnostic.span.map(|span| serde_json::json!({
+            "start": span.start,
+            "end": span.end,
+            "start_line": span.start_line,
+            "start_column": span.start_column,
+            "end_line": span.end_line,
+            "end_column": span.end_column,
+        })),
+    })
+}
+
 #[wasm_bindgen]
 pub fn simulate(cfg: &str, sim: &str) -> JsValue {
     JsValue::from_str(&match simulate_impl(cfg, sim) {
@@ -32,6 +71,15 @@ pub fn simulate(cfg: &str, sim: &str) -> JsValue {
     })
 }
 
+/// Like [`simulate`], but returns the simulation as JSON lines. Each input, tick, output key,
+/// mouse event, unicode output, layer change and custom action is a record with a `time` in
+/// milliseconds and a `type`. Errors in the configuration or simulation input are thrown.
+#[wasm_bindgen]
+pub fn simulate_json(cfg: &str, sim: &str) -> Result<JsValue, JsError> {
+    let k = run_simulation(cfg, sim).map_err(to_js_error)?;
+    Ok(JsValue::from_str(&k.kbd_out.json.records.join("\n")))
+}
+
 fn split_cfg_and_sim_files(original_cfg: &str) -> (String, FxHashMap<String, String>) {
     let mut cfg = String::new();
     let mut file_name = None;
@@ -73,6 +121,16 @@ fn split_cfg_and_sim_files(original_cfg: &str) -> (String, FxHashMap<String, Str
 }
 
 fn simulate_impl(cfg: &str, sim: &str) -> Result<String> {
+    let k = run_simulation(cfg, sim)?;
+    Ok(k.kbd_out
+        .outputs
+        .events
+        .join("\n")
+        .replace('↓', "↓(press)   ")
+        .replace('↑', "↑(release) "))
+}
+
+fn run_simulation(cfg: &str, sim: &str) -> Result<Kanata> {
     let (cfg, files) = split_cfg_and_sim_files(cfg);
     let mut k = Kanata::new_from_str(&cfg, files)?;
     let mut accumulated_ticks = 0;
@@ -86,13 +144,8 @@ fn simulate_impl(cfg: &str, sim: &str) -> Result<String> {
                         if ticks > 60000 {
                             bail!("line: {l}\nmax tick is 60000: {kind}:{val}")
                         }
-                        for _ in 0..ticks {
-                            if !k.can_block_update_idle_waiting(1) {
-                                k.tick_ms(1, &None)?;
-                            } else {
-                                k.kbd_out.tick();
-                            }
-                        }
+                        k.kbd_out.json.input_tick(ticks);
+                        tick(&mut k, ticks)?;
                         accumulated_ticks += ticks;
                         if accumulated_ticks > 3600000 {
                             bail!("You are trying to simulate over an hour's worth of time.\nAborting to avoid wasting your CPU cycles.")
@@ -101,6 +154,7 @@ fn simulate_impl(cfg: &str, sim: &str) -> Result<String> {
                     "press" | "↓" | "d" | "down" => {
                         let key_code =
                             str_to_oscode(val).ok_or_else(|| anyhow!("line: {l}\nunknown key in {kind}:{val}"))?;
+                        k.kbd_out.json.input(key_code, KeyValue::Press);
                         k.handle_input_event(&KeyEvent {
                             code: key_code,
                             value: KeyValue::Press,
@@ -109,6 +163,7 @@ fn simulate_impl(cfg: &str, sim: &str) -> Result<String> {
                     "release" | "↑" | "u" | "up" => {
                         let key_code =
                         str_to_oscode(val).ok_or_else(|| anyhow!("line: {l}\nunknown key in {kind}:{val}"))?;
+                        k.kbd_out.json.input(key_code, KeyValue::Release);
                         k.handle_input_event(&KeyEvent {
                             code: key_code,
                             value: KeyValue::Release,
@@ -117,6 +172,7 @@ fn simulate_impl(cfg: &str, sim: &str) -> Result<String> {
                     "repeat" | "⟳" | "r" => {
                         let key_code =
                         str_to_oscode(val).ok_or_else(|| anyhow!("line: {l}\nunknown key in {kind}:{val}"))?;
+                        k.kbd_out.json.input(key_code, KeyValue::Repeat);
                         k.handle_input_event(&KeyEvent {
                             code: key_code,
                             value: KeyValue::Repeat,
@@ -128,10 +184,107 @@ fn simulate_impl(cfg: &str, sim: &str) -> Result<String> {
             }
         }
     }
-    Ok(k.kbd_out
-        .outputs
-        .events
-        .join("\n")
-        .replace('↓', "↓(press)   ")
-        .replace('↑', "↑(release) "))
+    Ok(k)
+}
+
+/// Tick the given number of milliseconds, skipping the processing of ticks where kanata is idle.
+fn tick(k: &mut Kanata, ticks: u128) -> Result<()> {
+    for _ in 0..ticks {
+        if !k.can_block_update_idle_waiting(1) {
+            k.tick_ms(1, &None)?;
+        } else {
+            k.kbd_out.tick();
+        }
+    }
+    Ok(())
+}
+
+/// A simulator that keeps its state between calls, for interactive use such as a configuration
+/// playground. The methods that simulate input return the records produced since the previous
+/// call as a JSON array, with records in the same format as [`simulate_json`].
+#[wasm_bindgen]
+pub struct Simulator {
+    k: Kanata,
+    /// Number of JSON records already returned.
+    records_read: usize,
+}
+
+#[wasm_bindgen]
+impl Simulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(cfg: &str) -> Result<Simulator, JsError> {
+        let (cfg, files) = split_cfg_and_sim_files(cfg);
+        let k = Kanata::new_from_str(&cfg, files).map_err(to_js_error)?;
+        Ok(Self { k, records_read: 0 })
+    }
+
+    pub fn press(&mut self, key: &str) -> Result<String, JsError> {
+        self.key_event(key, KeyValue::Press)
+    }
+
+    pub fn release(&mut self, key: &str) -> Result<String, JsError> {
+        self.key_event(key, KeyValue::Release)
+    }
+
+    pub fn repeat(&mut self, key: &str) -> Result<String, JsError> {
+        self.key_event(key, KeyValue::Repeat)
+    }
+
+    /// Let the given number of milliseconds pass, at most 60000 per call.
+    pub fn tick(&mut self, ms: u32) -> Result<String, JsError> {
+        if ms > 60000 {
+            return Err(JsError::new(&format!("max tick is 60000: {ms}")));
+        }
+        self.k.kbd_out.json.input_tick(ms.into());
+        tick(&mut self.k, ms.into()).map_err(to_js_error)?;
+        Ok(self.new_records())
+    }
+
+    /// Name of the active layer.
+    #[wasm_bindgen(js_name = currentLayer)]
+    pub fn current_layer(&self) -> String {
+        self.k.layer_info[self.k.layout.b().current_layer()]
+            .name
+            .clone()
+    }
+
+    /// Names of the layers as a JSON array, in the order they are defined.
+    #[wasm_bindgen(js_name = layerNames)]
+    pub fn layer_names(&self) -> String {
+        let names: Vec<&str> = self.k.layer_info.iter().map(|l| l.name.as_str()).collect();
+        serde_json::to_string(&names).expect("strings serialize")
+    }
+
+    /// Names of the `defsrc` keys as a JSON array, in the order they are written.
+    pub fn defsrc(&self) -> String {
+        let names: Vec<String> = self
+            .k
+            .defsrc
+            .iter()
+            .map(|osc| match oscode_to_str(*osc) {
+                Some(name) => name.to_string(),
+                None => format!("{osc:?}"),
+            })
+            .collect();
+        serde_json::to_string(&names).expect("strings serialize")
+    }
+
+    fn key_event(&mut self, key: &str, value: KeyValue) -> Result<String, JsError> {
+        let code = str_to_oscode(key).ok_or_else(|| JsError::new(&format!("unknown key: {key}")))?;
+        self.k.kbd_out.json.input(code, value);
+        self.k
+            .handle_input_event(&KeyEvent::new(code, value))
+            .map_err(to_js_error)?;
+        Ok(self.new_records())
+    }
+
+    fn new_records(&mut self) -> String {
+        let records = &self.k.kbd_out.json.records[self.records_read..];
+        self.records_read = self.k.kbd_out.json.records.len();
+        format!("[{}]", records.join(","))
+    }
+}
+
+fn to_js_error(e: anyhow::Error) -> JsError {
+    JsError::new(&format!("{e:?}"))
 }